@@ -0,0 +1,91 @@
+use crate::metadata::ResultMetadata;
+use crate::Ranker;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// Rankers for this many saved queries are kept between calls. When there are
+// more, they are all dropped and built again as needed.
+const MAX_SAVED_QUERIES: usize = 1024;
+
+thread_local! {
+    // An empty ranker for each saved query, so the query is only rewritten
+    // and compiled the first time it is checked
+    static SAVED_QUERIES: RefCell<HashMap<String, Ranker>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CandidateResult {
+    pub url: String,
+    pub title: String,
+    pub extract: String,
+    pub metadata: ResultMetadata,
+}
+
+// Whether the result scores at least `threshold` for the query on its own,
+// the score `rank` would give it. False if the URL filter drops it.
+pub fn result_matches(query: &str, result: &CandidateResult, threshold: f32) -> bool {
+    SAVED_QUERIES.with(|saved_queries| {
+        let mut saved_queries = saved_queries.borrow_mut();
+        if !saved_queries.contains_key(query) && saved_queries.len() >= MAX_SAVED_QUERIES {
+            saved_queries.clear();
+        }
+        let ranker = saved_queries
+            .entry(query.to_string())
+            .or_insert_with(|| Ranker::new(query));
+        ranker
+            .score_alone(result)
+            .is_some_and(|score| score >= threshold)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_queries_score_like_the_ranker() {
+        let result = CandidateResult {
+            url: "https://a.com/".to_string(),
+            title: "The Rust book".to_string(),
+            ..CandidateResult::default()
+        };
+        let mut ranker = Ranker::new("rust book");
+        ranker.add_search_result(&result.url, &result.title, &result.extract);
+        let score = ranker.score_at(0).score;
+
+        assert!(result_matches("rust book", &result, score));
+        assert!(!result_matches("rust book", &result, score * 1.01));
+        assert!(!result_matches("cooking", &result, 0.01));
+
+        let blocked = CandidateResult {
+            url: "javascript:alert(1)".to_string(),
+            ..result
+        };
+        assert!(!result_matches("rust book", &blocked, 0.0));
+    }
+
+    #[test]
+    fn earlier_results_do_not_affect_matching() {
+        // Only the words of the first result split the query into "rust book"
+        let segmenting = CandidateResult {
+            url: "https://a.com/".to_string(),
+            title: "The Rust book".to_string(),
+            ..CandidateResult::default()
+        };
+        let result = CandidateResult {
+            url: "https://b.com/".to_string(),
+            title: "A book".to_string(),
+            ..CandidateResult::default()
+        };
+
+        let mut ranker = Ranker::new("rustbook");
+        let alone = ranker.score_alone(&result);
+        assert!(ranker.score_alone(&segmenting).unwrap() > 0.0);
+
+        let mut ranker = Ranker::new("rustbook");
+        ranker.score_alone(&segmenting);
+        assert_eq!(ranker.score_alone(&result), alone);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::encoding::Reader;
+use crate::urls::registrable_domain;
+use std::collections::HashMap;
+
+// Bump when the encoding changes, so old tables can still be read
+pub const FORMAT_VERSION: u8 = 1;
+
+// How much the web links to each domain, from the link graph of the mwmbl
+// crawl, e.g. the PageRank of the domain or the number of domains linking to
+// it. Unlike `DomainPopularity`, this reflects the pages mwmbl indexes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DomainAuthority {
+    // Scaled so the highest scoring domain has 1.0
+    scores: HashMap<String, f32>,
+}
+
+impl DomainAuthority {
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    // From 1.0 for the domain with the highest score down to 0.0, looking up
+    // the host or failing that its registrable domain. 0.0 for domains that
+    // aren't listed.
+    pub fn authority(&self, host: &str) -> f32 {
+        self.scores
+            .get(host)
+            .or_else(|| self.scores.get(registrable_domain(host)))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    // Encode as a version byte followed by the number of domains and each
+    // domain with its score as a little endian f64. Tables are exported by
+    // the crawler, so this is only for tests.
+    #[cfg(test)]
+    pub fn to_bytes(scores: &[(&str, f64)]) -> Vec<u8> {
+        use crate::encoding::{write_f64, write_string, write_varint};
+        let mut bytes = vec![FORMAT_VERSION];
+        write_varint(&mut bytes, scores.len());
+        for (domain, score) in scores {
+            write_string(&mut bytes, domain);
+            write_f64(&mut bytes, *score);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<DomainAuthority, String> {
+        let mut reader = Reader::new(bytes, "domain authority");
+        let version = reader.read_byte()?;
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported domain authority format version {}",
+                version
+            ));
+        }
+        let mut scores = HashMap::new();
+        for _ in 0..reader.read_varint()? {
+            let domain = reader.read_string()?.to_lowercase();
+            let score = reader.read_f64()?;
+            if !score.is_finite() || score < 0.0 {
+                return Err(format!("Invalid authority score for {}: {}", domain, score));
+            }
+            scores.insert(domain, score);
+        }
+        reader.finish()?;
+        let max_score = scores.values().copied().fold(0.0, f64::max);
+        let scores = scores
+            .into_iter()
+            .map(|(domain, score)| {
+                let scaled = if max_score > 0.0 {
+                    score / max_score
+                } else {
+                    0.0
+                };
+                (domain, scaled as f32)
+            })
+            .collect();
+        Ok(DomainAuthority { scores })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authority_round_trip() {
+        let bytes = DomainAuthority::to_bytes(&[("wikipedia.org", 40.0), ("Example.com", 10.0)]);
+        let authority = DomainAuthority::from_bytes(&bytes).unwrap();
+        assert_eq!(authority.authority("wikipedia.org"), 1.0);
+        assert_eq!(authority.authority("en.wikipedia.org"), 1.0);
+        assert_eq!(authority.authority("example.com"), 0.25);
+        assert_eq!(authority.authority("unknown.org"), 0.0);
+
+        assert!(DomainAuthority::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let negative = DomainAuthority::to_bytes(&[("example.com", -1.0)]);
+        assert!(DomainAuthority::from_bytes(&negative).is_err());
+    }
+}
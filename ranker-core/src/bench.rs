@@ -0,0 +1,156 @@
+use crate::utils::precise_now_ms;
+use crate::Ranker;
+use serde::Serialize;
+
+// Words that pad out the synthetic titles and extracts around the query terms
+const FILLER_WORDS: [&str; 16] = [
+    "guide", "review", "best", "news", "online", "free", "home", "learn", "price", "video",
+    "official", "tutorial", "compare", "local", "help", "about",
+];
+
+// Timings in milliseconds, with percentiles by nearest rank
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Timings {
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Timings {
+    pub fn new(samples: &[f64]) -> Timings {
+        if samples.is_empty() {
+            return Timings::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let rank = (p * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Timings {
+            min: sorted[0],
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub n_results: usize,
+    pub iterations: usize,
+    // Creating the ranker and adding the results
+    pub add: Timings,
+    // Ranking the added results
+    pub rank: Timings,
+    // Both of the above
+    pub total: Timings,
+}
+
+// A simple linear congruential generator, so every run ranks the same results
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+// Results of filler words with some of the query terms mixed in, like the
+// mix of good and poor matches the backend returns
+pub fn synthetic_results(query: &str, n_results: usize) -> Vec<(String, String, String)> {
+    let terms = query.split_whitespace().collect::<Vec<&str>>();
+    let mut lcg = Lcg(n_results as u64);
+    let mut text = |num_words: usize| {
+        (0..num_words)
+            .map(|_| match terms.len() {
+                len if len > 0 && lcg.next(4) == 0 => terms[lcg.next(len)],
+                _ => FILLER_WORDS[lcg.next(FILLER_WORDS.len())],
+            })
+            .collect::<Vec<&str>>()
+            .join(" ")
+    };
+    (0..n_results)
+        .map(|i| {
+            let title = text(6);
+            let extract = text(30);
+            (
+                format!("https://site{}.example.com/page/{}", i % 50, i),
+                title,
+                extract,
+            )
+        })
+        .collect()
+}
+
+// Time adding and ranking `n_results` synthetic results for the query,
+// `iterations` times, with a new ranker each time so nothing is cached
+pub fn run_bench(query: &str, n_results: usize, iterations: usize) -> BenchReport {
+    let results = synthetic_results(query, n_results);
+    let mut add = Vec::with_capacity(iterations);
+    let mut rank = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = precise_now_ms();
+        let mut ranker = Ranker::new(query);
+        for (url, title, extract) in &results {
+            ranker.add_search_result(url, title, extract);
+        }
+        let added = precise_now_ms();
+        ranker.ranked_results();
+        let finished = precise_now_ms();
+        add.push(added - start);
+        rank.push(finished - added);
+    }
+    let total = add
+        .iter()
+        .zip(&rank)
+        .map(|(add, rank)| add + rank)
+        .collect::<Vec<f64>>();
+    BenchReport {
+        n_results,
+        iterations,
+        add: Timings::new(&add),
+        rank: Timings::new(&rank),
+        total: Timings::new(&total),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let samples = (1..=100).map(f64::from).collect::<Vec<f64>>();
+        let timings = Timings::new(&samples);
+        assert_eq!(timings.min, 1.0);
+        assert_eq!(timings.p50, 50.0);
+        assert_eq!(timings.p90, 90.0);
+        assert_eq!(timings.p99, 99.0);
+        assert_eq!(timings.max, 100.0);
+        assert_eq!(timings.mean, 50.5);
+        assert_eq!(Timings::new(&[]), Timings::default());
+    }
+
+    #[test]
+    fn bench_ranks_synthetic_results() {
+        let results = synthetic_results("rust book", 20);
+        assert_eq!(results, synthetic_results("rust book", 20));
+        assert!(results.iter().any(|(_, title, _)| title.contains("rust")));
+
+        let report = run_bench("rust book", 20, 3);
+        assert_eq!(report.iterations, 3);
+        assert!(report.total.min >= 0.0);
+        assert!(report.total.max >= report.total.p50);
+    }
+}
@@ -0,0 +1,53 @@
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: ranker-cli replay <bundle.json> \
+    [--against-config <config.json>] [--against-model <model.json>]
+       ranker-cli sample <log.jsonl>";
+
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))
+}
+
+// Re-rank the results of an exported SERP bundle and print how they moved
+fn replay(args: &[String]) -> Result<String, String> {
+    let mut bundle = None;
+    let mut config = None;
+    let mut model = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--against-config" => config = Some(read_file(args.next().ok_or(USAGE)?)?),
+            "--against-model" => model = Some(read_file(args.next().ok_or(USAGE)?)?),
+            path if bundle.is_none() && !path.starts_with("--") => bundle = Some(read_file(path)?),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let bundle = bundle.ok_or(USAGE)?;
+    ranker_core::replay_serp(&bundle, config.as_deref(), model.as_deref())
+}
+
+// Turn a log of SERPs with clicks into weighted pairwise training examples
+fn sample(args: &[String]) -> Result<String, String> {
+    match args {
+        [log] => ranker_core::sample_training_data(&read_file(log)?),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.first().map(String::as_str) {
+        Some("replay") => replay(&args[1..]),
+        Some("sample") => sample(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(report) => println!("{}", report),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
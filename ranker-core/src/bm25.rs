@@ -0,0 +1,123 @@
+use crate::positions::TokenPositions;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bm25Params {
+    // How quickly repeated terms stop adding to the score
+    pub k1: f32,
+    // How much longer fields are penalised, from 0.0 (not at all) to 1.0
+    pub b: f32,
+}
+
+// BM25 parameters for each field
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bm25Config {
+    pub title: Bm25Params,
+    pub extract: Bm25Params,
+    pub domain: Bm25Params,
+    pub path: Bm25Params,
+}
+
+impl Default for Bm25Config {
+    fn default() -> Self {
+        Bm25Config {
+            title: Bm25Params { k1: 1.2, b: 0.5 },
+            extract: Bm25Params { k1: 1.2, b: 0.75 },
+            domain: Bm25Params { k1: 1.2, b: 0.3 },
+            path: Bm25Params { k1: 1.2, b: 0.5 },
+        }
+    }
+}
+
+// The number of results containing each query term in one field, and the
+// average length of the field, over every search result
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldStats {
+    num_results: usize,
+    average_length: f32,
+    // Sorted by term, so scores are summed in the same order every time
+    result_frequencies: Vec<(String, usize)>,
+}
+
+impl FieldStats {
+    pub fn new<'a>(
+        fields: impl Iterator<Item = &'a TokenPositions>,
+        terms: &[String],
+    ) -> FieldStats {
+        let unique_terms = terms.iter().collect::<HashSet<&String>>();
+        let mut result_frequencies = unique_terms
+            .into_iter()
+            .map(|term| (term.clone(), 0))
+            .collect::<Vec<(String, usize)>>();
+        result_frequencies.sort();
+        let mut num_results = 0;
+        let mut total_length = 0;
+        for field in fields {
+            num_results += 1;
+            total_length += field.num_tokens() as usize;
+            for (term, frequency) in result_frequencies.iter_mut() {
+                if !field.positions(term).is_empty() {
+                    *frequency += 1;
+                }
+            }
+        }
+        FieldStats {
+            num_results,
+            average_length: total_length as f32 / num_results.max(1) as f32,
+            result_frequencies,
+        }
+    }
+
+    // The BM25 score of the field for the query terms the stats were built for
+    pub fn score(&self, field: &TokenPositions, params: Bm25Params) -> f32 {
+        let length_ratio = if self.average_length > 0.0 {
+            field.num_tokens() as f32 / self.average_length
+        } else {
+            1.0
+        };
+        let length_norm = 1.0 - params.b + params.b * length_ratio;
+        self.result_frequencies
+            .iter()
+            .map(|(term, frequency)| {
+                let term_frequency = field.positions(term).len() as f32;
+                let idf = (1.0
+                    + (self.num_results as f32 - *frequency as f32 + 0.5)
+                        / (*frequency as f32 + 0.5))
+                    .ln();
+                idf * term_frequency * (params.k1 + 1.0)
+                    / (term_frequency + params.k1 * length_norm)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_score() {
+        let fields = [
+            TokenPositions::new("rust programming language"),
+            TokenPositions::new("rust rust rust"),
+            TokenPositions::new("a long extract about programming in rust and other things"),
+            TokenPositions::new("python"),
+        ];
+        let terms = vec!["rust".to_string(), "programming".to_string()];
+        let stats = FieldStats::new(fields.iter(), &terms);
+        let params = Bm25Params { k1: 1.2, b: 0.75 };
+        let scores = fields
+            .iter()
+            .map(|field| stats.score(field, params))
+            .collect::<Vec<f32>>();
+        assert!(scores[0] > scores[1]);
+        assert!(scores[0] > scores[2]);
+        assert_eq!(scores[3], 0.0);
+
+        // Repeating a term saturates rather than growing without bound
+        let repeated = TokenPositions::new(&"rust ".repeat(100));
+        assert!(stats.score(&repeated, params) < 3.0 * stats.score(&fields[1], params));
+    }
+}
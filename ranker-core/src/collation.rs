@@ -0,0 +1,67 @@
+use std::cmp::Ordering;
+
+// Locales written in the Arabic script, which sort it before the Latin script
+#[cfg(feature = "collation")]
+const ARABIC_SCRIPT_LANGUAGES: [&str; 4] = ["ar", "fa", "ps", "ur"];
+
+// Compares titles in the order people expect to find them listed. With the
+// `collation` feature this is the Unicode Collation Algorithm with the CLDR
+// root order, so accented letters sort next to their base letter and case and
+// punctuation only break ties. Without it, titles are compared by their
+// lowercase code points, which is only right for ASCII.
+pub struct TitleCollator {
+    #[cfg(feature = "collation")]
+    collator: feruca::Collator,
+}
+
+impl TitleCollator {
+    // A collator for a locale such as "ar-EG", which may be empty
+    #[cfg(feature = "collation")]
+    pub fn new(locale: &str) -> TitleCollator {
+        let language = locale.split(['-', '_']).next().unwrap_or("");
+        let tailoring = if ARABIC_SCRIPT_LANGUAGES.contains(&language.to_lowercase().as_str()) {
+            feruca::Tailoring::Cldr(feruca::Locale::ArabicScript)
+        } else {
+            feruca::Tailoring::default()
+        };
+        TitleCollator {
+            collator: feruca::Collator::new(tailoring, true, true),
+        }
+    }
+
+    #[cfg(not(feature = "collation"))]
+    pub fn new(_locale: &str) -> TitleCollator {
+        TitleCollator {}
+    }
+
+    #[cfg(feature = "collation")]
+    pub fn compare(&mut self, a: &str, b: &str) -> Ordering {
+        self.collator.collate(a, b)
+    }
+
+    #[cfg(not(feature = "collation"))]
+    pub fn compare(&mut self, a: &str, b: &str) -> Ordering {
+        a.to_lowercase()
+            .cmp(&b.to_lowercase())
+            .then_with(|| a.cmp(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_are_collated() {
+        let mut collator = TitleCollator::new("en-GB");
+        let mut titles = vec!["banana", "Cherry", "apple"];
+        titles.sort_by(|a, b| collator.compare(a, b));
+        assert_eq!(titles, vec!["apple", "banana", "Cherry"]);
+
+        if cfg!(feature = "collation") {
+            let mut titles = vec!["Zebra", "Émile", "Ernie"];
+            titles.sort_by(|a, b| collator.compare(a, b));
+            assert_eq!(titles, vec!["Émile", "Ernie", "Zebra"]);
+        }
+    }
+}
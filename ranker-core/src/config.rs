@@ -0,0 +1,499 @@
+use crate::bm25::Bm25Config;
+use crate::filter::UrlFilter;
+use crate::model::Monotonicity;
+use crate::rewrite::RewriteConfig;
+use crate::semantic::SimilarityMetric;
+use crate::slotting::SlotRule;
+use crate::urls;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const PROFILE_NAMES: [&str; 4] = ["default", "navigational", "research", "shopping"];
+
+// The title, extract, domain and path weights from before weights were configurable
+const LEGACY_FIELD_WEIGHTS: [f32; 4] = [4.0, 1.0, 4.0, 2.0];
+const LEGACY_MATCH_EXPONENT: f64 = 2.0;
+const LEGACY_LENGTH_PENALTY: f32 = 0.04;
+
+// Flags that pin ranking behavior, so the frontend can keep the old behavior
+// during a rollout while new ranking changes ship in the same binary. Combine
+// them into the `flags` bitset of the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BehaviorFlag {
+    // Score with the original fixed field weights and skip every pass added
+    // on top of the original formula, such as profiles and diversity
+    LegacyScoring = 1,
+    // Score field matches with BM25, using the `bm25` parameters, instead of
+    // the exponential match score, which saturates for longer queries
+    Bm25Scoring = 2,
+}
+
+// A range that a feature value is clamped to before scoring. A missing bound
+// leaves that side unbounded. JSON has no infinity, so unbounded sides are
+// left out rather than written as infinite.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clamp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f32>,
+}
+
+impl Clamp {
+    // A clamp to [min, max], where infinite bounds leave that side unbounded
+    pub fn new(min: f32, max: f32) -> Result<Clamp, String> {
+        if min.is_nan()
+            || max.is_nan()
+            || min > max
+            || min == f32::INFINITY
+            || max == f32::NEG_INFINITY
+        {
+            return Err(format!("Invalid clamp range [{}, {}]", min, max));
+        }
+        Ok(Clamp {
+            min: Some(min).filter(|min| min.is_finite()),
+            max: Some(max).filter(|max| max.is_finite()),
+        })
+    }
+
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankerConfig {
+    // One of `PROFILE_NAMES` to rank with in place of the profile picked from
+    // the query intent. Its weights replace the config's when the config is
+    // set.
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub title_weight: f32,
+    pub extract_weight: f32,
+    pub domain_weight: f32,
+    pub path_weight: f32,
+    // Each field's match score is match_exponent ^ (matched length - total
+    // query term length) / position of the last match, so higher values
+    // favour matching every term more strongly
+    pub match_exponent: f64,
+    // Scores are multiplied by exp(-length_penalty * URL length), favouring
+    // shorter URLs
+    pub length_penalty: f32,
+    // Scores are multiplied by exp(-path_depth_penalty * path segments),
+    // favouring pages near the root of their site over deeply nested ones
+    pub path_depth_penalty: f32,
+    // Results whose URL isn't https are multiplied by 1 - https_weight, so
+    // https wins when scores are otherwise close
+    pub https_weight: f32,
+    // Results whose URL has tracking parameters or a session ID are
+    // multiplied by 1 - tracking_url_penalty
+    pub tracking_url_penalty: f32,
+    // Subtracted from the final score in proportion to how spammy the URL
+    // looks, see `urls::url_spam_score`, without taking it below zero
+    pub url_spam_penalty: f32,
+    // Top level domains that count as a sign of a spammy URL, without the dot
+    pub spam_tlds: Vec<String>,
+    // Results in another language than the query, or failing that the
+    // user's locale, are multiplied by 1 - language_mismatch_penalty
+    pub language_mismatch_penalty: f32,
+    // Give part of the extract weight to the title for results with no or a
+    // short extract, in proportion to how much shorter it is than a typical
+    // extract, so that missing data isn't treated as a non-match
+    pub fill_missing_extracts: bool,
+    // Score the domain and path of the URL, penalise long URLs and spread out
+    // results from the same host. Turn off to rank items that aren't web
+    // pages, such as notes or files, whose "URL" is only an identifier. The
+    // domain and path weights are then shared between the title and extract.
+    pub score_urls: bool,
+    // Report how uncertain each score is when an ensemble model is loaded,
+    // as `score_stds` in the ranking output
+    pub score_confidence: bool,
+    // Weight of the similarity between query and result embeddings, when provided
+    pub semantic_weight: f32,
+    // Added for each of the title and extract containing a number that fits
+    // a number or range in the query, e.g. "$450" for "laptops under 500"
+    pub numeric_weight: f32,
+    // Results are multiplied by 1 + proximity_weight * proximity, where the
+    // proximity is from 0.0 to 1.0 for the closer of the title and extract
+    // matches, so that terms appearing together beat scattered terms
+    pub proximity_weight: f32,
+    // Results are multiplied by 1 + in_order_weight * in_order, where in_order
+    // is from 0.0 to 1.0 for the title or extract with more of the query
+    // terms in the order of the query
+    pub in_order_weight: f32,
+    // Results are multiplied by 1 + clustering_weight * clustering, where the
+    // clustering is from 0.0 to 1.0 for how many of the query terms in the
+    // extract appear in one sentence rather than spread across several
+    pub clustering_weight: f32,
+    // Results are multiplied by 1 + phrase_weight for each of the title and
+    // extract containing the whole query verbatim
+    pub phrase_weight: f32,
+    // Results are multiplied by 1 + all_terms_weight when every query term
+    // matches in the title, so that a title with all of them isn't beaten by
+    // one with a single term near the start
+    pub all_terms_weight: f32,
+    // Results are multiplied by 1 + starts_with_weight when the title starts
+    // with the query, or by half that bonus when it starts with the first term
+    pub starts_with_weight: f32,
+    // Results are multiplied by 1 + exact_domain_weight when their registrable
+    // domain spells out the query, e.g. github.com for "github", which should
+    // almost always put them first
+    pub exact_domain_weight: f32,
+    // A field's match score is multiplied by (1 - fuzzy_discount) for each
+    // term matched by a near miss rather than exactly
+    pub fuzzy_discount: f32,
+    // And by (1 - synonym_discount) for each term only in the query as a
+    // synonym of another
+    pub synonym_discount: f32,
+    // Factors results are multiplied by when the crawler flags them as
+    // noindex or as a suspected soft 404
+    pub noindex_factor: f32,
+    pub soft404_factor: f32,
+    // Multiply paywalled results by paywall_factor
+    pub prefer_open_access: bool,
+    pub paywall_factor: f32,
+    // Results matching less than this proportion of the unique query terms
+    // across all fields are multiplied by low_coverage_factor, or dropped
+    // from the output if drop_low_coverage is set
+    pub min_term_coverage: f32,
+    pub low_coverage_factor: f32,
+    pub drop_low_coverage: bool,
+    // Results are multiplied by 1 + cross_field_weight for each unique query
+    // term they match beyond the first, in any field, so that matching every
+    // term across fields beats matching one term in every field
+    pub cross_field_weight: f32,
+    // Return the results left out by the URL filter's blocklist, schemes or
+    // safe search, or by drop_low_coverage, in the `filtered` section of the
+    // rank output with the reason, so the user can choose to see them anyway
+    pub return_filtered: bool,
+    pub similarity_metric: SimilarityMetric,
+    // Fall back to hashed bag-of-words embeddings of the query and of each
+    // result's title and extract when no embeddings have been provided
+    pub hashed_embeddings: bool,
+    // Each result from a domain that already appeared higher up has its score
+    // multiplied by (1 - diversity), so 0.0 disables the diversity pass.
+    pub diversity: f32,
+    // A result only moves above one that was ranked above it last time if
+    // its score is more than (1 + hysteresis_margin) times higher, so the
+    // order doesn't flicker as results stream in. 0.0 disables this.
+    pub hysteresis_margin: f32,
+    // Constraints on the top of the ranking, such as at most one video in
+    // the top 5, applied in order after everything else
+    pub slot_rules: Vec<SlotRule>,
+    // Blend the ranking with the order the backend returned results in, by a
+    // weighted Borda count: each result gets (1 - backend_rank_weight) points
+    // per result below it in the ranking, plus backend_rank_weight per result
+    // below it in the backend's order. 0.0 keeps the ranking as it is, and
+    // 1.0 keeps the backend's order.
+    pub backend_rank_weight: f32,
+    // Results are multiplied by exp(-host_reputation_weight * host_penalty),
+    // demoting hosts the user keeps skipping or bouncing from
+    pub host_reputation_weight: f32,
+    // Results are multiplied by 1 + popularity_weight * domain_popularity
+    // when a domain popularity table is loaded
+    pub popularity_weight: f32,
+    // Results are multiplied by 1 + authority_weight * domain_authority when
+    // a domain authority table from the crawl is loaded
+    pub authority_weight: f32,
+    // Results whose title matches the query are multiplied by
+    // 1 - clickbait_weight * (1 - title_extract_overlap), demoting titles
+    // unrelated to their extract. 0.0 disables the demotion.
+    pub clickbait_weight: f32,
+    // Drop language and mobile variants of a page ranked higher up, e.g.
+    // en.m.wikipedia.org when en.wikipedia.org has the same title and extract
+    pub dedup_locale_variants: bool,
+    // The user's locale, e.g. "en-GB". Of duplicate language variants, the
+    // one in this language is kept. Empty if unknown.
+    pub locale: String,
+    // Sort results with equal scores by title for the locale, rather than
+    // keeping the order they were added in, which suits browsing queries
+    // where many scores tie. Build with the `collation` feature for proper
+    // Unicode ordering.
+    pub sort_ties_by_title: bool,
+    // Results with spammy title formatting are multiplied by
+    // 1 - title_spam_weight * spam score, where the score is from 0.0 to 1.0
+    pub title_spam_weight: f32,
+    // Results with junky text, such as an empty title or an extract of
+    // numbers and punctuation, are multiplied by
+    // 1 - junk_text_weight * junk score, where the score is from 0.0 to 1.0
+    pub junk_text_weight: f32,
+    // Weight given to the recency of results with a date. They are multiplied
+    // by 1 + freshness * recency, where recency halves every year from 1.0.
+    pub freshness: f32,
+    // The most the title, extract, domain or path can add to the score, by
+    // field name, so that e.g. a stuffed extract can't outweigh a clean title
+    // and domain match. Fields without a cap are unlimited.
+    pub field_caps: BTreeMap<String, f32>,
+    // Ranges to clamp features to by feature name, so that no single feature
+    // can dominate the score
+    pub feature_clamps: BTreeMap<String, Clamp>,
+    // Loaded models must respond to these features in the given direction,
+    // so that e.g. a better title match can never lower the score
+    pub monotonic_features: BTreeMap<String, Monotonicity>,
+    // Reject invalid search results at ingestion rather than mangling them
+    pub strict_validation: bool,
+    // Which results are dropped at ingestion
+    pub url_filter: UrlFilter,
+    // How the query is rewritten into the terms results are matched against
+    pub rewrite: RewriteConfig,
+    // Per field parameters for `BehaviorFlag::Bm25Scoring`
+    pub bm25: Bm25Config,
+    pub flags: u32,
+}
+
+impl Default for RankerConfig {
+    fn default() -> Self {
+        RankerConfig {
+            profile: None,
+            title_weight: 4.0,
+            extract_weight: 1.0,
+            domain_weight: 4.0,
+            path_weight: 2.0,
+            match_exponent: LEGACY_MATCH_EXPONENT,
+            length_penalty: LEGACY_LENGTH_PENALTY,
+            path_depth_penalty: 0.05,
+            https_weight: 0.05,
+            tracking_url_penalty: 0.3,
+            url_spam_penalty: 0.1,
+            spam_tlds: urls::DEFAULT_SPAM_TLDS.map(String::from).to_vec(),
+            language_mismatch_penalty: 0.5,
+            fill_missing_extracts: true,
+            score_urls: true,
+            score_confidence: false,
+            semantic_weight: 1.0,
+            numeric_weight: 0.5,
+            proximity_weight: 0.5,
+            in_order_weight: 0.5,
+            clustering_weight: 0.5,
+            phrase_weight: 1.0,
+            all_terms_weight: 1.0,
+            starts_with_weight: 1.0,
+            exact_domain_weight: 10.0,
+            fuzzy_discount: 0.3,
+            synonym_discount: 0.2,
+            noindex_factor: 0.2,
+            soft404_factor: 0.1,
+            prefer_open_access: false,
+            paywall_factor: 0.5,
+            min_term_coverage: 0.5,
+            low_coverage_factor: 0.1,
+            drop_low_coverage: false,
+            cross_field_weight: 0.5,
+            return_filtered: false,
+            similarity_metric: SimilarityMetric::Cosine,
+            hashed_embeddings: false,
+            diversity: 0.0,
+            hysteresis_margin: 0.0,
+            slot_rules: Vec::new(),
+            backend_rank_weight: 0.0,
+            host_reputation_weight: 0.5,
+            popularity_weight: 0.5,
+            authority_weight: 0.5,
+            clickbait_weight: 0.3,
+            title_spam_weight: 0.5,
+            junk_text_weight: 0.5,
+            dedup_locale_variants: true,
+            locale: String::new(),
+            sort_ties_by_title: false,
+            freshness: 0.0,
+            field_caps: BTreeMap::new(),
+            feature_clamps: BTreeMap::new(),
+            monotonic_features: [
+                "title_score",
+                "title_num_terms",
+                "extract_score",
+                "domain_score",
+                "path_score",
+            ]
+            .iter()
+            .map(|name| (name.to_string(), Monotonicity::Increasing))
+            .collect(),
+            strict_validation: false,
+            url_filter: UrlFilter::default(),
+            rewrite: RewriteConfig::default(),
+            bm25: Bm25Config::default(),
+            flags: 0,
+        }
+    }
+}
+
+impl RankerConfig {
+    // Look up one of the named weight profiles in `PROFILE_NAMES`
+    pub fn from_profile(name: &str) -> Option<RankerConfig> {
+        let default = RankerConfig::default();
+        match name {
+            "default" => Some(default),
+            "navigational" => Some(RankerConfig {
+                title_weight: 3.0,
+                extract_weight: 0.5,
+                domain_weight: 8.0,
+                path_weight: 1.0,
+                diversity: 0.0,
+                ..default
+            }),
+            "research" => Some(RankerConfig {
+                title_weight: 3.0,
+                extract_weight: 2.0,
+                domain_weight: 2.0,
+                path_weight: 1.0,
+                diversity: 0.3,
+                ..default
+            }),
+            "shopping" => Some(RankerConfig {
+                title_weight: 4.0,
+                extract_weight: 1.0,
+                domain_weight: 3.0,
+                path_weight: 2.0,
+                diversity: 0.5,
+                freshness: 0.3,
+                ..default
+            }),
+            _ => None,
+        }
+    }
+
+    // Replace the weights with those of the pinned profile, if there is one,
+    // and return the name of the profile to report, which is "custom" if
+    // none is pinned
+    pub fn apply_pinned_profile(self) -> Result<(RankerConfig, String), String> {
+        let name = match &self.profile {
+            Some(name) => name.clone(),
+            None => return Ok((self, "custom".to_string())),
+        };
+        let profile = RankerConfig::from_profile(&name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        let config = RankerConfig {
+            title_weight: profile.title_weight,
+            extract_weight: profile.extract_weight,
+            domain_weight: profile.domain_weight,
+            path_weight: profile.path_weight,
+            diversity: profile.diversity,
+            freshness: profile.freshness,
+            ..self
+        };
+        Ok((config, name))
+    }
+
+    pub fn has_flag(&self, flag: BehaviorFlag) -> bool {
+        self.flags & flag as u32 != 0
+    }
+
+    // The title, extract, domain and path weights to score with
+    pub fn field_weights(&self) -> [f32; 4] {
+        let weights = if self.has_flag(BehaviorFlag::LegacyScoring) {
+            LEGACY_FIELD_WEIGHTS
+        } else {
+            [
+                self.title_weight,
+                self.extract_weight,
+                self.domain_weight,
+                self.path_weight,
+            ]
+        };
+        let [title, extract, _, _] = weights;
+        if self.score_urls || title + extract <= 0.0 {
+            return weights;
+        }
+        // Keep the total weight the same so scores stay on the same scale
+        let scale = weights.iter().sum::<f32>() / (title + extract);
+        [title * scale, extract * scale, 0.0, 0.0]
+    }
+
+    // The weighted score of a field, limited to its cap
+    pub fn cap_field(&self, field: &str, value: f32) -> f32 {
+        match self.field_caps.get(field) {
+            Some(&cap) if !self.has_flag(BehaviorFlag::LegacyScoring) => value.min(cap),
+            _ => value,
+        }
+    }
+
+    pub fn match_exponent(&self) -> f64 {
+        if self.has_flag(BehaviorFlag::LegacyScoring) {
+            return LEGACY_MATCH_EXPONENT;
+        }
+        self.match_exponent
+    }
+
+    // The coefficient of the URL length penalty to score with
+    pub fn length_penalty(&self) -> f32 {
+        if !self.score_urls {
+            return 0.0;
+        }
+        if self.has_flag(BehaviorFlag::LegacyScoring) {
+            return LEGACY_LENGTH_PENALTY;
+        }
+        self.length_penalty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_profile_names_resolve() {
+        for name in PROFILE_NAMES.iter() {
+            assert!(RankerConfig::from_profile(name).is_some(), "{}", name);
+        }
+        assert_eq!(
+            RankerConfig::from_profile("default"),
+            Some(RankerConfig::default())
+        );
+        assert_eq!(RankerConfig::from_profile("unknown"), None);
+    }
+
+    #[test]
+    fn test_clamp() {
+        let clamp = Clamp::new(0.0, 1.0).unwrap();
+        assert_eq!(clamp.apply(2.0), 1.0);
+        assert_eq!(clamp.apply(-1.0), 0.0);
+        assert_eq!(clamp.apply(0.5), 0.5);
+
+        let above = Clamp::new(0.0, f32::INFINITY).unwrap();
+        assert_eq!(above.max, None);
+        assert_eq!(above.apply(1e30), 1e30);
+        assert_eq!(serde_json::to_string(&above).unwrap(), r#"{"min":0.0}"#);
+        assert!(Clamp::new(1.0, 0.0).is_err());
+        assert!(Clamp::new(f32::NAN, 1.0).is_err());
+        assert!(Clamp::new(f32::INFINITY, f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn legacy_scoring_pins_field_weights() {
+        let mut config = RankerConfig::from_profile("navigational").unwrap();
+        assert_eq!(config.field_weights(), [3.0, 0.5, 8.0, 1.0]);
+        config.flags |= BehaviorFlag::LegacyScoring as u32;
+        assert_eq!(config.field_weights(), LEGACY_FIELD_WEIGHTS);
+    }
+
+    #[test]
+    fn pinned_profiles_replace_the_weights() {
+        let config: RankerConfig =
+            serde_json::from_str(r#"{"profile": "research", "title_weight": 9}"#).unwrap();
+        let (config, name) = config.apply_pinned_profile().unwrap();
+        assert_eq!(name, "research");
+        assert_eq!(config.field_weights(), [3.0, 2.0, 2.0, 1.0]);
+        assert_eq!(config.diversity, 0.3);
+
+        let (_, name) = RankerConfig::default().apply_pinned_profile().unwrap();
+        assert_eq!(name, "custom");
+        let unknown = RankerConfig {
+            profile: Some("unknown".to_string()),
+            ..RankerConfig::default()
+        };
+        assert_eq!(unknown.apply_pinned_profile().unwrap_err(), "Unknown profile: unknown");
+    }
+
+    #[test]
+    fn unscored_urls_share_their_weight() {
+        let config = RankerConfig {
+            score_urls: false,
+            ..RankerConfig::from_profile("navigational").unwrap()
+        };
+        let scale = 12.5 / 3.5;
+        assert_eq!(config.field_weights(), [3.0 * scale, 0.5 * scale, 0.0, 0.0]);
+        assert_eq!(config.length_penalty(), 0.0);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::encoding::{write_string, write_varint, Reader};
+use std::collections::BTreeMap;
+
+// Bump when the encoding changes, so old exports can still be read
+pub const FORMAT_VERSION: u8 = 1;
+
+// The score reported for pinned results in place of their own, above any
+// organic score, so that logs and training data can leave curated positions
+// out. Explanations of pinned results are marked as curated too.
+pub const CURATED_SCORE: f32 = 1_000_000.0;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryCuration {
+    // URLs shown first, in this order
+    pub pins: Vec<String>,
+    // URLs removed from the results
+    pub exclusions: Vec<String>,
+}
+
+// A user's pins and exclusions, keyed by normalized query
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Curations {
+    queries: BTreeMap<String, QueryCuration>,
+}
+
+impl Curations {
+    pub fn get(&self, query: &str) -> Option<&QueryCuration> {
+        self.queries.get(query)
+    }
+
+    pub fn pin(&mut self, query: &str, url: &str) {
+        self.remove(query, url);
+        let curation = self.queries.entry(query.to_string()).or_default();
+        curation.pins.push(url.to_string());
+    }
+
+    pub fn exclude(&mut self, query: &str, url: &str) {
+        self.remove(query, url);
+        let curation = self.queries.entry(query.to_string()).or_default();
+        curation.exclusions.push(url.to_string());
+    }
+
+    pub fn remove(&mut self, query: &str, url: &str) {
+        if let Some(curation) = self.queries.get_mut(query) {
+            curation.pins.retain(|pin| pin != url);
+            curation.exclusions.retain(|exclusion| exclusion != url);
+            if curation.pins.is_empty() && curation.exclusions.is_empty() {
+                self.queries.remove(query);
+            }
+        }
+    }
+
+    // Encode as a version byte followed by, for each query, the query, its
+    // pins and its exclusions. Strings and counts are LEB128 length prefixed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![FORMAT_VERSION];
+        write_varint(&mut bytes, self.queries.len());
+        for (query, curation) in &self.queries {
+            write_string(&mut bytes, query);
+            for urls in [&curation.pins, &curation.exclusions] {
+                write_varint(&mut bytes, urls.len());
+                for url in urls {
+                    write_string(&mut bytes, url);
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Curations, String> {
+        let mut reader = Reader::new(bytes, "curations");
+        let version = reader.read_byte()?;
+        if version != FORMAT_VERSION {
+            return Err(format!("Unsupported curation format version {}", version));
+        }
+        let mut curations = Curations::default();
+        for _ in 0..reader.read_varint()? {
+            let query = reader.read_string()?;
+            let mut curation = QueryCuration::default();
+            for urls in [&mut curation.pins, &mut curation.exclusions] {
+                for _ in 0..reader.read_varint()? {
+                    urls.push(reader.read_string()?);
+                }
+            }
+            curations.queries.insert(query, curation);
+        }
+        reader.finish()?;
+        Ok(curations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curations_round_trip() {
+        let mut curations = Curations::default();
+        curations.pin("rust", "https://rust-lang.org/");
+        curations.exclude("rust", "https://spam.example/");
+        curations.pin("python", "https://python.org/");
+
+        let bytes = curations.to_bytes();
+        assert_eq!(Curations::from_bytes(&bytes), Ok(curations));
+        assert!(Curations::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Curations::from_bytes(&[2, 0]).is_err());
+    }
+
+    #[test]
+    fn pinning_replaces_an_exclusion() {
+        let mut curations = Curations::default();
+        curations.exclude("rust", "https://rust-lang.org/");
+        curations.pin("rust", "https://rust-lang.org/");
+        let curation = curations.get("rust").unwrap();
+        assert_eq!(curation.pins, vec!["https://rust-lang.org/"]);
+        assert!(curation.exclusions.is_empty());
+
+        curations.remove("rust", "https://rust-lang.org/");
+        assert!(curations.get("rust").is_none());
+    }
+}
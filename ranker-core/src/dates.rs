@@ -0,0 +1,176 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+// Years outside this range are more likely to be other numbers
+const MIN_YEAR: u16 = 1990;
+const MAX_YEAR: u16 = 2100;
+
+// A date found in a result, as precise as its source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResultDate {
+    pub year: u16,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+impl ResultDate {
+    fn new(year: &str, month: Option<&str>, day: Option<&str>) -> Option<ResultDate> {
+        let year = year.parse::<u16>().ok()?;
+        let month = month.map(|month| month.parse::<u8>()).transpose().ok()?;
+        let day = day.map(|day| day.parse::<u8>()).transpose().ok()?;
+        let valid = (MIN_YEAR..=MAX_YEAR).contains(&year)
+            && month.map(|month| (1..=12).contains(&month)).unwrap_or(true)
+            && day.map(|day| (1..=31).contains(&day)).unwrap_or(true);
+        if valid {
+            Some(ResultDate { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    // Days since 1970-01-01, taking the middle of the month or year when the
+    // day or month isn't known
+    pub fn days_since_epoch(&self) -> i64 {
+        let (month, default_day) = match self.month {
+            Some(month) => (month, 15),
+            None => (7, 1),
+        };
+        days_from_civil(
+            self.year as i64,
+            month as i64,
+            self.day.unwrap_or(default_day) as i64,
+        )
+    }
+
+    // How many days before `now_ms` this date was, or zero if it's later
+    pub fn age_days(&self, now_ms: f64) -> f32 {
+        let now_days = (now_ms / MS_PER_DAY).floor() as i64;
+        (now_days - self.days_since_epoch()).max(0) as f32
+    }
+}
+
+impl fmt::Display for ResultDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+            if let Some(day) = self.day {
+                write!(f, "-{:02}", day)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Days from 1970-01-01 to the given date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Parse an ISO 8601 date, "YYYY", "YYYY-MM" or "YYYY-MM-DD", ignoring any
+// time after it, e.g. "2024-03-01T09:30:00Z"
+pub fn parse_iso_date(text: &str) -> Option<ResultDate> {
+    let date = text.trim().split(['T', ' ']).next()?;
+    let mut parts = date.split('-');
+    let year = parts.next()?;
+    let month = parts.next();
+    let day = parts.next();
+    if year.len() != 4 || parts.next().is_some() {
+        return None;
+    }
+    ResultDate::new(year, month, day)
+}
+
+// Find a date in the URL path, e.g. /2023/11/05/ or /2023-11-05-slug, or in
+// the title, e.g. "Some film (2019)", preferring the URL
+pub fn extract_date(path: &str, title: &str) -> Option<ResultDate> {
+    static PATH_REGEX: OnceLock<Regex> = OnceLock::new();
+    static TITLE_REGEX: OnceLock<Regex> = OnceLock::new();
+    let path_regex = PATH_REGEX.get_or_init(|| {
+        Regex::new(r"(?:^|/)(\d{4})(?:[/-](\d{1,2})(?:[/-](\d{1,2}))?)?(?:[/-]|\.html?$|$)")
+            .unwrap()
+    });
+    let title_regex =
+        TITLE_REGEX.get_or_init(|| Regex::new(r"\((\d{4})\)|\b(\d{4})-(\d{2})-(\d{2})\b").unwrap());
+
+    let from_path = path_regex.captures_iter(path).find_map(|captures| {
+        ResultDate::new(
+            &captures[1],
+            captures.get(2).map(|m| m.as_str()),
+            captures.get(3).map(|m| m.as_str()),
+        )
+    });
+    from_path.or_else(|| {
+        title_regex
+            .captures_iter(title)
+            .find_map(|captures| match captures.get(1) {
+                Some(year) => ResultDate::new(year.as_str(), None, None),
+                None => ResultDate::new(
+                    &captures[2],
+                    captures.get(3).map(|m| m.as_str()),
+                    captures.get(4).map(|m| m.as_str()),
+                ),
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(path: &str, title: &str) -> Option<String> {
+        extract_date(path, title).map(|date| date.to_string())
+    }
+
+    #[test]
+    fn test_extract_date() {
+        assert_eq!(
+            date("/2023/11/05/some-post", ""),
+            Some("2023-11-05".to_string())
+        );
+        assert_eq!(date("/blog/2023/11/", ""), Some("2023-11".to_string()));
+        assert_eq!(
+            date("/2021-03-09-release.html", ""),
+            Some("2021-03-09".to_string())
+        );
+        assert_eq!(date("/news/2019", ""), Some("2019".to_string()));
+        assert_eq!(date("/", "The Irishman (2019)"), Some("2019".to_string()));
+        assert_eq!(
+            date("/", "Changelog 2022-01-31"),
+            Some("2022-01-31".to_string())
+        );
+        assert_eq!(date("/products/1234/", "Model 3000"), None);
+        assert_eq!(date("/2023/13/", ""), None);
+    }
+
+    #[test]
+    fn test_parse_iso_date() {
+        let parse = |text: &str| parse_iso_date(text).map(|date| date.to_string());
+        assert_eq!(parse("2024-03-01"), Some("2024-03-01".to_string()));
+        assert_eq!(
+            parse("2024-03-01T09:30:00Z"),
+            Some("2024-03-01".to_string())
+        );
+        assert_eq!(parse("2024-3"), Some("2024-03".to_string()));
+        assert_eq!(parse("2024"), Some("2024".to_string()));
+        assert_eq!(parse("24-03-01"), None);
+        assert_eq!(parse("2024-13-01"), None);
+        assert_eq!(parse("yesterday"), None);
+    }
+
+    #[test]
+    fn test_age_days() {
+        let date = ResultDate::new("2000", Some("1"), Some("2")).unwrap();
+        assert_eq!(date.days_since_epoch(), 10958);
+        assert_eq!(date.age_days(10968.0 * MS_PER_DAY), 10.0);
+        assert_eq!(date.age_days(0.0), 0.0);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::urls::registrable_domain;
+use crate::{ScoredResult, SearchResult};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+// Host labels that don't change the page, like those of mobile versions
+const FOLDED_LABELS: [&str; 3] = ["m", "mobile", "www"];
+
+// Results whose title and extract share at least this proportion of their
+// words are considered the same page
+const MIN_TEXT_SIMILARITY: f32 = 0.8;
+
+// A URL with its language and mobile variations folded away, so that e.g.
+// "https://en.m.wikipedia.org/wiki/Rust" and
+// "https://en.wikipedia.org/wiki/Rust" have the same key, as do
+// "https://de.example.com/" and "https://example.com/de/"
+#[derive(Debug, PartialEq)]
+struct LocaleVariant {
+    key: String,
+    locale: Option<String>,
+}
+
+fn is_language_code(text: &str) -> bool {
+    let (language, region) = match text.split_once('-') {
+        Some((language, region)) => (language, Some(region)),
+        None => (text, None),
+    };
+    language.len() == 2
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && region.is_none_or(|region| {
+            region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic())
+        })
+}
+
+fn locale_variant(url: &str) -> Option<LocaleVariant> {
+    let parsed_url = Url::parse(url).ok()?;
+    let host = parsed_url.host_str()?.to_lowercase();
+    let registrable = registrable_domain(&host);
+    let mut locale = None;
+    let mut labels = Vec::new();
+    for label in host
+        .strip_suffix(registrable)
+        .unwrap_or("")
+        .split('.')
+        .filter(|label| !label.is_empty())
+    {
+        if FOLDED_LABELS.contains(&label) {
+            continue;
+        }
+        if locale.is_none() && is_language_code(label) {
+            locale = Some(label.to_string());
+            continue;
+        }
+        labels.push(label);
+    }
+    labels.push(registrable);
+
+    let mut segments = parsed_url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect::<Vec<&str>>())
+        .unwrap_or_default();
+    if locale.is_none() && segments.first().is_some_and(|s| is_language_code(s)) {
+        locale = Some(segments.remove(0).to_lowercase());
+    }
+    let key = format!(
+        "{}/{}?{}",
+        labels.join("."),
+        segments.join("/"),
+        parsed_url.query().unwrap_or("")
+    );
+    Some(LocaleVariant { key, locale })
+}
+
+fn words(result: &SearchResult) -> HashSet<String> {
+    format!("{} {}", result.title, result.extract)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn near_identical(a: &SearchResult, b: &SearchResult) -> bool {
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    union == 0 || a.intersection(&b).count() as f32 / union as f32 >= MIN_TEXT_SIMILARITY
+}
+
+// Whether the variant is in the user's language, comparing only the language
+// part of locales like "en-GB"
+fn matches_locale(variant: &LocaleVariant, locale: &str) -> bool {
+    let language = |locale: &str| locale.split('-').next().unwrap_or("").to_lowercase();
+    match &variant.locale {
+        Some(variant_locale) => !locale.is_empty() && language(variant_locale) == language(locale),
+        None => false,
+    }
+}
+
+// Remove language and mobile variants of pages that appear higher up in the
+// ranking. The variant in the user's `locale` takes the place of the
+// highest ranked variant.
+pub fn remove_locale_duplicates(scored_results: &mut Vec<ScoredResult>, locale: &str) {
+    let mut kept: Vec<(ScoredResult, Option<LocaleVariant>)> = Vec::new();
+    for scored in scored_results.drain(..) {
+        let variant = locale_variant(scored.result.match_url());
+        let duplicate = variant.as_ref().and_then(|variant| {
+            kept.iter().position(|(other, other_variant)| {
+                other_variant.as_ref().map(|v| &v.key) == Some(&variant.key)
+                    && near_identical(scored.result, other.result)
+            })
+        });
+        match duplicate {
+            Some(position) => {
+                let variant = variant.unwrap();
+                let kept_matches = kept[position]
+                    .1
+                    .as_ref()
+                    .is_some_and(|v| matches_locale(v, locale));
+                if matches_locale(&variant, locale) && !kept_matches {
+                    kept[position] = (scored, Some(variant));
+                }
+            }
+            None => kept.push((scored, variant)),
+        }
+    }
+    scored_results.extend(kept.into_iter().map(|(scored, _)| scored));
+}
+
+// The URL as the `url` crate normalizes it, e.g. with a lowercase host and
+// a path of at least "/", without its fragment
+fn normalize_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+// Remove results that lead to the same page as a higher ranked result, when
+// either of them got there by a redirect
+pub fn remove_redirect_duplicates(scored_results: &mut Vec<ScoredResult>) {
+    // Whether the highest ranked result leading to each page was redirected
+    let mut seen: HashMap<String, bool> = HashMap::new();
+    scored_results.retain(|scored| {
+        let redirected = scored.result.final_url.is_some();
+        match seen.entry(normalize_url(scored.result.match_url())) {
+            Entry::Occupied(entry) => !redirected && !*entry.get(),
+            Entry::Vacant(entry) => {
+                entry.insert(redirected);
+                true
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_variant() {
+        let variant = |url| locale_variant(url).unwrap();
+        let wikipedia = variant("https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(wikipedia.locale.as_deref(), Some("en"));
+        assert_eq!(variant("https://en.m.wikipedia.org/wiki/Rust"), wikipedia);
+
+        let subdomain = variant("https://de.example.com/");
+        assert_eq!(subdomain.locale.as_deref(), Some("de"));
+        assert_eq!(variant("https://www.example.com/de/"), subdomain);
+        assert_eq!(variant("https://example.com/en-GB/").key, subdomain.key);
+
+        let other = variant("https://docs.example.com/");
+        assert_eq!(other.locale, None);
+        assert_ne!(other.key, subdomain.key);
+    }
+}
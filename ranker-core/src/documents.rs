@@ -0,0 +1,189 @@
+use crate::config::RankerConfig;
+use crate::query_terms::QueryTerms;
+use crate::{get_query_regex, match_field, rewrite, MAX_QUERY_TERMS};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentField {
+    pub name: String,
+    // How much a match in this field counts, like `RankerConfig::title_weight`
+    pub weight: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RankedDocument {
+    pub index: usize,
+    pub score: f32,
+}
+
+// Ranks documents made of any fields the caller defines, e.g. the name, tags
+// and body of notes, with the same term matching as search results. Only the
+// weighted match scores of the fields count, none of the web page signals.
+pub struct DocumentRanker {
+    fields: Vec<DocumentField>,
+    config: RankerConfig,
+    query_regex: Regex,
+    query_terms: QueryTerms,
+    num_unique_terms: u8,
+    total_possible_match_length: u32,
+    // The text of each field of each document, in the order of `fields`
+    documents: Vec<Vec<String>>,
+}
+
+impl DocumentRanker {
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    pub fn new(query: &str, fields: Vec<DocumentField>) -> Result<DocumentRanker, String> {
+        if fields.is_empty() {
+            return Err("Documents need at least one field".to_string());
+        }
+        for (i, field) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|other| other.name == field.name) {
+                return Err(format!("Duplicate field: {}", field.name));
+            }
+            if !field.weight.is_finite() || field.weight < 0.0 {
+                return Err(format!("Invalid weight for field {}", field.name));
+            }
+        }
+        let config = RankerConfig::default();
+        let rewrite = rewrite::rewrite_query(query, &config.rewrite, MAX_QUERY_TERMS);
+        let query_terms = QueryTerms::new(&rewrite, &config.rewrite, true);
+        let (query_regex, num_unique_terms, total_possible_match_length) =
+            get_query_regex(&rewrite.terms, false, &query_terms.stopwords);
+        Ok(DocumentRanker {
+            fields,
+            config,
+            query_regex,
+            query_terms,
+            num_unique_terms,
+            total_possible_match_length,
+            documents: Vec::new(),
+        })
+    }
+
+    pub fn add_document(&mut self, mut document: BTreeMap<String, String>) -> Result<(), String> {
+        let texts = self
+            .fields
+            .iter()
+            .map(|field| document.remove(&field.name).unwrap_or_default())
+            .collect();
+        if let Some(name) = document.keys().next() {
+            return Err(format!("Unknown field: {}", name));
+        }
+        self.documents.push(texts);
+        Ok(())
+    }
+
+    fn score(&self, texts: &[String]) -> Option<f32> {
+        let mut num_terms = 0;
+        let mut score = 0.0;
+        for (field, text) in self.fields.iter().zip(texts) {
+            let field_match = match_field(
+                &self.query_regex,
+                text,
+                self.total_possible_match_length,
+                self.num_unique_terms,
+                &self.query_terms,
+                &self.config,
+            );
+            num_terms += field_match.terms.len();
+            score += field.weight * field_match.features.score;
+        }
+        if num_terms > 0 {
+            Some(score)
+        } else {
+            None
+        }
+    }
+
+    pub fn ranked_documents(&self) -> Vec<RankedDocument> {
+        let mut ranked = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(index, texts)| {
+                let score = self.score(texts)?;
+                Some(RankedDocument { index, score })
+            })
+            .collect::<Vec<RankedDocument>>();
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[(&str, f32)]) -> Vec<DocumentField> {
+        fields
+            .iter()
+            .map(|&(name, weight)| DocumentField {
+                name: name.to_string(),
+                weight,
+            })
+            .collect()
+    }
+
+    fn document(fields: &[(&str, &str)]) -> BTreeMap<String, String> {
+        fields
+            .iter()
+            .map(|&(name, text)| (name.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn documents_rank_by_weighted_fields() {
+        let fields = fields(&[("name", 3.0), ("tags", 2.0), ("body", 1.0)]);
+        let mut ranker = DocumentRanker::new("rust traits", fields).unwrap();
+        ranker
+            .add_document(document(&[("name", "Shopping"), ("body", "Rust traits")]))
+            .unwrap();
+        ranker
+            .add_document(document(&[("name", "Rust traits"), ("tags", "rust")]))
+            .unwrap();
+        ranker
+            .add_document(document(&[("name", "Holiday plans")]))
+            .unwrap();
+        assert_eq!(ranker.len(), 3);
+
+        let ranked = ranker.ranked_documents();
+        let indexes = ranked.iter().map(|doc| doc.index).collect::<Vec<usize>>();
+        assert_eq!(indexes, vec![1, 0]);
+        assert!(ranked[0].score > ranked[1].score);
+
+        let error = ranker.add_document(document(&[("title", "Rust")]));
+        assert_eq!(error, Err("Unknown field: title".to_string()));
+    }
+
+    #[test]
+    fn long_documents_rank_by_match_position() {
+        let mut ranker = DocumentRanker::new("rust", fields(&[("body", 1.0)])).unwrap();
+        let padding = "notes ".repeat(1000);
+        let far = format!("{}rust", padding);
+        let near = format!("{}rust", &padding[..600]);
+        ranker.add_document(document(&[("body", &far)])).unwrap();
+        ranker.add_document(document(&[("body", &near)])).unwrap();
+
+        let ranked = ranker.ranked_documents();
+        assert_eq!(ranked[0].index, 1);
+        assert!(ranked[0].score > ranked[1].score);
+    }
+
+    #[test]
+    fn fields_are_validated() {
+        assert!(DocumentRanker::new("rust", Vec::new()).is_err());
+        let duplicate = fields(&[("name", 1.0), ("name", 2.0)]);
+        assert!(DocumentRanker::new("rust", duplicate).is_err());
+        let negative = fields(&[("name", -1.0)]);
+        assert!(DocumentRanker::new("rust", negative).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+// Helpers for the compact binary formats that user data is exported in.
+// Strings and counts are LEB128 length prefixed.
+
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+pub fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    write_varint(bytes, s.len());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+pub fn write_f64(bytes: &mut Vec<u8>, value: f64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    // What is being read, for error messages
+    name: &'static str,
+}
+
+impl Reader<'_> {
+    pub fn new<'a>(bytes: &'a [u8], name: &'static str) -> Reader<'a> {
+        Reader {
+            bytes,
+            position: 0,
+            name,
+        }
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| self.unexpected_end())?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    pub fn read_varint(&mut self) -> Result<usize, String> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(format!("Invalid length in {}", self.name))
+    }
+
+    pub fn read_string(&mut self) -> Result<String, String> {
+        let length = self.read_varint()?;
+        let name = self.name;
+        let bytes = self.read_slice(length)?;
+        let s =
+            std::str::from_utf8(bytes).map_err(|e| format!("Invalid string in {}: {}", name, e))?;
+        Ok(s.to_string())
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.read_slice(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    // Check that everything has been read
+    pub fn finish(&self) -> Result<(), String> {
+        if self.position != self.bytes.len() {
+            return Err(format!("Unexpected trailing bytes in {}", self.name));
+        }
+        Ok(())
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&[u8], String> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.unexpected_end())?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn unexpected_end(&self) -> String {
+        format!("Unexpected end of {}", self.name)
+    }
+}
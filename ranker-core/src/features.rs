@@ -0,0 +1,480 @@
+use crate::page_type::PageType;
+use crate::Features;
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+// Every feature that can be explained, clamped or used by a model, so their
+// names are defined in one place
+pub struct FeatureDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    get: fn(&Features) -> f32,
+    // Only set for features that can be clamped
+    get_mut: Option<fn(&mut Features) -> &mut f32>,
+}
+
+impl FeatureDefinition {
+    pub fn clampable(&self) -> bool {
+        self.get_mut.is_some()
+    }
+}
+
+impl Serialize for FeatureDefinition {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("FeatureDefinition", 3)?;
+        state.serialize_field("name", self.name)?;
+        state.serialize_field("description", self.description)?;
+        state.serialize_field("clampable", &self.clampable())?;
+        state.end()
+    }
+}
+
+macro_rules! feature_registry {
+    ($(($prefix:literal, $field:ident)),*) => {
+        &[
+            FeatureDefinition {
+                name: "semantic_similarity",
+                description: "Similarity of the result embedding to the query embedding",
+                get: |features| features.semantic_similarity,
+                get_mut: Some(|features| &mut features.semantic_similarity),
+            },
+            FeatureDefinition {
+                name: "host_penalty",
+                description: "Decayed count of recent skips and bounces from the result's host",
+                get: |features| features.host_penalty,
+                get_mut: Some(|features| &mut features.host_penalty),
+            },
+            FeatureDefinition {
+                name: "domain_popularity",
+                description: "From 0 for unlisted domains to 1 for the most popular domain",
+                get: |features| features.domain_popularity,
+                get_mut: Some(|features| &mut features.domain_popularity),
+            },
+            FeatureDefinition {
+                name: "domain_authority",
+                description: "From 0 for unlisted domains to 1 for the most linked to domain",
+                get: |features| features.domain_authority,
+                get_mut: Some(|features| &mut features.domain_authority),
+            },
+            FeatureDefinition {
+                name: "title_extract_overlap",
+                description: "Proportion of title words that also appear in the extract",
+                get: |features| features.title_extract_overlap,
+                get_mut: Some(|features| &mut features.title_extract_overlap),
+            },
+            FeatureDefinition {
+                name: "title_punctuation_ratio",
+                description: "Proportion of non-whitespace title characters that are punctuation",
+                get: |features| features.title_punctuation_ratio,
+                get_mut: Some(|features| &mut features.title_punctuation_ratio),
+            },
+            FeatureDefinition {
+                name: "title_num_emoji",
+                description: "Number of emoji in the title",
+                get: |features| features.title_num_emoji as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_keyword_list",
+                description: "1 if the title is a pipe separated list of keyword variations",
+                get: |features| features.title_keyword_list as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_all_caps",
+                description: "1 if the title has at least 8 letters and all are capitals",
+                get: |features| features.title_all_caps as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_clickbait_template",
+                description: "1 if the title follows a clickbait template, otherwise 0",
+                get: |features| features.title_clickbait_template as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_empty",
+                description: "1 if the title is empty or only whitespace, otherwise 0",
+                get: |features| features.title_empty as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_length_in_range",
+                description: "1 if the title is of a plausible length for a page title",
+                get: |features| features.title_length_in_range as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "extract_chars",
+                description: "Number of characters in the extract",
+                get: |features| features.extract_chars,
+                get_mut: Some(|features| &mut features.extract_chars),
+            },
+            FeatureDefinition {
+                name: "alphabetic_ratio",
+                description: "Proportion of letters in the title and extract, ignoring whitespace",
+                get: |features| features.alphabetic_ratio,
+                get_mut: Some(|features| &mut features.alphabetic_ratio),
+            },
+            FeatureDefinition {
+                name: "extract_punctuation_ratio",
+                description: "Proportion of non-whitespace extract characters that are punctuation",
+                get: |features| features.extract_punctuation_ratio,
+                get_mut: Some(|features| &mut features.extract_punctuation_ratio),
+            },
+            FeatureDefinition {
+                name: "title_phrase_match",
+                description: "1 if the whole query appears verbatim in the title, otherwise 0",
+                get: |features| features.title_phrase_match as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "extract_phrase_match",
+                description: "1 if the whole query appears verbatim in the extract, otherwise 0",
+                get: |features| features.extract_phrase_match as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "extract_clustering",
+                description: "How much the query terms in the extract share a sentence, 0 to 1",
+                get: |features| features.extract_clustering,
+                get_mut: Some(|features| &mut features.extract_clustering),
+            },
+            FeatureDefinition {
+                name: "title_all_terms",
+                description: "1 if every query term matches in the title, otherwise 0",
+                get: |features| features.title_all_terms as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "exact_domain_match",
+                description: "1 if the registrable domain spells out the query, otherwise 0",
+                get: |features| features.exact_domain_match as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "extract_completeness",
+                description: "From 0 for a missing extract to 1 for one of typical length",
+                get: |features| features.extract_completeness,
+                get_mut: Some(|features| &mut features.extract_completeness),
+            },
+            FeatureDefinition {
+                name: "path_depth",
+                description: "Number of non-empty segments in the URL path",
+                get: |features| features.path_depth as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "https",
+                description: "Whether the URL is served over https",
+                get: |features| features.https as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "tracking_url",
+                description: "Whether the URL has tracking parameters or a session ID",
+                get: |features| features.tracking_url as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "url_spam_score",
+                description: "From 0 to 1, the proportion of signs of spam shown by the URL",
+                get: |features| features.url_spam_score,
+                get_mut: Some(|features| &mut features.url_spam_score),
+            },
+            FeatureDefinition {
+                name: "language_mismatch",
+                description: "Whether the result is detectably in another language than the query",
+                get: |features| features.language_mismatch as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "redirect_depth",
+                description: "Number of redirects from the result's URL to its final URL",
+                get: |features| features.redirect_depth as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "term_coverage",
+                description: "Proportion of unique query terms matched in any field",
+                get: |features| features.term_coverage,
+                get_mut: Some(|features| &mut features.term_coverage),
+            },
+            FeatureDefinition {
+                name: "num_terms_matched",
+                description: "Number of unique query terms matched in any field",
+                get: |features| features.num_terms_matched as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "noindex_detected",
+                description: "1 if the crawler found a noindex directive, otherwise 0",
+                get: |features| features.noindex_detected as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "soft404_suspected",
+                description: "1 if the crawler suspects the page is a soft 404, otherwise 0",
+                get: |features| features.soft404_suspected as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "paywalled",
+                description: "1 if the backend flagged the page as paywalled, otherwise 0",
+                get: |features| features.paywalled as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "has_date",
+                description: "1 if the result has a published date or one in its URL or title",
+                get: |features| features.has_date as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "age_days",
+                description: "Days since the result's date, 0 without one",
+                get: |features| features.age_days,
+                get_mut: Some(|features| &mut features.age_days),
+            },
+            FeatureDefinition {
+                name: "recency",
+                description: "From 1 if dated today, halving every year, 0 without a date",
+                get: |features| features.recency,
+                get_mut: Some(|features| &mut features.recency),
+            },
+            FeatureDefinition {
+                name: "title_numeric_match",
+                description: "1 if a number in the title fits a number or range in the query",
+                get: |features| features.title_numeric_match as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "extract_numeric_match",
+                description: "1 if a number in the extract fits a number or range in the query",
+                get: |features| features.extract_numeric_match as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "title_first_position",
+                description: "Token position of first query term in title, or its length if none",
+                get: |features| features.title_first_position,
+                get_mut: Some(|features| &mut features.title_first_position),
+            },
+            FeatureDefinition {
+                name: "extract_first_position",
+                description: "Token position of first query term in extract, or its length if none",
+                get: |features| features.extract_first_position,
+                get_mut: Some(|features| &mut features.extract_first_position),
+            },
+            FeatureDefinition {
+                name: "url_parse_failed",
+                description: "1 if the URL couldn't be parsed, otherwise 0",
+                get: |features| features.url_parse_failed as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "saturated",
+                description: "1 if any match feature hit the limit of its type, otherwise 0",
+                get: |features| features.saturated as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "match_limit_hit",
+                description: "1 if matching stopped at the limit in any field, otherwise 0",
+                get: |features| features.match_limit_hit as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "page_type_article",
+                description: "1 if the backend hinted the result is an article, otherwise 0",
+                get: |features| (features.page_type == PageType::Article) as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "page_type_product",
+                description: "1 if the backend hinted the result is a product, otherwise 0",
+                get: |features| (features.page_type == PageType::Product) as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "page_type_recipe",
+                description: "1 if the backend hinted the result is a recipe, otherwise 0",
+                get: |features| (features.page_type == PageType::Recipe) as u8 as f32,
+                get_mut: None,
+            },
+            FeatureDefinition {
+                name: "page_type_video",
+                description: "1 if the backend hinted the result is a video, otherwise 0",
+                get: |features| (features.page_type == PageType::Video) as u8 as f32,
+                get_mut: None,
+            },
+            $(
+                FeatureDefinition {
+                    name: concat!($prefix, "_score"),
+                    description: concat!("Match score of the ", $prefix),
+                    get: |features| features.$field.score,
+                    get_mut: Some(|features| &mut features.$field.score),
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_num_terms"),
+                    description: concat!("Unique query terms matched in the ", $prefix),
+                    get: |features| features.$field.num_terms as f32,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_term_proportion"),
+                    description: concat!(
+                        "Proportion of the unique query terms matched in the ",
+                        $prefix
+                    ),
+                    get: |features| features.$field.term_proportion,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_length"),
+                    description: concat!("Total length of the matches in the ", $prefix),
+                    get: |features| features.$field.length as f32,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_last_char"),
+                    description: concat!("Position of the end of the last match in the ", $prefix),
+                    get: |features| features.$field.last_char as f32,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_num_fuzzy_terms"),
+                    description: concat!("Query terms matched by a near miss in the ", $prefix),
+                    get: |features| features.$field.num_fuzzy_terms as f32,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_num_synonym_terms"),
+                    description: concat!("Query terms matched only as a synonym in the ", $prefix),
+                    get: |features| features.$field.num_synonym_terms as f32,
+                    get_mut: None,
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_in_order"),
+                    description: concat!("How much of the query is in order in the ", $prefix),
+                    get: |features| features.$field.in_order,
+                    get_mut: Some(|features| &mut features.$field.in_order),
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_starts_with"),
+                    description: concat!(
+                        "1 if the query starts the ",
+                        $prefix,
+                        ", 0.5 if only its first term does"
+                    ),
+                    get: |features| features.$field.starts_with,
+                    get_mut: Some(|features| &mut features.$field.starts_with),
+                },
+                FeatureDefinition {
+                    name: concat!($prefix, "_proximity"),
+                    description: concat!("How close together the query terms are in the ", $prefix),
+                    get: |features| features.$field.proximity,
+                    get_mut: Some(|features| &mut features.$field.proximity),
+                },
+            )*
+        ]
+    };
+}
+
+pub const FEATURES: &[FeatureDefinition] = feature_registry!(
+    ("title", title_match),
+    ("extract", extract_match),
+    ("domain", domain_match),
+    ("path", path_match)
+);
+
+pub fn definition(name: &str) -> Option<&'static FeatureDefinition> {
+    FEATURES.iter().find(|definition| definition.name == name)
+}
+
+impl Features {
+    // Look up a feature by name, e.g. "title_score" or "semantic_similarity"
+    pub fn value(&self, name: &str) -> Option<f32> {
+        definition(name).map(|definition| (definition.get)(self))
+    }
+
+    pub fn is_known(name: &str) -> bool {
+        definition(name).is_some()
+    }
+
+    // Every feature value, in registry order
+    pub fn values(&self) -> Vec<f32> {
+        FEATURES
+            .iter()
+            .map(|definition| (definition.get)(self))
+            .collect()
+    }
+
+    // The feature values that can be clamped, by name
+    pub fn value_mut(&mut self, name: &str) -> Option<&mut f32> {
+        let get_mut = definition(name)?.get_mut?;
+        Some(get_mut(self))
+    }
+}
+
+// Serialize features as a map from name to value, in registry order
+pub fn serialize_values<S>(features: &Features, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(Some(FEATURES.len()))?;
+    for definition in FEATURES {
+        map.serialize_entry(definition.name, &(definition.get)(features))?;
+    }
+    map.end()
+}
+
+// Features that serialize as a name to value map
+pub struct FeatureValues(pub(crate) Features);
+
+impl Serialize for FeatureValues {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_values(&self.0, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_lookup() {
+        let mut features = Features::default();
+        features.title_match.num_terms = 2;
+        features.url_parse_failed = true;
+        assert_eq!(features.value("title_num_terms"), Some(2.0));
+        assert_eq!(features.value("url_parse_failed"), Some(1.0));
+        assert_eq!(features.value("title_unknown"), None);
+
+        *features.value_mut("path_score").unwrap() = 3.0;
+        assert_eq!(features.path_match.score, 3.0);
+        assert!(features.value_mut("path_length").is_none());
+
+        let values = features.values();
+        assert_eq!(values.len(), FEATURES.len());
+        let index = FEATURES.iter().position(|d| d.name == "path_score");
+        assert_eq!(values[index.unwrap()], 3.0);
+    }
+
+    #[test]
+    fn registry_names_are_unique() {
+        for (i, definition) in FEATURES.iter().enumerate() {
+            assert!(Features::is_known(definition.name));
+            assert!(FEATURES[i + 1..]
+                .iter()
+                .all(|other| other.name != definition.name));
+        }
+        assert_eq!(FEATURES.len(), 86);
+    }
+}
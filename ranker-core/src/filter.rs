@@ -0,0 +1,165 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use crate::urls;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use url::{Host, Url};
+
+// Host or path tokens that mark a result as adult content for safe search
+const ADULT_TERMS: [&str; 8] = [
+    "porn", "porno", "xxx", "sex", "nsfw", "hentai", "escort", "camgirls",
+];
+
+// How safe a result's URL looks, for the frontend to badge or warn about
+// Ordered from safest to least safe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UrlSafety {
+    Ok,
+    // The host may be imitating another one, see `is_suspicious_host`
+    SuspiciousHost,
+    // The filter would drop the URL if it were added now
+    Blocked,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlFilter {
+    // Results from these domains and their subdomains are dropped
+    pub blocked_domains: BTreeSet<String>,
+    pub safe_search: bool,
+    // URLs with other schemes are dropped. URLs that can't be parsed are let
+    // through, as their scheme isn't known.
+    pub allowed_schemes: Vec<String>,
+}
+
+impl Default for UrlFilter {
+    fn default() -> Self {
+        UrlFilter {
+            blocked_domains: BTreeSet::new(),
+            safe_search: false,
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+        }
+    }
+}
+
+impl UrlFilter {
+    // Why the URL is filtered out, or None if it is allowed
+    pub fn check(&self, url: &str) -> Option<String> {
+        if let Ok(parsed_url) = Url::parse(url) {
+            if !self
+                .allowed_schemes
+                .iter()
+                .any(|scheme| scheme == parsed_url.scheme())
+            {
+                return Some(format!("Scheme {} is not allowed", parsed_url.scheme()));
+            }
+        }
+        let (host, path, _) = urls::domain_and_path(url);
+        let mut domain = host.as_str();
+        loop {
+            if self.blocked_domains.contains(domain) {
+                return Some(format!("Domain {} is blocked", domain));
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => break,
+            }
+        }
+        if self.safe_search {
+            let text = format!("{} {}", host, path);
+            if tokenize(&text, &TokenizeOptions::default())
+                .iter()
+                .any(|token| ADULT_TERMS.contains(&token.text.as_str()))
+            {
+                return Some("Filtered by safe search".to_string());
+            }
+        }
+        None
+    }
+
+    pub fn allows(&self, url: &str) -> bool {
+        self.check(url).is_none()
+    }
+
+    pub fn classify(&self, url: &str) -> UrlSafety {
+        if !self.allows(url) {
+            UrlSafety::Blocked
+        } else if is_suspicious_host(url) {
+            UrlSafety::SuspiciousHost
+        } else {
+            UrlSafety::Ok
+        }
+    }
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii() || ('\u{c0}'..='\u{24f}').contains(&c)
+}
+
+// Whether a host may be imitating another: labels mixing Latin with look-alike
+// letters from other scripts, e.g. a Cyrillic "а" in "pаypal.com", IP
+// addresses, and URLs with a username that could be mistaken for the host,
+// e.g. "https://bank.com@example.com/"
+fn is_suspicious_host(url: &str) -> bool {
+    let parsed_url = match Url::parse(url) {
+        Ok(parsed_url) => parsed_url,
+        Err(_) => return false,
+    };
+    if !parsed_url.username().is_empty() || parsed_url.password().is_some() {
+        return true;
+    }
+    match parsed_url.host() {
+        Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) => true,
+        Some(Host::Domain(domain)) => {
+            let (domain, _) = idna::domain_to_unicode(domain);
+            domain.split('.').any(|label| {
+                label.chars().any(|c| c.is_ascii_alphabetic())
+                    && label.chars().any(|c| c.is_alphabetic() && !is_latin(c))
+            })
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_filter() {
+        let mut filter = UrlFilter::default();
+        assert!(filter.allows("https://example.com/"));
+        assert!(filter.allows("example.com/page"));
+        assert!(!filter.allows("javascript:alert(1)"));
+        assert!(!filter.allows("ftp://example.com/file"));
+
+        filter.blocked_domains.insert("spam.com".to_string());
+        assert!(!filter.allows("https://spam.com/"));
+        assert!(!filter.allows("https://www.spam.com/page"));
+        assert!(filter.allows("https://notspam.com/"));
+
+        assert!(filter.allows("https://xxx.example.com/"));
+        filter.safe_search = true;
+        assert!(!filter.allows("https://xxx.example.com/"));
+        assert!(filter.allows("https://essex.gov.uk/"));
+    }
+
+    #[test]
+    fn test_classify() {
+        let mut filter = UrlFilter::default();
+        filter.blocked_domains.insert("spam.com".to_string());
+        assert_eq!(filter.classify("https://example.com/"), UrlSafety::Ok);
+        assert_eq!(filter.classify("https://münchen.de/"), UrlSafety::Ok);
+        assert_eq!(filter.classify("https://xn--mnchen-3ya.de/"), UrlSafety::Ok);
+        assert_eq!(filter.classify("https://spam.com/"), UrlSafety::Blocked);
+        assert_eq!(filter.classify("ftp://example.com/"), UrlSafety::Blocked);
+        for url in &[
+            "https://p\u{430}ypal.com/",
+            "https://xn--pypal-4ve.com/",
+            "http://192.168.0.1/login",
+            "https://bank.com@example.com/",
+        ] {
+            assert_eq!(filter.classify(url), UrlSafety::SuspiciousHost, "{}", url);
+        }
+    }
+}
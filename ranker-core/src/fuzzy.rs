@@ -0,0 +1,65 @@
+// Terms shorter than this must match exactly, as too many other words are
+// only an edit or two away
+const MIN_FUZZY_LENGTH: usize = 4;
+// Terms at least this long may be two edits away rather than one
+const TWO_EDIT_LENGTH: usize = 8;
+
+// The number of insertions, deletions, substitutions and swaps of adjacent
+// characters needed to turn one word into the other, so that "recieve" is
+// one edit from "receive"
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+    // Rows of the distance matrix for the last two prefixes of `a` and the
+    // current one
+    let mut before_last = vec![0; b.len() + 1];
+    let mut last = (0..=b.len()).collect::<Vec<usize>>();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = last[j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            current[j] = substitution.min(last[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_last[j - 2] + 1);
+            }
+        }
+        before_last = std::mem::replace(&mut last, current);
+    }
+    last[b.len()]
+}
+
+// How many edits away a word may be from the term and still match it
+pub fn allowed_edits(term: &str, max_edit_distance: u8) -> usize {
+    let length = term.chars().count();
+    let allowed = if length < MIN_FUZZY_LENGTH {
+        0
+    } else if length < TWO_EDIT_LENGTH {
+        1
+    } else {
+        2
+    };
+    allowed.min(max_edit_distance as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("receive", "receive"), 0);
+        assert_eq!(edit_distance("recieve", "receive"), 1);
+        assert_eq!(edit_distance("recive", "receive"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_allowed_edits() {
+        assert_eq!(allowed_edits("rust", 2), 1);
+        assert_eq!(allowed_edits("go", 2), 0);
+        assert_eq!(allowed_edits("javascript", 2), 2);
+        assert_eq!(allowed_edits("javascript", 1), 1);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::features::FEATURES;
+use crate::model::MODEL_TYPES;
+use crate::serp::stable_hash;
+use crate::{authority, curation, popularity, reputation, serp};
+use serde::Serialize;
+
+// The version of each format the ranker exports and loads, which must match
+// for a bundle or table to load
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FormatVersions {
+    pub serp_bundle: u32,
+    pub curations: u8,
+    pub host_reputation: u8,
+    pub domain_popularity: u8,
+    pub domain_authority: u8,
+}
+
+// What this build of the ranker supports, for callers to log and to check
+// before loading models, tables and bundles made for another build
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BuildInfo {
+    // Whether this is a debug build, which ranks much slower
+    pub debug: bool,
+    // The `type`s of model that can be loaded
+    pub model_types: Vec<&'static str>,
+    // A hash of the feature names in registry order, which changes whenever a
+    // feature is added, removed or renamed, so models trained on another
+    // schema can be spotted
+    pub feature_schema_version: String,
+    pub formats: FormatVersions,
+}
+
+pub fn build_info() -> BuildInfo {
+    let feature_names = FEATURES
+        .iter()
+        .map(|definition| definition.name)
+        .collect::<Vec<&str>>();
+    BuildInfo {
+        debug: cfg!(debug_assertions),
+        model_types: MODEL_TYPES.to_vec(),
+        feature_schema_version: stable_hash(&feature_names.join(",")),
+        formats: FormatVersions {
+            serp_bundle: serp::BUNDLE_VERSION,
+            curations: curation::FORMAT_VERSION,
+            host_reputation: reputation::FORMAT_VERSION,
+            domain_popularity: popularity::FORMAT_VERSION,
+            domain_authority: authority::FORMAT_VERSION,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+
+    #[test]
+    fn build_info_lists_loadable_model_types() {
+        let info = build_info();
+        assert_eq!(info, build_info());
+        assert_eq!(info.feature_schema_version.len(), 16);
+        for model_type in &info.model_types {
+            let json = format!(
+                r#"{{"type": "{}", "features": [], "weights": []}}"#,
+                model_type
+            );
+            let error = serde_json::from_str::<Model>(&json).err();
+            assert!(error.is_none_or(|e| !e.to_string().contains("unknown variant")));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Serialize;
+
+const SHOPPING_TERMS: [&str; 10] = [
+    "buy", "price", "prices", "cheap", "cheapest", "deal", "deals", "discount", "coupon", "shop",
+];
+const RESEARCH_TERMS: [&str; 9] = [
+    "paper",
+    "research",
+    "study",
+    "definition",
+    "meaning",
+    "history",
+    "explained",
+    "vs",
+    "theory",
+];
+const QUESTION_WORDS: [&str; 7] = ["how", "what", "why", "when", "who", "which", "where"];
+const NAVIGATIONAL_TERMS: [&str; 4] = ["login", "www", "homepage", "signin"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryIntent {
+    General,
+    Navigational,
+    Research,
+    Shopping,
+}
+
+impl QueryIntent {
+    // The name of the intent as serialized, e.g. "navigational"
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryIntent::General => "general",
+            QueryIntent::Navigational => "navigational",
+            QueryIntent::Research => "research",
+            QueryIntent::Shopping => "shopping",
+        }
+    }
+
+    // The weight profile used for queries with this intent
+    pub fn profile_name(&self) -> &'static str {
+        match self {
+            QueryIntent::General => "default",
+            QueryIntent::Navigational => "navigational",
+            QueryIntent::Research => "research",
+            QueryIntent::Shopping => "shopping",
+        }
+    }
+}
+
+fn looks_like_domain(token: &str) -> bool {
+    match token.rsplit_once('.') {
+        Some((name, tld)) => {
+            !name.is_empty() && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
+    }
+}
+
+// Whether the whole query names a site: a domain, a single word such as
+// "github", or a word with a term like "login" or "www"
+fn looks_like_site_name(tokens: &[&str]) -> bool {
+    if tokens.iter().any(|token| looks_like_domain(token)) {
+        return true;
+    }
+    match tokens {
+        [_] => true,
+        [first, second] => {
+            NAVIGATIONAL_TERMS.contains(first) || NAVIGATIONAL_TERMS.contains(second)
+        }
+        _ => false,
+    }
+}
+
+pub fn classify_intent(query: &str) -> QueryIntent {
+    let query = query.to_lowercase();
+    let tokens = query.split_whitespace().collect::<Vec<&str>>();
+
+    if looks_like_site_name(&tokens) {
+        return QueryIntent::Navigational;
+    }
+    if tokens.iter().any(|token| SHOPPING_TERMS.contains(token)) {
+        return QueryIntent::Shopping;
+    }
+    let starts_with_question = tokens
+        .first()
+        .map(|token| QUESTION_WORDS.contains(token))
+        .unwrap_or(false);
+    if starts_with_question || tokens.iter().any(|token| RESEARCH_TERMS.contains(token)) {
+        return QueryIntent::Research;
+    }
+    QueryIntent::General
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_intent() {
+        assert_eq!(classify_intent("github.com"), QueryIntent::Navigational);
+        assert_eq!(classify_intent("Facebook login"), QueryIntent::Navigational);
+        assert_eq!(classify_intent("www bbc"), QueryIntent::Navigational);
+        assert_eq!(classify_intent("wikipedia"), QueryIntent::Navigational);
+        assert_eq!(classify_intent("lost my login"), QueryIntent::General);
+        assert_eq!(classify_intent("buy cheap laptop"), QueryIntent::Shopping);
+        assert_eq!(classify_intent("how does dns work"), QueryIntent::Research);
+        assert_eq!(classify_intent("rust web framework"), QueryIntent::General);
+    }
+}
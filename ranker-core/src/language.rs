@@ -0,0 +1,70 @@
+use whatlang::Lang;
+
+// Detections less confident than this are treated as unknown. A few words,
+// such as most queries, rarely get more than 0.2, while a sentence in a
+// Latin script gets 0.5 or more.
+const MIN_CONFIDENCE: f64 = 0.5;
+
+// The languages of locales, by the ISO 639-1 code that locales start with.
+// whatlang only knows the three letter codes.
+const LOCALE_LANGUAGES: [(&str, Lang); 16] = [
+    ("ar", Lang::Ara),
+    ("de", Lang::Deu),
+    ("en", Lang::Eng),
+    ("es", Lang::Spa),
+    ("fr", Lang::Fra),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("ko", Lang::Kor),
+    ("nl", Lang::Nld),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ru", Lang::Rus),
+    ("sv", Lang::Swe),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("zh", Lang::Cmn),
+];
+
+// The language of the text, or None if it can't be told with confidence
+pub fn detect_language(text: &str) -> Option<Lang> {
+    let info = whatlang::detect(text)?;
+    if info.confidence() >= MIN_CONFIDENCE {
+        Some(info.lang())
+    } else {
+        None
+    }
+}
+
+// The language of a locale such as "en-GB" or "pt_BR"
+pub fn locale_language(locale: &str) -> Option<Lang> {
+    let code = locale.split(['-', '_']).next()?.to_lowercase();
+    LOCALE_LANGUAGES
+        .iter()
+        .find(|(locale_code, _)| *locale_code == code)
+        .map(|(_, language)| *language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_are_detected_when_confident() {
+        let english =
+            "The Rust programming language helps you write faster, more reliable software";
+        let german = concat!(
+            "Die Programmiersprache Rust hilft dabei, ",
+            "schnellere und zuverlässigere Software zu schreiben"
+        );
+        assert_eq!(detect_language(english), Some(Lang::Eng));
+        assert_eq!(detect_language(german), Some(Lang::Deu));
+        assert_eq!(detect_language("rust programming"), None);
+
+        assert_eq!(locale_language("en-GB"), Some(Lang::Eng));
+        assert_eq!(locale_language("pt_BR"), Some(Lang::Por));
+        assert_eq!(locale_language("DE"), Some(Lang::Deu));
+        assert_eq!(locale_language(""), None);
+        assert_eq!(locale_language("xx-YY"), None);
+    }
+}
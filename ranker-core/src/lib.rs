@@ -0,0 +1,4238 @@
+mod alerts;
+mod authority;
+mod bench;
+mod bm25;
+mod collation;
+mod config;
+mod curation;
+mod dates;
+mod dedup;
+mod documents;
+mod encoding;
+mod features;
+mod filter;
+mod fuzzy;
+mod info;
+mod intent;
+mod language;
+mod metadata;
+mod model;
+mod numeric;
+mod output;
+mod page_type;
+mod popularity;
+mod positions;
+mod query_terms;
+mod replay;
+mod reputation;
+mod rewrite;
+mod rules;
+mod semantic;
+mod serp;
+mod slotting;
+mod stemming;
+mod stopwords;
+mod stability;
+mod text;
+mod text_quality;
+mod title_quality;
+mod training;
+mod urls;
+mod utils;
+mod validation;
+mod waterfall;
+
+pub use alerts::{result_matches, CandidateResult};
+pub use bench::{run_bench, BenchReport};
+pub use config::{BehaviorFlag, RankerConfig, PROFILE_NAMES};
+pub use curation::CURATED_SCORE;
+pub use documents::{DocumentField, DocumentRanker, RankedDocument};
+pub use features::{FeatureDefinition, FEATURES};
+pub use filter::{UrlFilter, UrlSafety};
+pub use info::{build_info, BuildInfo, FormatVersions};
+pub use intent::QueryIntent;
+pub use metadata::ResultMetadata;
+pub use model::Model;
+pub use output::{
+    ExplainAll, Explanation, FilteredResult, Highlights, RankOutput, RankStats, SerpQuality,
+};
+pub use rewrite::{QueryRewrite, RewriteConfig};
+pub use semantic::{hashed_embedding, SimilarityMetric};
+pub use text::tokenizer::{tokenize, utf16_offset, Token, TokenizeOptions};
+pub use training::{sample_pairs, sample_training_data, LoggedResult, LoggedSerp, TrainingPair};
+pub use urls::{parse_url_features, UrlFeatures};
+pub use utils::{set_clock, Clock};
+
+use arrayvec::ArrayString;
+use bm25::FieldStats;
+use config::Clamp;
+use collation::TitleCollator;
+use curation::Curations;
+use dates::ResultDate;
+use intent::classify_intent;
+use numeric::NumericConstraint;
+use positions::{ResultPositions, TokenPositions};
+use query_terms::QueryTerms;
+use semantic::{Embeddings, HASHED_EMBEDDING_DIM};
+use serp::{BundledResult, SerpBundle};
+use authority::DomainAuthority;
+use features::FeatureValues;
+use page_type::PageType;
+use output::ExplainedResult;
+use popularity::DomainPopularity;
+use reputation::HostReputation;
+use rewrite::RewriteStage;
+use rules::{apply_rules, parse_rules, Rule, RuleContext};
+use text_quality::TextQuality;
+use title_quality::TitleFormatting;
+use utils::Cache;
+use validation::IngestError;
+use waterfall::Waterfall;
+use text::{highlight_spans, matchable_text, shorten_string, snippet, tokenizer, word_regex};
+use regex::Regex;
+use serde::{Serialize, Serializer};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use whatlang::Lang;
+use serde::ser::SerializeStruct;
+
+const MAX_URL_LENGTH: usize = 200;
+const MAX_TITLE_LENGTH: usize = 100;
+const MAX_EXTRACT_LENGTH: usize = 200;
+// How many days old a result is when its freshness boost halves
+const FRESHNESS_HALF_LIFE_DAYS: f32 = 365.0;
+
+// Limits on the work done per query and per field, so that pathological input
+// degrades the ranking gracefully rather than blowing the time budget
+const MAX_QUERY_TERMS: usize = 32;
+const MAX_MATCHES_PER_FIELD: usize = 64;
+
+// Queries with a clearly better and worse (url, title, extract) result, that
+// any model must order correctly before it is used
+const MODEL_SANITY_SUITE: [(&str, [&str; 3], [&str; 3]); 3] = [
+    (
+        "rust programming",
+        ["https://rust-lang.org/", "Rust Programming Language", "A language empowering everyone"],
+        ["https://example.com/garden", "Gardening tips", "How to grow tomatoes"],
+    ),
+    (
+        "wikipedia",
+        ["https://wikipedia.org/", "Wikipedia", "The free encyclopedia"],
+        ["https://example.com/blog/post", "My blog", "Thoughts on things"],
+    ),
+    (
+        "python tutorial",
+        ["https://example.org/learn", "Learn", "A python tutorial for beginners"],
+        ["https://example.org/cook", "Learn", "Cooking for beginners"],
+    ),
+];
+
+// Extracts this long or longer count as complete, see `fill_missing_extracts`
+const FULL_EXTRACT_CHARS: usize = 80;
+
+// How many results to score between checks of the time budget
+const BUDGET_CHECK_INTERVAL: usize = 64;
+
+// The version of the ranking API, following semver, recorded in SERP bundles
+pub fn api_version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+// Rank the results of a bundle from `export_serp` again, optionally with a
+// new config and model given as JSON, and report how their positions changed.
+// Models aren't bundled, so without `model` the results are ranked without one.
+pub fn replay_serp(
+    bundle: &str,
+    config: Option<&str>,
+    model: Option<&str>,
+) -> Result<String, String> {
+    let bundle: SerpBundle =
+        serde_json::from_str(bundle).map_err(|e| format!("Invalid bundle: {}", e))?;
+    if bundle.version != serp::BUNDLE_VERSION {
+        return Err(format!("Unsupported bundle version {}", bundle.version));
+    }
+    let mut replay = bundle.clone();
+    if let Some(config) = config {
+        let config: RankerConfig =
+            serde_json::from_str(config).map_err(|e| format!("Invalid config: {}", e))?;
+        (replay.config, replay.profile) = config.apply_pinned_profile()?;
+    }
+    let mut ranker = Ranker::from_bundle(&replay);
+    let mut notes = Vec::new();
+    match model {
+        Some(model) => {
+            let model: Model =
+                serde_json::from_str(model).map_err(|e| format!("Invalid model: {}", e))?;
+            if let Some(warning) = ranker.load_model(model)? {
+                notes.push(format!("Model not used: {}", warning));
+            }
+        }
+        None => {
+            if let Some(version) = &bundle.model_version {
+                notes.push(format!("Bundled with model {}, replayed without a model", version));
+            }
+        }
+    }
+    Ok(replay::diff_report(&bundle, &ranker.serp_bundle(), &notes))
+}
+
+// The query as the ranker will use it, trimmed, lowercased and with operators
+// and bangs removed, so the frontend can display and cache the same form
+pub fn normalize_query(raw: &str) -> String {
+    rewrite::rewrite_query(raw, &RewriteConfig::default(), MAX_QUERY_TERMS).normalized_query()
+}
+
+fn query_tokens(query: &str) -> Vec<String> {
+    let options = TokenizeOptions {
+        max_tokens: Some(MAX_QUERY_TERMS),
+        ..TokenizeOptions::default()
+    };
+    tokenizer::tokenize(query, &options)
+        .into_iter()
+        .map(|token| token.text)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchResult {
+    pub url: ArrayString<MAX_URL_LENGTH>,
+    pub title: ArrayString<MAX_TITLE_LENGTH>,
+    pub extract: ArrayString<MAX_EXTRACT_LENGTH>,
+    // The extract for display, trimmed to a sentence or word boundary
+    pub snippet: ArrayString<MAX_EXTRACT_LENGTH>,
+    // Whether the url, title or extract had to be shortened to fit
+    pub truncated: bool,
+    pub page_type: PageType,
+    // The published date from the backend, or failing that a date found in
+    // the URL path or title
+    pub date: Option<ResultDate>,
+    // `date` is the published date the result was added with
+    pub date_published: bool,
+    // Where the URL redirects to, if the backend followed redirects
+    pub final_url: Option<ArrayString<MAX_URL_LENGTH>>,
+    pub redirect_depth: u8,
+    // Flags from the crawler, see `ResultMetadata`
+    pub noindex_detected: bool,
+    pub soft404_suspected: bool,
+    pub paywalled: bool,
+    // The language of the title and extract, if it could be detected
+    pub language: Option<Lang>,
+}
+
+impl Serialize for SearchResult {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SearchResult", 6)?;
+
+        state.serialize_field("url", &self.url.as_str())?;
+        state.serialize_field("title", &self.title.as_str())?;
+        state.serialize_field("extract", &self.extract.as_str())?;
+        state.serialize_field("snippet", &self.snippet.as_str())?;
+        if self.page_type == PageType::Unknown {
+            state.skip_field("page_type")?;
+        } else {
+            state.serialize_field("page_type", &self.page_type)?;
+        }
+        match self.date {
+            Some(date) => state.serialize_field("date", &date.to_string())?,
+            None => state.skip_field("date")?,
+        }
+        match self.final_url {
+            Some(final_url) => state.serialize_field("final_url", final_url.as_str())?,
+            None => state.skip_field("final_url")?,
+        }
+        if self.paywalled {
+            state.serialize_field("paywalled", &true)?;
+        } else {
+            state.skip_field("paywalled")?;
+        }
+        state.end()
+    }
+}
+
+
+
+impl SearchResult {
+    pub fn new(url: &str, title: &str, extract: &str) -> SearchResult {
+        SearchResult {
+            url: ArrayString::from(shorten_string(url, MAX_URL_LENGTH)).unwrap(),
+            title: ArrayString::from(shorten_string(title, MAX_TITLE_LENGTH)).unwrap(),
+            extract: ArrayString::from(shorten_string(extract, MAX_EXTRACT_LENGTH)).unwrap(),
+            snippet: ArrayString::from(&snippet::trim_snippet(extract, MAX_EXTRACT_LENGTH))
+                .unwrap(),
+            truncated: url.len() > MAX_URL_LENGTH
+                || title.len() > MAX_TITLE_LENGTH
+                || extract.len() > MAX_EXTRACT_LENGTH,
+            page_type: PageType::Unknown,
+            date: dates::extract_date(&urls::domain_and_path(url).1, title),
+            date_published: false,
+            final_url: None,
+            redirect_depth: 0,
+            noindex_detected: false,
+            soft404_suspected: false,
+            paywalled: false,
+            language: language::detect_language(&format!(
+                "{} {}",
+                shorten_string(title, MAX_TITLE_LENGTH),
+                shorten_string(extract, MAX_EXTRACT_LENGTH)
+            )),
+        }
+    }
+
+    // The details it was added with, as for `add_search_result_with_metadata`
+    pub fn metadata(&self) -> ResultMetadata {
+        ResultMetadata {
+            page_type: self.page_type.hint().to_string(),
+            final_url: self.final_url.map(|final_url| final_url.to_string()),
+            redirect_depth: self.redirect_depth,
+            noindex_detected: self.noindex_detected,
+            soft404_suspected: self.soft404_suspected,
+            paywalled: self.paywalled,
+            published_date: self
+                .date
+                .filter(|_| self.date_published)
+                .map(|date| date.to_string()),
+        }
+    }
+
+    // The URL to match and deduplicate on: where the result redirects to if
+    // known, otherwise its own URL
+    pub fn match_url(&self) -> &str {
+        match &self.final_url {
+            Some(final_url) => final_url,
+            None => &self.url,
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
+struct MatchFeatures {
+    // Character offsets and lengths are u32, so that matches deep into long
+    // fields keep their true position
+    last_char: u32,
+    length: u32,
+    total_possible_length: u32,
+    num_terms: u8,
+    score: f32,
+    term_proportion: f32,
+    // How close together the query terms appear, see `TokenPositions::proximity`
+    proximity: f32,
+    // How much of the query appears in query order, see `TokenPositions::in_order`
+    in_order: f32,
+    // 1 if the field starts with the query, 0.5 if it starts with only the
+    // first term that isn't a stopword, otherwise 0
+    starts_with: f32,
+    // Terms matched by a near miss rather than exactly, see `fuzzy`
+    num_fuzzy_terms: u8,
+    // Terms matched only because they are synonyms of query terms
+    num_synonym_terms: u8,
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
+struct Features {
+    title_match: MatchFeatures,
+    extract_match: MatchFeatures,
+    domain_match: MatchFeatures,
+    path_match: MatchFeatures,
+    // Similarity between the query and result embeddings, zero if either is missing
+    semantic_similarity: f32,
+    // The URL couldn't be parsed, so the domain and path were extracted with
+    // a regex and may be wrong
+    url_parse_failed: bool,
+    // A match position or length didn't fit in a u32, or a count in a u8,
+    // and was capped
+    saturated: bool,
+    // A field had too many matches, so only the first were considered
+    match_limit_hit: bool,
+    // How often the user skipped or bounced from results on this host lately
+    host_penalty: f32,
+    // From 0.0 for unlisted domains to 1.0 for the most popular domain, see
+    // `DomainPopularity::popularity`
+    domain_popularity: f32,
+    // From 0.0 for unlisted domains to 1.0 for the domain the most links
+    // point to, see `DomainAuthority::authority`
+    domain_authority: f32,
+    // The proportion of title words that also appear in the extract
+    title_extract_overlap: f32,
+    // Signs of spammy title formatting, see `TitleFormatting`
+    title_punctuation_ratio: f32,
+    title_num_emoji: u8,
+    title_keyword_list: bool,
+    title_all_caps: bool,
+    title_clickbait_template: bool,
+    // Query-independent signs of junk text, see `TextQuality`
+    title_empty: bool,
+    title_length_in_range: bool,
+    extract_chars: f32,
+    alphabetic_ratio: f32,
+    extract_punctuation_ratio: f32,
+    page_type: PageType,
+    has_date: bool,
+    // Days since the result's date, zero if it has none
+    age_days: f32,
+    // From 1.0 for a result dated today, halving every
+    // `FRESHNESS_HALF_LIFE_DAYS`, and 0.0 for results without a date
+    recency: f32,
+    // A number in the field satisfies a number or range in the query
+    title_numeric_match: bool,
+    extract_numeric_match: bool,
+    // The token position of the first query term in the field, or the number
+    // of tokens in the field if no query term appears
+    title_first_position: f32,
+    extract_first_position: f32,
+    // The whole query of two or more terms appears verbatim in the field
+    title_phrase_match: bool,
+    extract_phrase_match: bool,
+    // How much the query terms in the extract share a sentence, see
+    // `TokenPositions::clustering`
+    extract_clustering: f32,
+    // Every unique query term of two or more matches in the title
+    title_all_terms: bool,
+    // The registrable domain spells out the query, e.g. github.com for "github"
+    exact_domain_match: bool,
+    // From 0.0 for a missing extract to 1.0 for one of `FULL_EXTRACT_CHARS`
+    // characters or more
+    extract_completeness: f32,
+    // The number of non-empty segments in the path of the URL, 0 for the
+    // homepage
+    path_depth: u8,
+    // The URL the result is matched on is served over https
+    https: bool,
+    // The URL has tracking parameters or a session ID, see
+    // `urls::has_tracking_noise`
+    tracking_url: bool,
+    // From 0.0 to 1.0, see `urls::url_spam_score`
+    url_spam_score: f32,
+    // The languages of the result and the query were both detected, and
+    // differ
+    language_mismatch: bool,
+    // How many redirects lead from the result's URL to its final URL
+    redirect_depth: u8,
+    noindex_detected: bool,
+    soft404_suspected: bool,
+    paywalled: bool,
+    // The proportion of unique query terms matched in any field
+    term_coverage: f32,
+    // The number of unique query terms matched in any field
+    num_terms_matched: u8,
+}
+
+impl Features {
+    fn clamp(&mut self, clamps: &BTreeMap<String, Clamp>) {
+        for (name, clamp) in clamps {
+            if let Some(value) = self.value_mut(name) {
+                *value = clamp.apply(*value);
+            }
+        }
+    }
+
+    // The most unique query terms matched by any single field
+    fn num_matched_terms(&self) -> u8 {
+        [
+            &self.title_match,
+            &self.extract_match,
+            &self.domain_match,
+            &self.path_match,
+        ]
+        .iter()
+        .map(|field| field.num_terms)
+        .max()
+        .unwrap_or(0)
+    }
+}
+
+// Called with the stats of each ranking when it completes
+type RankCompleteCallback = Box<dyn Fn(&RankStats) -> Result<(), String>>;
+type ResultsChangedCallback = Box<dyn Fn(u32) -> Result<(), String>>;
+
+pub struct Ranker {
+    query: String,
+    intent: QueryIntent,
+    profile: String,
+    config: RankerConfig,
+    total_possible_match_length: u32,
+    num_unique_terms: u8,
+    query_regex: Regex,
+    query_terms: QueryTerms,
+    rewrite: QueryRewrite,
+    // The words of the results' titles and domains, collected while the query
+    // is a single term that may be several words typed without spaces
+    segment_vocabulary: HashSet<String>,
+    // The words that term splits into, which replace it in the rewrite
+    segments: Option<Vec<String>>,
+    // Numbers and ranges in the query, e.g. "under 500"
+    numeric_constraints: Vec<NumericConstraint>,
+    search_results: Vec<SearchResult>,
+    // The token positions of each search result, in the same order
+    token_positions: Vec<ResultPositions>,
+    // The title, extract, domain and path stats for BM25 scoring, built when
+    // first needed after the results or query terms change
+    bm25_stats: Cache<Option<[FieldStats; 4]>>,
+    revision: u32,
+    results_revision: u32,
+    on_results_changed: Option<ResultsChangedCallback>,
+    // The results revision not yet passed to `on_results_changed`'s callback
+    unnotified_results_revision: Cell<Option<u32>>,
+    on_rank_start: Option<Box<dyn Fn() -> Result<(), String>>>,
+    on_rank_complete: Option<RankCompleteCallback>,
+    // The first error returned by the rank callbacks since it was last taken
+    rank_callback_error: Cell<Option<String>>,
+    // The explanation of each search result in the most recent ranking, keyed
+    // by the index of the result
+    last_ranking: Cache<HashMap<usize, Explanation>>,
+    // The features and score contributions of each result scored so far by
+    // `rank_step`, in the order the results were added
+    step_scores: Cache<Vec<(Features, Waterfall, Option<f32>)>>,
+    // The language of the query, if it could be detected from the query alone
+    query_language: Option<Lang>,
+    query_embedding: Option<Vec<f32>>,
+    result_embeddings: Embeddings,
+    curations: Curations,
+    host_reputation: HostReputation,
+    domain_popularity: DomainPopularity,
+    domain_authority: DomainAuthority,
+    // Replaces the default scoring formula when loaded
+    model: Option<Model>,
+    // User defined adjustments applied to each score
+    score_rules: Vec<Rule>,
+    ingest_errors: Vec<IngestError>,
+    // The results the URL filter rejected, with the reason
+    filtered_out: Vec<(SearchResult, String)>,
+    last_stats: Cache<Option<RankStats>>,
+}
+
+impl Ranker {
+    // Construct a ranker using the weight profile for the detected query intent
+    pub fn new(query: &str) -> Ranker {
+        let profile = classify_intent(query).profile_name();
+        let config = RankerConfig::from_profile(profile).unwrap();
+        Ranker::with_config(query, config, profile)
+    }
+
+    // Construct a ranker with a bitset of `BehaviorFlag`s, e.g. to score with
+    // BM25
+    pub fn new_with_flags(query: &str, flags: u32) -> Ranker {
+        let mut ranker = Ranker::new(query);
+        ranker.set_flags(flags);
+        ranker
+    }
+
+    // Construct a ranker using one of the named weight profiles, e.g. "research",
+    // overriding the profile that would be picked from the query intent
+    pub fn new_with_profile(query: &str, profile: &str) -> Result<Ranker, String> {
+        match RankerConfig::from_profile(profile) {
+            Some(config) => Ok(Ranker::with_config(query, config, profile)),
+            None => Err(format!("Unknown profile: {}", profile)),
+        }
+    }
+
+    // The unique terms of the query and the bigrams of adjacent terms, to
+    // fetch results for
+    pub fn get_query_terms(&self) -> Vec<String> {
+        let tokens = &self.rewrite.terms;
+        let bigrams = tokens.windows(2).map(|pair| pair.join(" ")).collect::<Vec<String>>();
+        let unique_tokens = tokens.iter().map(|s| s.to_string()).collect::<HashSet<String>>();
+        let unique_bigrams = bigrams.into_iter().collect::<HashSet<String>>();
+        let mut terms = unique_tokens.into_iter().collect::<Vec<String>>();
+        terms.extend(unique_bigrams);
+        terms
+    }
+
+    // The terms and operators the query was rewritten to, with the terms after
+    // every stage of the rewrite
+    pub fn get_query_rewrite(&self) -> &QueryRewrite {
+        &self.rewrite
+    }
+
+    // Load a dictionary of multi-word entities, e.g. "new york times", which
+    // results must match as a whole rather than word by word
+    pub fn set_entities(&mut self, entities: Vec<String>) {
+        let config = RewriteConfig {
+            entities,
+            ..self.config.rewrite.clone()
+        };
+        self.set_query_rewrite(config);
+    }
+
+    // Configure which URLs are filtered out
+    pub fn set_url_filter(&mut self, filter: UrlFilter) {
+        self.config.url_filter = filter;
+        self.config_changed();
+    }
+
+    // Whether each URL would get past the URL filter, so candidates can be
+    // dropped before fetching their details
+    pub fn filter_urls(&self, urls: &[String]) -> Vec<bool> {
+        urls.iter()
+            .map(|url| self.config.url_filter.allows(url))
+            .collect()
+    }
+
+    // Add a search result, returning whether it was accepted. Results are
+    // rejected when filtered out by the URL filter, and in strict validation
+    // mode when invalid; see `get_ingest_errors`.
+    pub fn add_search_result(&mut self, url: &str, title: &str, extract: &str) -> bool {
+        self.add_result(url, title, extract, &ResultMetadata::default())
+    }
+
+    // As `add_search_result`, with a hint of the kind of page the result is:
+    // "article", "product", "recipe" or "video", or a schema.org type such as
+    // "NewsArticle". Other hints are ignored.
+    pub fn add_search_result_with_type(
+        &mut self,
+        url: &str,
+        title: &str,
+        extract: &str,
+        page_type: &str,
+    ) -> bool {
+        let metadata = ResultMetadata {
+            page_type: page_type.to_string(),
+            ..ResultMetadata::default()
+        };
+        self.add_result(url, title, extract, &metadata)
+    }
+
+    // As `add_search_result`, with optional details about the result, see
+    // `ResultMetadata`. Results are matched and deduplicated on the final URL,
+    // and displayed with the original. Flagged results are strongly demoted,
+    // and recent ones boosted as configured by `freshness`.
+    pub fn add_search_result_with_metadata(
+        &mut self,
+        url: &str,
+        title: &str,
+        extract: &str,
+        metadata: &ResultMetadata,
+    ) -> bool {
+        self.add_result(url, title, extract, metadata)
+    }
+
+    // Remove the search result at `index`, moving later results down by one.
+    // Returns false if there is no result at `index`.
+    pub fn remove_search_result(&mut self, index: usize) -> bool {
+        if index >= self.search_results.len() {
+            return false;
+        }
+        self.search_results.remove(index);
+        self.token_positions.remove(index);
+        self.result_embeddings.remove_row(index);
+        // Cached scores and explanations are keyed by index, so are now wrong
+        self.reset_steps();
+        self.last_ranking.write(|last_ranking| last_ranking.clear());
+        self.results_changed();
+        true
+    }
+
+    // What the query seems to be looking for, which picks the weight profile
+    // unless one was given. Navigational queries name a site, so its
+    // homepage should come first.
+    pub fn intent(&self) -> QueryIntent {
+        self.intent
+    }
+
+    // Incremented every time a search result is added or removed, so callers
+    // can tell whether a ranking is out of date
+    pub fn results_revision(&self) -> u32 {
+        self.results_revision
+    }
+
+    // Incremented on every change to the results or configuration that can
+    // affect the ranking. Every rank output includes the revision it was
+    // ranked at, so async callers can discard outputs older than `revision()`.
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    // Call `callback` with the new `results_revision` from
+    // `notify_results_changed` once search results have been added or
+    // removed, e.g. to re-rank with `rank_available` as results stream in
+    pub fn on_results_changed(
+        &mut self,
+        callback: impl Fn(u32) -> Result<(), String> + 'static,
+    ) {
+        self.on_results_changed = Some(Box::new(callback));
+    }
+
+    // Call `on_results_changed`'s callback if the results changed since it
+    // was last called, returning its error. The callback isn't called from
+    // the methods that change the results, as it can't rank while they
+    // borrow the ranker mutably, so callers should call this after them.
+    pub fn notify_results_changed(&self) -> Result<(), String> {
+        match (self.unnotified_results_revision.take(), &self.on_results_changed) {
+            (Some(revision), Some(callback)) => callback(revision),
+            _ => Ok(()),
+        }
+    }
+
+    // Call `callback` with no arguments when a ranking starts, and
+    // `on_rank_complete`'s callback with its `RankStats` when it finishes, so
+    // the frontend can measure ranking latency and candidate counts. Their
+    // errors don't stop the ranking, but are kept for `take_rank_callback_error`.
+    pub fn on_rank_start(&mut self, callback: impl Fn() -> Result<(), String> + 'static) {
+        self.on_rank_start = Some(Box::new(callback));
+    }
+
+    pub fn on_rank_complete(
+        &mut self,
+        callback: impl Fn(&RankStats) -> Result<(), String> + 'static,
+    ) {
+        self.on_rank_complete = Some(Box::new(callback));
+    }
+
+    // The first error returned by `on_rank_start`'s or `on_rank_complete`'s
+    // callback since this was last called, if any
+    pub fn take_rank_callback_error(&self) -> Option<String> {
+        self.rank_callback_error.take()
+    }
+
+    // Reject invalid search results instead of ingesting them as best we can
+    pub fn set_strict_validation(&mut self, strict: bool) {
+        self.config.strict_validation = strict;
+        self.config_changed();
+    }
+
+    // The URL of each search result rejected when added, and why
+    pub fn get_ingest_errors(&self) -> &[IngestError] {
+        &self.ingest_errors
+    }
+
+    pub fn len(&self) -> usize {
+        self.search_results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.search_results.is_empty()
+    }
+
+    // Turn scoring of result URLs on or off, e.g. off to rank notes or files
+    // rather than web pages, see `RankerConfig::score_urls`
+    pub fn set_score_urls(&mut self, score_urls: bool) {
+        let config = RankerConfig {
+            score_urls,
+            ..self.config.clone()
+        };
+        // The config's profile, if any, was checked when it was set
+        self.set_config(config).unwrap();
+    }
+
+    // Demote paywalled results, for users who prefer open access
+    pub fn set_prefer_open_access(&mut self, prefer_open_access: bool) {
+        self.config.prefer_open_access = prefer_open_access;
+        self.config_changed();
+    }
+
+    // Set the user's locale, e.g. "en-GB", to prefer results in their language
+    // among duplicate language variants
+    pub fn set_locale(&mut self, locale: &str) {
+        self.config.locale = locale.to_string();
+        self.config_changed();
+    }
+
+    // Set the bitset of `BehaviorFlag`s to rank with
+    pub fn set_flags(&mut self, flags: u32) {
+        self.config.flags = flags;
+        // Legacy scoring counts stopwords, so the query terms need updating
+        self.set_query_rewrite(self.config.rewrite.clone());
+    }
+
+    // Return the search results in the order of the rank, along with their
+    // scores and the intent and weight profile that were used to rank them
+    pub fn rank(&self) -> RankOutput<'_> {
+        self.rank_started();
+        let scored_results = self.ranked_results();
+        self.finish_rank(scored_results, false, false)
+    }
+
+    // As `rank`, but for results that are still being fetched: ranks the
+    // results added so far and marks the output as `partial`
+    pub fn rank_available(&self) -> RankOutput<'_> {
+        self.rank_started();
+        let scored_results = self.ranked_results();
+        let output = RankOutput {
+            partial: true,
+            ..self.rank_output(&scored_results, false, false)
+        };
+        self.publish_output(&output);
+        self.cache_ranking(scored_results);
+        output
+    }
+
+    // Rank the results and export them as a self-contained JSON bundle of the
+    // query, config, results and their scores and explanations, which the
+    // CLI can rank again to reproduce a user's bug report
+    pub fn export_serp(&self) -> String {
+        serde_json::to_string(&self.serp_bundle()).unwrap()
+    }
+
+    // As `rank`, but also include the features of each result
+    pub fn rank_with_explanations(&self) -> RankOutput<'_> {
+        self.rank_started();
+        let scored_results = self.ranked_results();
+        self.finish_rank(scored_results, true, false)
+    }
+
+    // As `rank`, but give up scoring after `budget_ms` milliseconds and return
+    // a best-effort ranking with `truncated` set
+    pub fn rank_with_budget(&self, budget_ms: f64) -> RankOutput<'_> {
+        self.rank_started();
+        let (scored_results, truncated) = self.ranked_results_with_budget(budget_ms);
+        self.finish_rank(scored_results, false, truncated)
+    }
+
+    // Set the embedding of the query, which must have the dimension of the
+    // result embeddings if they have been set
+    pub fn set_query_embedding(&mut self, embedding: Vec<f32>) -> Result<(), String> {
+        if embedding.is_empty() {
+            return Err("Query embedding is empty".to_string());
+        }
+        semantic::check_finite(&embedding)?;
+        let dim = self.result_embeddings.dim();
+        if dim != 0 && embedding.len() != dim {
+            return Err(format!(
+                "Query embedding has dimension {}, but result embeddings have {}",
+                embedding.len(),
+                dim
+            ));
+        }
+        self.query_embedding = Some(embedding);
+        self.config_changed();
+        Ok(())
+    }
+
+    // Use hashed bag-of-words embeddings when no embeddings have been provided
+    pub fn set_hashed_embeddings(&mut self, enabled: bool) {
+        self.config.hashed_embeddings = enabled;
+        self.config_changed();
+    }
+
+    // Clamp the named feature, e.g. "domain_score", to [min, max] before
+    // scoring. Either bound may be infinite to leave that side unbounded.
+    pub fn set_feature_clamp(&mut self, name: &str, min: f32, max: f32) -> Result<(), String> {
+        match features::definition(name) {
+            None => return Err(format!("Unknown feature: {}", name)),
+            Some(definition) if !definition.clampable() => {
+                return Err(format!("Feature can't be clamped: {}", name));
+            }
+            Some(_) => {}
+        }
+        let clamp = Clamp::new(min, max)?;
+        self.config.feature_clamps.insert(name.to_string(), clamp);
+        self.config_changed();
+        Ok(())
+    }
+
+    pub fn set_similarity_metric(&mut self, metric: SimilarityMetric) {
+        self.config.similarity_metric = metric;
+        self.config_changed();
+    }
+
+    // Set the embeddings of all search results at once from a single buffer,
+    // with a row of `dim` values per result in the order the results were
+    // added. `dim` must match the query embedding if it has been set.
+    pub fn set_result_embeddings(
+        &mut self,
+        embeddings: Vec<f32>,
+        dim: usize,
+    ) -> Result<(), String> {
+        let embeddings = Embeddings::new(embeddings, dim)?;
+        if let Some(query_embedding) = &self.query_embedding {
+            if query_embedding.len() != dim {
+                return Err(format!(
+                    "Result embeddings have dimension {}, but the query embedding has {}",
+                    dim,
+                    query_embedding.len()
+                ));
+            }
+        }
+        self.result_embeddings = embeddings;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Load a model to score with in place of the default formula. Ensemble
+    // models can also report how much their members disagree, see
+    // `RankerConfig::score_confidence`. Models violating the configured
+    // monotonic features are rejected.
+    //
+    // A model that misorders the built in sanity suite is not activated and
+    // the default formula stays in use; the returned warning explains why.
+    pub fn load_model(&mut self, model: Model) -> Result<Option<String>, String> {
+        model.validate(Features::is_known)?;
+        model.check_monotonicity(&self.config.monotonic_features)?;
+        if let Err(warning) = check_model_sanity(&model, &self.config) {
+            self.model = None;
+            self.config_changed();
+            return Ok(Some(warning));
+        }
+        self.model = Some(model);
+        self.config_changed();
+        Ok(None)
+    }
+
+    // Go back to scoring with the default formula
+    pub fn unload_model(&mut self) {
+        self.model = None;
+        self.config_changed();
+    }
+
+    // Adjust scores with rules such as `if domain ends_with ".edu" then * 1.2`,
+    // one per line. See the `rules` module for the full syntax. Replaces any
+    // previous rules; an empty string removes them all.
+    pub fn set_score_rules(&mut self, rules: &str) -> Result<(), String> {
+        self.score_rules = parse_rules(rules)?;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Always show the result with this URL first for this query. Results
+    // pinned later go below those pinned earlier.
+    pub fn pin_result(&mut self, url: &str) {
+        let key = self.curation_key();
+        self.curations.pin(&key, url);
+        self.config_changed();
+    }
+
+    // Never show the result with this URL for this query
+    pub fn exclude_result(&mut self, url: &str) {
+        let key = self.curation_key();
+        self.curations.exclude(&key, url);
+        self.config_changed();
+    }
+
+    // Undo pinning or excluding the result with this URL for this query
+    pub fn remove_curation(&mut self, url: &str) {
+        let key = self.curation_key();
+        self.curations.remove(&key, url);
+        self.config_changed();
+    }
+
+    // Serialize the pins and exclusions for all queries in a compact binary
+    // format that can be persisted and loaded with `import_curations`
+    pub fn export_curations(&self) -> Vec<u8> {
+        self.curations.to_bytes()
+    }
+
+    // Replace all pins and exclusions with those from `export_curations`
+    pub fn import_curations(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.curations = Curations::from_bytes(bytes)?;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Record that the user passed over the result with this URL for a lower
+    // one, which demotes results from its host in future rankings
+    pub fn record_skip(&mut self, url: &str) {
+        self.record_host_penalty(url, reputation::SKIP_PENALTY, utils::now_ms());
+    }
+
+    // Record that the user came straight back after opening the result with
+    // this URL, which demotes results from its host more than a skip
+    pub fn record_bounce(&mut self, url: &str) {
+        self.record_host_penalty(url, reputation::BOUNCE_PENALTY, utils::now_ms());
+    }
+
+    // Serialize the skips and bounces recorded for each host in a compact
+    // binary format that can be persisted and loaded with
+    // `import_host_reputation`
+    pub fn export_host_reputation(&self) -> Vec<u8> {
+        self.host_reputation.to_bytes(utils::now_ms())
+    }
+
+    pub fn import_host_reputation(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.host_reputation = HostReputation::from_bytes(bytes)?;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Load a table of domain popularity ranks, such as the Tranco list, to
+    // prefer popular domains over obscure ones, e.g. canonical sites over
+    // their mirrors. The table is a version byte, then the number of domains
+    // and each domain from most to least popular, all LEB128 length prefixed.
+    pub fn load_domain_popularity(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.domain_popularity = DomainPopularity::from_bytes(bytes)?;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Load the authority of domains in the link graph of the mwmbl crawl, to
+    // prefer domains that many others link to. The table is a version byte,
+    // then the number of domains, then each domain, LEB128 length prefixed,
+    // with its score as a little endian f64. Scores can be on any
+    // non-negative scale, such as PageRank, and are scaled to the highest.
+    pub fn load_domain_authority(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.domain_authority = DomainAuthority::from_bytes(bytes)?;
+        self.config_changed();
+        Ok(())
+    }
+
+    // Rank the other results by their similarity to the result at `index`,
+    // using the result embeddings if provided, otherwise token overlap
+    pub fn more_like_this(&self, index: usize) -> RankOutput<'_> {
+        let similar_results = self.similar_results(index);
+        let mut output = self.rank_output(&similar_results, false, false);
+        // Not a ranking of the query, so not comparable with the last one, and
+        // the results filtered out of that don't apply
+        output.stats.stability = None;
+        output.filtered.clear();
+        output
+    }
+
+    // Score up to `n` more results, returning true once every result has been
+    // scored. This lets the frontend interleave ranking with rendering, e.g.
+    // from requestIdleCallback, then call `rank_stepped` to get the ranking.
+    pub fn rank_step(&self, n: usize) -> bool {
+        self.score_step(n)
+    }
+
+    // Rank the results scored so far by `rank_step`. Results that haven't been
+    // scored yet follow the ranked ones, and `truncated` is set.
+    pub fn rank_stepped(&self) -> RankOutput<'_> {
+        self.rank_started();
+        let (scored_results, truncated) = self.stepped_results();
+        self.finish_rank(scored_results, false, truncated)
+    }
+
+    // Discard the scores from `rank_step` so the next step starts over
+    pub fn reset_steps(&self) {
+        self.step_scores.write(|step_scores| step_scores.clear());
+    }
+
+    // The stats, including data quality anomaly counts, of the most recent
+    // ranking, or None if nothing has been ranked yet
+    pub fn last_rank_stats(&self) -> Option<RankStats> {
+        self.last_stats.read(|last_stats| last_stats.clone())
+    }
+
+    // Explain the score of the search result that was added at `index` in the
+    // most recent ranking, or None if it hasn't been ranked
+    pub fn explain(&self, index: usize) -> Option<Explanation> {
+        self.last_ranking
+            .read(|last_ranking| last_ranking.get(&index).cloned())
+    }
+
+    // Summarise the quality of the most recent ranking, which is empty before
+    // the first one
+    pub fn serp_quality(&self) -> SerpQuality {
+        self.last_ranking.read(|last_ranking| {
+            let mut ranked = last_ranking
+                .iter()
+                .map(|(&index, explanation)| (&self.search_results[index], explanation))
+                .collect::<Vec<(&SearchResult, &Explanation)>>();
+            ranked.sort_by_key(|(_, explanation)| explanation.position);
+            SerpQuality::new(&ranked)
+        })
+    }
+
+    // Explain every result in the most recent ranking in one call, optionally
+    // only the top `top_k`, as a feature matrix plus score contributions
+    pub fn explain_all(&self, top_k: Option<usize>) -> ExplainAll {
+        let mut results = self.last_ranking.read(|last_ranking| {
+            last_ranking
+                .iter()
+                .map(|(&index, explanation)| ExplainedResult {
+                    index,
+                    position: explanation.position,
+                    score: explanation.score,
+                    features: explanation.features.values(),
+                    contributions: explanation.contributions.clone(),
+                    curated: explanation.curated,
+                })
+                .collect::<Vec<ExplainedResult>>()
+        });
+        results.sort_by_key(|result| result.position);
+        results.truncate(top_k.unwrap_or(usize::MAX));
+        ExplainAll {
+            feature_names: features::FEATURES.iter().map(|d| d.name).collect(),
+            results,
+        }
+    }
+
+    // A plain text explanation of every result in the most recent ranking, in
+    // ranked order, for snapshot tests of ranking behavior
+    pub fn render_explanation_text(&self) -> String {
+        let mut explanations = self.last_ranking.read(|last_ranking| {
+            last_ranking
+                .iter()
+                .map(|(&index, explanation)| (index, explanation.clone()))
+                .collect::<Vec<(usize, Explanation)>>()
+        });
+        explanations.sort_by_key(|(_, explanation)| explanation.position);
+        explanations
+            .iter()
+            .map(|(index, explanation)| explanation.render_text(&self.search_results[*index].url))
+            .collect()
+    }
+
+    // The spans of the title and extract of the search result at `index` that
+    // match the query, in UTF-16 code units
+    pub fn highlights(&self, index: usize) -> Option<Highlights> {
+        let fold_plurals = self.config.rewrite.fold_plurals;
+        let result = self.search_results.get(index)?;
+        Some(Highlights {
+            title: highlight_spans(&self.query_regex, &result.title, fold_plurals),
+            extract: highlight_spans(&self.query_regex, &result.extract, fold_plurals),
+        })
+    }
+}
+
+impl Ranker {
+    // A ranker with the query, config and results of a bundle from
+    // `export_serp`
+    pub fn from_bundle(bundle: &SerpBundle) -> Ranker {
+        let mut ranker = Ranker::with_config(&bundle.query, bundle.config.clone(), &bundle.profile);
+        for result in &bundle.results {
+            ranker.add_result(&result.url, &result.title, &result.extract, &result.metadata);
+        }
+        ranker
+    }
+
+    fn serp_bundle(&self) -> SerpBundle {
+        let mut ranked = self
+            .ranked_results()
+            .into_iter()
+            .enumerate()
+            .map(|(position, scored)| (scored.index, (position, scored)))
+            .collect::<HashMap<usize, (usize, ScoredResult)>>();
+        let results = self
+            .search_results
+            .iter()
+            .enumerate()
+            .map(|(index, result)| {
+                let (position, scored) = match ranked.remove(&index) {
+                    Some((position, scored)) => (Some(position), scored),
+                    None => (None, self.score_at(index)),
+                };
+                BundledResult {
+                    url: result.url.to_string(),
+                    title: result.title.to_string(),
+                    extract: result.extract.to_string(),
+                    metadata: result.metadata(),
+                    position,
+                    score: scored.score,
+                    features: features::FEATURES
+                        .iter()
+                        .map(|definition| definition.name.to_string())
+                        .zip(scored.features.values())
+                        .collect(),
+                    contributions: scored.contributions.steps,
+                }
+            })
+            .collect();
+        SerpBundle {
+            version: serp::BUNDLE_VERSION,
+            api_version: api_version(),
+            query: self.query.clone(),
+            profile: self.profile.clone(),
+            config: self.config.clone(),
+            config_hash: serp::config_hash(&self.config),
+            model_version: self.model.as_ref().map(|model| model.version().to_string()),
+            results,
+        }
+    }
+
+    fn with_config(query: &str, config: RankerConfig, profile: &str) -> Ranker {
+        let rewrite = rewrite::rewrite_query(query, &config.rewrite, MAX_QUERY_TERMS);
+        // Legacy scoring counts stopwords like any other term
+        let query_terms = QueryTerms::new(
+            &rewrite,
+            &config.rewrite,
+            !config.has_flag(BehaviorFlag::LegacyScoring),
+        );
+        let (query_regex, num_unique_terms, total_possible_match_length) =
+            get_query_regex(&rewrite.terms, config.rewrite.stemming, &query_terms.stopwords);
+        Ranker {
+            query: query.to_string(),
+            intent: classify_intent(query),
+            profile: profile.to_string(),
+            config,
+            total_possible_match_length,
+            num_unique_terms,
+            query_regex,
+            query_terms,
+            rewrite,
+            segment_vocabulary: HashSet::new(),
+            segments: None,
+            numeric_constraints: numeric::query_constraints(query),
+            search_results: Vec::new(),
+            token_positions: Vec::new(),
+            bm25_stats: Cache::default(),
+            revision: 0,
+            results_revision: 0,
+            on_results_changed: None,
+            unnotified_results_revision: Cell::new(None),
+            on_rank_start: None,
+            on_rank_complete: None,
+            rank_callback_error: Cell::new(None),
+            last_ranking: Cache::default(),
+            step_scores: Cache::default(),
+            query_language: language::detect_language(query),
+            query_embedding: None,
+            result_embeddings: Embeddings::default(),
+            curations: Curations::default(),
+            host_reputation: HostReputation::default(),
+            domain_popularity: DomainPopularity::default(),
+            domain_authority: DomainAuthority::default(),
+            model: None,
+            score_rules: Vec::new(),
+            ingest_errors: Vec::new(),
+            filtered_out: Vec::new(),
+            last_stats: Cache::default(),
+        }
+    }
+
+    // Start a new revision after a change to how results are ranked
+    fn config_changed(&mut self) {
+        self.revision += 1;
+        self.reset_steps();
+    }
+
+    fn results_changed(&mut self) {
+        self.bm25_stats.write(|stats| *stats = None);
+        self.revision += 1;
+        self.results_revision += 1;
+        self.unnotified_results_revision.set(Some(self.results_revision));
+    }
+
+    fn rank_started(&self) {
+        if let Some(callback) = &self.on_rank_start {
+            self.record_rank_callback_result(callback());
+        }
+    }
+
+    fn record_rank_callback_result(&self, result: Result<(), String>) {
+        if let Err(error) = result {
+            let first = self.rank_callback_error.take().unwrap_or(error);
+            self.rank_callback_error.set(Some(first));
+        }
+    }
+
+    // Build the output of the ranking, and cache it and its stats for
+    // `explain` and `last_rank_stats`
+    fn finish_rank<'a>(
+        &'a self,
+        scored_results: Vec<ScoredResult<'a>>,
+        explain: bool,
+        truncated: bool,
+    ) -> RankOutput<'a> {
+        let output = self.rank_output(&scored_results, explain, truncated);
+        self.publish_output(&output);
+        self.cache_ranking(scored_results);
+        output
+    }
+
+    // Keep the stats of the output for `last_rank_stats` and pass them to the
+    // `on_rank_complete` callback
+    fn publish_output(&self, output: &RankOutput) {
+        self.last_stats
+            .write(|last_stats| *last_stats = Some(output.stats.clone()));
+        if let Some(callback) = &self.on_rank_complete {
+            self.record_rank_callback_result(callback(&output.stats));
+        }
+    }
+
+    fn cache_ranking(&self, scored_results: Vec<ScoredResult>) {
+        let ranking = scored_results
+            .into_iter()
+            .enumerate()
+            .map(|(position, scored)| {
+                (
+                    scored.index,
+                    Explanation {
+                        position,
+                        score: scored.score,
+                        features: scored.features,
+                        contributions: scored.contributions,
+                        curated: scored.curated,
+                    },
+                )
+            })
+            .collect();
+        self.last_ranking.write(|last_ranking| *last_ranking = ranking);
+    }
+
+    // How much the order of the results changed since the last ranking, as
+    // Kendall's tau over the results in both
+    fn ranking_stability(&self, scored_results: &[ScoredResult]) -> Option<f32> {
+        let positions = self.last_ranking.read(|last_ranking| {
+            scored_results
+                .iter()
+                .enumerate()
+                .filter_map(|(position, scored)| {
+                    let previous = last_ranking.get(&scored.index)?;
+                    Some((previous.position, position))
+                })
+                .collect::<Vec<(usize, usize)>>()
+        });
+        stability::kendall_tau(&positions)
+    }
+
+    // Replace the match score of each field with its BM25 score
+    fn set_bm25_scores(&self, positions: &ResultPositions, features: &mut Features) {
+        self.bm25_stats.write(|stats| {
+            let [title, extract, domain, path] = stats.get_or_insert_with(|| {
+                let terms = &self.rewrite.terms;
+                let all = &self.token_positions;
+                [
+                    FieldStats::new(all.iter().map(|positions| &positions.title), terms),
+                    FieldStats::new(all.iter().map(|positions| &positions.extract), terms),
+                    FieldStats::new(all.iter().map(|positions| &positions.domain), terms),
+                    FieldStats::new(all.iter().map(|positions| &positions.path), terms),
+                ]
+            });
+            let params = &self.config.bm25;
+            features.title_match.score = title.score(&positions.title, params.title);
+            features.extract_match.score = extract.score(&positions.extract, params.extract);
+            features.domain_match.score = domain.score(&positions.domain, params.domain);
+            features.path_match.score = path.score(&positions.path, params.path);
+        });
+    }
+
+    fn score_at(&self, index: usize) -> ScoredResult<'_> {
+        let result = &self.search_results[index];
+        // A query of only operators, such as "!g", leaves nothing to match,
+        // so every result scores zero and keeps the backend's order
+        if self.rewrite.terms.is_empty() {
+            return ScoredResult {
+                index,
+                result,
+                features: Features::default(),
+                score: 0.0,
+                contributions: Waterfall::single("no_terms", 0.0),
+                score_std: None,
+                curated: false,
+            };
+        }
+        let mut features = get_features(
+            &self.query_regex,
+            *result,
+            self.total_possible_match_length,
+            self.num_unique_terms,
+            &self.query_terms,
+            &self.config,
+        );
+        features.semantic_similarity = self.semantic_similarity(index);
+        let positions = &self.token_positions[index];
+        if self.config.has_flag(BehaviorFlag::Bm25Scoring)
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            self.set_bm25_scores(positions, &mut features);
+        }
+        for (field, first_position) in [
+            (&positions.title, &mut features.title_first_position),
+            (&positions.extract, &mut features.extract_first_position),
+        ] {
+            *first_position = field
+                .first_position(&self.rewrite.terms)
+                .unwrap_or_else(|| field.num_tokens()) as f32;
+        }
+        if self.rewrite.terms.len() > 1 {
+            let phrase = self.rewrite.terms.join(" ");
+            features.title_phrase_match = !positions.title.term_positions(&phrase).is_empty();
+            features.extract_phrase_match = !positions.extract.term_positions(&phrase).is_empty();
+        }
+        features.extract_clustering = positions.extract.clustering(&self.rewrite.terms);
+        // Synonyms are added after the terms, so their order means nothing
+        let ordered_terms = self
+            .query_terms
+            .scored
+            .iter()
+            .filter(|term| !self.query_terms.synonyms.contains(*term))
+            .cloned()
+            .collect::<Vec<String>>();
+        let query = self
+            .rewrite
+            .terms
+            .iter()
+            .filter(|term| !self.query_terms.synonyms.contains(*term))
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ");
+        for (field, match_features) in [
+            (&positions.title, &mut features.title_match),
+            (&positions.extract, &mut features.extract_match),
+            (&positions.domain, &mut features.domain_match),
+            (&positions.path, &mut features.path_match),
+        ] {
+            match_features.proximity = field.proximity(&self.rewrite.terms);
+            match_features.in_order = field.in_order(&ordered_terms);
+            match_features.starts_with = if !query.is_empty() && field.starts_with(&query) {
+                1.0
+            } else if ordered_terms.first().is_some_and(|term| field.starts_with(term)) {
+                0.5
+            } else {
+                0.0
+            };
+        }
+        if !self.numeric_constraints.is_empty() {
+            features.title_numeric_match =
+                numeric::matches_constraints(&self.numeric_constraints, &result.title);
+            features.extract_numeric_match =
+                numeric::matches_constraints(&self.numeric_constraints, &result.extract);
+        }
+        if let Some(date) = result.date {
+            features.has_date = true;
+            features.age_days = date.age_days(utils::now_ms());
+            features.recency = 0.5f32.powf(features.age_days / FRESHNESS_HALF_LIFE_DAYS);
+        }
+        if !self.host_reputation.is_empty()
+            && self.config.score_urls
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            let (host, _, _) = urls::domain_and_path(result.match_url());
+            features.host_penalty = self.host_reputation.penalty(&host, utils::now_ms()) as f32;
+        }
+        if !self.domain_popularity.is_empty()
+            && self.config.score_urls
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            let (host, _, _) = urls::domain_and_path(result.match_url());
+            features.domain_popularity = self.domain_popularity.popularity(&host);
+        }
+        if !self.domain_authority.is_empty()
+            && self.config.score_urls
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            let (host, _, _) = urls::domain_and_path(result.match_url());
+            features.domain_authority = self.domain_authority.authority(&host);
+        }
+        let query_language = self
+            .query_language
+            .or_else(|| language::locale_language(&self.config.locale));
+        if let (Some(query_language), Some(result_language)) = (query_language, result.language) {
+            features.language_mismatch = query_language != result_language;
+        }
+        if !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            features.clamp(&self.config.feature_clamps);
+        }
+        let (mut contributions, score_std) = match &self.model {
+            Some(model) if !self.config.has_flag(BehaviorFlag::LegacyScoring) => {
+                let values = model
+                    .features()
+                    .iter()
+                    .map(|name| features.value(name).unwrap_or(0.0))
+                    .collect::<Vec<f32>>();
+                let (score, score_std) = model.predict_with_std(&values);
+                (Waterfall::single("model", score), score_std)
+            }
+            _ => (score_contributions(&features, result, &self.config), None),
+        };
+        if !self.score_rules.is_empty() && !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            let (domain, path, _) = urls::domain_and_path(result.match_url());
+            let context = RuleContext {
+                url: result.match_url(),
+                domain: &domain,
+                path: &path,
+                title: &result.title,
+                extract: &result.extract,
+            };
+            apply_rules(&self.score_rules, &context, &mut contributions);
+        }
+        ScoredResult {
+            index,
+            result,
+            features,
+            score: contributions.total(),
+            contributions,
+            score_std,
+            curated: false,
+        }
+    }
+
+    fn record_host_penalty(&mut self, url: &str, penalty: f64, now_ms: f64) {
+        let (host, _, _) = urls::domain_and_path(url);
+        if !host.is_empty() {
+            self.host_reputation.record(&host, penalty, now_ms);
+            self.config_changed();
+        }
+    }
+
+    fn add_result(
+        &mut self,
+        url: &str,
+        title: &str,
+        extract: &str,
+        metadata: &ResultMetadata,
+    ) -> bool {
+        let filtered = std::iter::once(url)
+            .chain(metadata.final_url.as_deref())
+            .find_map(|url| self.config.url_filter.check(url));
+        if let Some(reason) = filtered {
+            let result = SearchResult::new(url, title, extract);
+            self.filtered_out.push((result, reason.clone()));
+            self.ingest_errors.push(IngestError {
+                url: url.to_string(),
+                reason,
+            });
+            return false;
+        }
+        if self.config.strict_validation {
+            if let Err(reason) = validation::validate_result(url, title, extract) {
+                self.ingest_errors.push(IngestError {
+                    url: url.to_string(),
+                    reason,
+                });
+                return false;
+            }
+        }
+        let final_url = metadata
+            .final_url
+            .as_deref()
+            .map(|final_url| ArrayString::from(shorten_string(final_url, MAX_URL_LENGTH)).unwrap());
+        let published_date = metadata.published_date.as_deref().and_then(dates::parse_iso_date);
+        let mut result = SearchResult {
+            page_type: PageType::from_hint(&metadata.page_type),
+            final_url,
+            redirect_depth: metadata.redirect_depth,
+            noindex_detected: metadata.noindex_detected,
+            soft404_suspected: metadata.soft404_suspected,
+            paywalled: metadata.paywalled,
+            ..SearchResult::new(url, title, extract)
+        };
+        if published_date.is_some() {
+            result.date = published_date;
+            result.date_published = true;
+        }
+        self.update_segments(&result);
+        self.token_positions.push(self.index_positions(&result));
+        self.search_results.push(result);
+        self.results_changed();
+        true
+    }
+
+    // Score a result on its own in a ranker with no results, then remove it
+    // again, along with the words it added for segmenting the query. None if
+    // the result is filtered out.
+    fn score_alone(&mut self, result: &alerts::CandidateResult) -> Option<f32> {
+        let added = self.add_result(&result.url, &result.title, &result.extract, &result.metadata);
+        let score = if added {
+            Some(self.score_at(self.search_results.len() - 1).score)
+        } else {
+            None
+        };
+        self.search_results.clear();
+        self.token_positions.clear();
+        self.ingest_errors.clear();
+        self.filtered_out.clear();
+        self.segment_vocabulary.clear();
+        if self.segments.take().is_some() {
+            self.set_query_rewrite(self.config.rewrite.clone());
+        }
+        self.results_changed();
+        score
+    }
+
+    // Replace the whole configuration. The profile becomes the one the config
+    // pins, or "custom" if it doesn't pin one.
+    pub fn set_config(&mut self, config: RankerConfig) -> Result<(), String> {
+        let (config, profile) = config.apply_pinned_profile()?;
+        let reindex = config.score_urls != self.config.score_urls;
+        let rewrite = config.rewrite.clone();
+        self.config = RankerConfig {
+            rewrite: self.config.rewrite.clone(),
+            ..config
+        };
+        self.profile = profile;
+        if reindex {
+            self.reindex_positions();
+        }
+        self.set_query_rewrite(rewrite);
+        Ok(())
+    }
+
+    fn index_positions(&self, result: &SearchResult) -> ResultPositions {
+        let fold_plurals = self.config.rewrite.fold_plurals;
+        let (domain, path, _) = scored_domain_and_path(result, &self.config);
+        ResultPositions {
+            title: TokenPositions::new(&matchable_text(&result.title, fold_plurals)),
+            extract: TokenPositions::new(&matchable_text(&result.extract, fold_plurals)),
+            domain: TokenPositions::new(&matchable_text(&domain, fold_plurals)),
+            path: TokenPositions::new(&matchable_text(&path, fold_plurals)),
+        }
+    }
+
+    // Load a synonym table from each term to the terms it should also match,
+    // e.g. "js" to "javascript". Matches of the synonyms score less than
+    // matches of the query terms, see `synonym_discount`. The synonyms are
+    // lowercased to match the rewritten terms, and the synonyms stage is
+    // added if it isn't configured.
+    pub fn load_synonyms(&mut self, synonyms: BTreeMap<String, Vec<String>>) {
+        let mut config = RewriteConfig {
+            synonyms: synonyms
+                .into_iter()
+                .map(|(term, synonyms)| {
+                    let synonyms = synonyms.iter().map(|synonym| synonym.to_lowercase());
+                    (term.to_lowercase(), synonyms.collect())
+                })
+                .collect(),
+            ..self.config.rewrite.clone()
+        };
+        if !config.stages.contains(&RewriteStage::Synonyms) {
+            config.stages.push(RewriteStage::Synonyms);
+        }
+        self.set_query_rewrite(config);
+    }
+
+    // Configure the query rewrite, then rewrite the query again
+    pub fn set_query_rewrite(&mut self, config: RewriteConfig) {
+        let reindex = config.fold_plurals != self.config.rewrite.fold_plurals;
+        self.rewrite = rewrite::rewrite_query(&self.query, &config, MAX_QUERY_TERMS);
+        let segmented = self.segments.as_ref().filter(|segments| {
+            config.segment_terms
+                && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+                && self.rewrite.terms == [segments.concat()]
+        });
+        match segmented {
+            Some(segments) => self.rewrite = self.rewrite.segmented(segments),
+            None => self.segments = None,
+        }
+        self.query_terms = QueryTerms::new(
+            &self.rewrite,
+            &config,
+            !self.config.has_flag(BehaviorFlag::LegacyScoring),
+        );
+        let (query_regex, num_unique_terms, total_possible_match_length) =
+            get_query_regex(&self.rewrite.terms, config.stemming, &self.query_terms.stopwords);
+        self.query_regex = query_regex;
+        self.num_unique_terms = num_unique_terms;
+        self.total_possible_match_length = total_possible_match_length;
+        self.config.rewrite = config;
+        self.bm25_stats.write(|stats| *stats = None);
+        if reindex {
+            self.reindex_positions();
+        }
+        self.config_changed();
+    }
+
+    // The query's only term, if it is long enough to be several words typed
+    // without spaces
+    fn segmentable_term(&self) -> Option<String> {
+        if let Some(segments) = &self.segments {
+            return Some(segments.concat());
+        }
+        match self.rewrite.terms.as_slice() {
+            [term]
+                if self.config.rewrite.segment_terms
+                    && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+                    && term.chars().count() >= rewrite::MIN_SEGMENTED_LENGTH
+                    && term.chars().all(char::is_alphanumeric) =>
+            {
+                Some(term.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // Split the query term into words found in the results so far, unless
+    // the term itself is found, and rewrite the query if that changes
+    fn update_segments(&mut self, result: &SearchResult) {
+        let term = match self.segmentable_term() {
+            Some(term) => term,
+            None => return,
+        };
+        let (domain, _, _) = urls::domain_and_path(result.match_url());
+        let text = format!("{} {}", result.title, domain);
+        let tokens = tokenizer::tokenize(&text, &TokenizeOptions::default());
+        self.segment_vocabulary
+            .extend(tokens.into_iter().map(|token| token.text));
+        let segments = if self.segment_vocabulary.contains(&term) {
+            None
+        } else {
+            rewrite::segment_term(&term, &self.segment_vocabulary)
+        };
+        if segments != self.segments {
+            self.segments = segments;
+            self.set_query_rewrite(self.config.rewrite.clone());
+        }
+    }
+
+    fn reindex_positions(&mut self) {
+        self.token_positions = self
+            .search_results
+            .iter()
+            .map(|result| self.index_positions(result))
+            .collect();
+    }
+
+    fn semantic_similarity(&self, index: usize) -> f32 {
+        match (&self.query_embedding, self.result_embeddings.row(index)) {
+            (Some(query_embedding), Some(result_embedding))
+                if query_embedding.len() == result_embedding.len() =>
+            {
+                semantic::similarity(
+                    self.config.similarity_metric,
+                    query_embedding,
+                    result_embedding,
+                )
+            }
+            (None, None) if self.config.hashed_embeddings => {
+                let result = &self.search_results[index];
+                let text = format!("{} {}", result.title, result.extract);
+                semantic::similarity(
+                    self.config.similarity_metric,
+                    &semantic::hashed_embedding(&self.query, HASHED_EMBEDDING_DIM),
+                    &semantic::hashed_embedding(&text, HASHED_EMBEDDING_DIM),
+                )
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn similar_results(&self, index: usize) -> Vec<ScoredResult<'_>> {
+        let result_tokens = |result: &SearchResult| {
+            query_tokens(&format!("{} {}", result.title, result.extract))
+                .into_iter()
+                .collect::<HashSet<String>>()
+        };
+        let target = match self.search_results.get(index) {
+            Some(target) => target,
+            None => return Vec::new(),
+        };
+        let target_embedding = self.result_embeddings.row(index);
+        let target_tokens = result_tokens(target);
+
+        let mut similar_results = self
+            .search_results
+            .iter()
+            .enumerate()
+            .filter(|(other_index, _)| *other_index != index)
+            .map(|(other_index, result)| {
+                let score = match (target_embedding, self.result_embeddings.row(other_index)) {
+                    (Some(target_embedding), Some(embedding)) => semantic::similarity(
+                        self.config.similarity_metric,
+                        target_embedding,
+                        embedding,
+                    ),
+                    _ => {
+                        let tokens = result_tokens(result);
+                        let union = target_tokens.union(&tokens).count();
+                        if union == 0 {
+                            0.0
+                        } else {
+                            target_tokens.intersection(&tokens).count() as f32 / union as f32
+                        }
+                    }
+                };
+                ScoredResult {
+                    index: other_index,
+                    result,
+                    features: Features::default(),
+                    score,
+                    contributions: Waterfall::single("similarity", score),
+                    score_std: None,
+                    curated: false,
+                }
+            })
+            .collect::<Vec<ScoredResult>>();
+        similar_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        similar_results
+    }
+
+    fn order_results<'a>(
+        &self,
+        mut scored_results: Vec<ScoredResult<'a>>,
+    ) -> Vec<ScoredResult<'a>> {
+        if self.config.sort_ties_by_title && !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            let mut collator = TitleCollator::new(&self.config.locale);
+            scored_results.sort_by(|a, b| {
+                b.score
+                    .total_cmp(&a.score)
+                    .then_with(|| collator.compare(&a.result.title, &b.result.title))
+            });
+        } else {
+            scored_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        if !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            if self.config.drop_low_coverage {
+                let min_term_coverage = self.config.min_term_coverage;
+                scored_results.retain(|scored| scored.features.term_coverage >= min_term_coverage);
+            }
+            scored_results.retain(|scored| self.operator_filter_reason(scored.result).is_none());
+            dedup::remove_redirect_duplicates(&mut scored_results);
+        }
+        if self.config.dedup_locale_variants && !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            dedup::remove_locale_duplicates(&mut scored_results, &self.config.locale);
+        }
+        if self.config.diversity > 0.0
+            && self.config.score_urls
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            apply_diversity(&mut scored_results, self.config.diversity);
+        }
+        // The points of each result in the fused ranking, by index
+        let mut fused_points = None;
+        if self.config.backend_rank_weight > 0.0
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            fused_points = Some(fuse_backend_rank(
+                &mut scored_results,
+                self.config.backend_rank_weight,
+            ));
+        }
+        if self.config.hysteresis_margin > 0.0
+            && !self.config.has_flag(BehaviorFlag::LegacyScoring)
+        {
+            let value = |scored: &ScoredResult| match &fused_points {
+                Some(points) => points[&scored.index],
+                None => scored.score,
+            };
+            self.last_ranking.read(|last_ranking| {
+                apply_hysteresis(
+                    &mut scored_results,
+                    last_ranking,
+                    self.config.hysteresis_margin,
+                    value,
+                )
+            });
+        }
+        if !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            slotting::apply_slot_rules(&mut scored_results, &self.config.slot_rules);
+        }
+        scored_results
+    }
+
+    fn ranked_results(&self) -> Vec<ScoredResult<'_>> {
+        let scored_results = (0..self.search_results.len())
+            .map(|index| self.score_at(index))
+            .collect();
+        self.apply_curation(self.order_results(scored_results))
+    }
+
+    // The key of this ranker's query in the curations
+    fn curation_key(&self) -> String {
+        query_tokens(&self.query).join(" ")
+    }
+
+    // Move pinned results to the top in the order they were pinned, with
+    // `CURATED_SCORE` in place of their own, and remove excluded results
+    fn apply_curation<'a>(
+        &self,
+        ranked_results: Vec<ScoredResult<'a>>,
+    ) -> Vec<ScoredResult<'a>> {
+        let curation = match self.curations.get(&self.curation_key()) {
+            Some(curation) => curation,
+            None => return ranked_results,
+        };
+        let pin_position = |scored: &ScoredResult| {
+            curation
+                .pins
+                .iter()
+                .position(|url| url == scored.result.url.as_str())
+        };
+        let (mut pinned, unpinned): (Vec<ScoredResult>, Vec<ScoredResult>) = ranked_results
+            .into_iter()
+            .filter(|scored| {
+                !curation
+                    .exclusions
+                    .iter()
+                    .any(|url| url == scored.result.url.as_str())
+            })
+            .partition(|scored| pin_position(scored).is_some());
+        pinned.sort_by_key(pin_position);
+        for scored in &mut pinned {
+            scored.score = CURATED_SCORE;
+            scored.curated = true;
+        }
+        pinned.extend(unpinned);
+        pinned
+    }
+
+    // Rank the results, stopping scoring once `budget_ms` has passed. Results
+    // that weren't scored in time follow the ranked ones in the order they
+    // were added, with a score of zero. Also returns whether that happened.
+    fn ranked_results_with_budget(&self, budget_ms: f64) -> (Vec<ScoredResult<'_>>, bool) {
+        let start = utils::now_ms();
+        let mut scored_results = Vec::with_capacity(self.search_results.len());
+        for index in 0..self.search_results.len() {
+            if index > 0
+                && index % BUDGET_CHECK_INTERVAL == 0
+                && utils::now_ms() - start >= budget_ms
+            {
+                break;
+            }
+            scored_results.push(self.score_at(index));
+        }
+        self.order_partially_scored(scored_results)
+    }
+
+    // Order the scored results, which must be the first results that were
+    // added, followed by the rest in the order they were added with a score of
+    // zero. Also returns whether any results were left unscored.
+    fn order_partially_scored<'a>(
+        &'a self,
+        scored_results: Vec<ScoredResult<'a>>,
+    ) -> (Vec<ScoredResult<'a>>, bool) {
+        let num_scored = scored_results.len();
+        let mut ranked_results = self.order_results(scored_results);
+        ranked_results.extend((num_scored..self.search_results.len()).map(|index| ScoredResult {
+            index,
+            result: &self.search_results[index],
+            features: Features::default(),
+            score: 0.0,
+            contributions: Waterfall::default(),
+            score_std: None,
+            curated: false,
+        }));
+        (
+            self.apply_curation(ranked_results),
+            num_scored < self.search_results.len(),
+        )
+    }
+
+    // Score up to `n` more results for a chunked ranking, returning whether
+    // every result has now been scored
+    fn score_step(&self, n: usize) -> bool {
+        let start = self.step_scores.read(|step_scores| step_scores.len());
+        let end = usize::min(start + n, self.search_results.len());
+        let scores = (start..end)
+            .map(|index| {
+                let scored = self.score_at(index);
+                (scored.features, scored.contributions, scored.score_std)
+            })
+            .collect::<Vec<(Features, Waterfall, Option<f32>)>>();
+        self.step_scores
+            .write(|step_scores| step_scores.extend(scores));
+        end == self.search_results.len()
+    }
+
+    fn stepped_results(&self) -> (Vec<ScoredResult<'_>>, bool) {
+        let scored_results = self.step_scores.read(|step_scores| {
+            step_scores
+                .iter()
+                .enumerate()
+                .map(|(index, (features, contributions, score_std))| ScoredResult {
+                    index,
+                    result: &self.search_results[index],
+                    features: features.clone(),
+                    score: contributions.total(),
+                    contributions: contributions.clone(),
+                    score_std: *score_std,
+                    curated: false,
+                })
+                .collect()
+        });
+        self.order_partially_scored(scored_results)
+    }
+
+    fn rank_output<'a>(
+        &'a self,
+        scored_results: &[ScoredResult<'a>],
+        explain: bool,
+        truncated: bool,
+    ) -> RankOutput<'a> {
+        RankOutput {
+            intent: self.intent,
+            profile: &self.profile,
+            truncated,
+            partial: false,
+            revision: self.revision,
+            results: scored_results.iter().map(|scored| scored.result).collect(),
+            scores: scored_results.iter().map(|scored| scored.score).collect(),
+            safety: scored_results
+                .iter()
+                .map(|scored| {
+                    let filter = &self.config.url_filter;
+                    filter
+                        .classify(&scored.result.url)
+                        .max(filter.classify(scored.result.match_url()))
+                })
+                .collect(),
+            stats: RankStats {
+                stability: self.ranking_stability(scored_results),
+                ..RankStats::new(scored_results, truncated)
+            },
+            explanations: if explain {
+                let features = scored_results.iter().map(|scored| scored.features.clone());
+                Some(features.map(FeatureValues).collect())
+            } else {
+                None
+            },
+            score_stds: if self.config.score_confidence
+                && scored_results.iter().any(|scored| scored.score_std.is_some())
+            {
+                Some(scored_results.iter().map(|scored| scored.score_std.unwrap_or(0.0)).collect())
+            } else {
+                None
+            },
+            filtered: self.filtered_results(scored_results, truncated),
+        }
+    }
+
+    // The results the URL filter rejected and, unless scoring was cut short,
+    // those `drop_low_coverage` left out of the ranking, if `return_filtered`
+    // is set
+    fn filtered_results(
+        &self,
+        scored_results: &[ScoredResult],
+        truncated: bool,
+    ) -> Vec<FilteredResult<'_>> {
+        if !self.config.return_filtered {
+            return Vec::new();
+        }
+        let mut filtered = self
+            .filtered_out
+            .iter()
+            .map(|(result, reason)| FilteredResult {
+                result,
+                reason: reason.clone(),
+            })
+            .collect::<Vec<FilteredResult>>();
+        if !truncated && !self.config.has_flag(BehaviorFlag::LegacyScoring) {
+            let ranked = scored_results
+                .iter()
+                .map(|scored| scored.index)
+                .collect::<HashSet<usize>>();
+            for index in (0..self.search_results.len()).filter(|index| !ranked.contains(index)) {
+                let result = &self.search_results[index];
+                let reason = match self.operator_filter_reason(result) {
+                    Some(reason) => reason,
+                    None if self.config.drop_low_coverage
+                        && self.score_at(index).features.term_coverage
+                            < self.config.min_term_coverage =>
+                    {
+                        "Matches too few of the query terms".to_string()
+                    }
+                    None => continue,
+                };
+                filtered.push(FilteredResult { result, reason });
+            }
+        }
+        filtered
+    }
+
+    // Why the query's `site:` or `-term` operators leave the result out, if
+    // they do
+    fn operator_filter_reason(&self, result: &SearchResult) -> Option<String> {
+        let operators = &self.rewrite.operators;
+        if !operators.sites.is_empty() {
+            let (host, _, _) = urls::domain_and_path(result.match_url());
+            let host = host.to_lowercase();
+            let on_site = operators.sites.iter().any(|site| {
+                host == *site || host.ends_with(&format!(".{}", site))
+            });
+            if !on_site {
+                return Some(format!("Not on {}", operators.sites.join(" or ")));
+            }
+        }
+        if !operators.excluded.is_empty() {
+            let text = format!("{} {} {}", result.title, result.extract, result.match_url());
+            let words = tokenizer::tokenize(&text, &TokenizeOptions::default())
+                .into_iter()
+                .map(|token| token.text)
+                .collect::<HashSet<String>>();
+            if let Some(term) = operators.excluded.iter().find(|term| words.contains(*term)) {
+                return Some(format!("Contains the excluded term {}", term));
+            }
+        }
+        None
+    }
+}
+
+struct ScoredResult<'a> {
+    index: usize,
+    result: &'a SearchResult,
+    features: Features,
+    score: f32,
+    // How the score was built up, ending at `score`
+    contributions: Waterfall,
+    // How much the members of an ensemble model disagree about the score
+    // before any score rules, or None without an ensemble
+    score_std: Option<f32>,
+    // Pinned by a curation, so `score` is `CURATED_SCORE` rather than the
+    // total of the contributions
+    curated: bool,
+}
+
+fn check_model_sanity(model: &Model, config: &RankerConfig) -> Result<(), String> {
+    for (query, better, worse) in MODEL_SANITY_SUITE.iter() {
+        let mut ranker = Ranker::with_config(query, config.clone(), "custom");
+        ranker.model = Some(model.clone());
+        ranker.add_search_result(better[0], better[1], better[2]);
+        ranker.add_search_result(worse[0], worse[1], worse[2]);
+        if ranker.score_at(0).score <= ranker.score_at(1).score {
+            return Err(format!(
+                "Model not activated: for the query \"{}\" it doesn't rank {} above {}",
+                query, better[0], worse[0]
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Put results that were in the last ranking back in their previous order,
+// then move each result up past those it now outscores. A result only passes
+// one that was above it last time if it outscores it by the margin. Results
+// are compared by `value`, which must be what they are currently ordered by,
+// such as their score or their points in the fused ranking.
+fn apply_hysteresis(
+    scored_results: &mut [ScoredResult],
+    last_ranking: &HashMap<usize, Explanation>,
+    margin: f32,
+    value: impl Fn(&ScoredResult) -> f32,
+) {
+    let previous_position =
+        |scored: &ScoredResult| last_ranking.get(&scored.index).map(|e| e.position);
+    scored_results.sort_by_key(|scored| previous_position(scored).unwrap_or(usize::MAX));
+    for i in 1..scored_results.len() {
+        let mut j = i;
+        while j > 0 {
+            let (above, below) = (&scored_results[j - 1], &scored_results[j]);
+            let required = match (previous_position(above), previous_position(below)) {
+                (Some(_), Some(_)) => value(above) * (1.0 + margin),
+                _ => value(above),
+            };
+            if value(below) <= required {
+                break;
+            }
+            scored_results.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+// Demote each result by a factor of (1 - diversity) for every higher ranked
+// result from the same domain, then re-sort.
+fn apply_diversity(scored_results: &mut [ScoredResult], diversity: f32) {
+    let mut domain_counts: HashMap<String, i32> = HashMap::new();
+    for scored in scored_results.iter_mut() {
+        let domain = urls::parse_url_features(scored.result.match_url())
+            .map(|features| features.registrable_domain)
+            .unwrap_or_default();
+        let count = domain_counts.entry(domain).or_insert(0);
+        if *count > 0 {
+            scored.contributions.multiply("diversity", (1.0 - diversity).powi(*count));
+            scored.score = scored.contributions.total();
+        }
+        *count += 1;
+    }
+    scored_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+// Reorder ranked results by a weighted Borda count of their position in the
+// ranking and in the order they were added, which is the backend's order.
+// Ties keep their order in the ranking. Returns the points of each result by
+// its index.
+fn fuse_backend_rank(
+    scored_results: &mut Vec<ScoredResult>,
+    backend_rank_weight: f32,
+) -> HashMap<usize, f32> {
+    let num_results = scored_results.len();
+    let mut backend_order = scored_results
+        .iter()
+        .map(|scored| scored.index)
+        .collect::<Vec<usize>>();
+    backend_order.sort_unstable();
+    let points = |position: usize| (num_results - position) as f32;
+    let mut fused = scored_results
+        .drain(..)
+        .enumerate()
+        .map(|(position, scored)| {
+            let backend_position = backend_order.binary_search(&scored.index).unwrap();
+            let total = (1.0 - backend_rank_weight) * points(position)
+                + backend_rank_weight * points(backend_position);
+            (total, scored)
+        })
+        .collect::<Vec<(f32, ScoredResult)>>();
+    fused.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    let points = fused
+        .iter()
+        .map(|(total, scored)| (scored.index, *total))
+        .collect();
+    scored_results.extend(fused.into_iter().map(|(_, scored)| scored));
+    points
+}
+
+// Build a regex matching any of the terms. The words of multi-word entity
+// terms may be separated by any non-word characters. With stemming, single
+// words also match any word starting with their stem, which `get_features`
+// then checks has the same stem. Stopwords are matched, for highlighting,
+// but not counted in the number of terms or their total length.
+fn get_query_regex(
+    terms: &[String],
+    stemming: bool,
+    stopwords: &HashSet<String>,
+) -> (Regex, u8, u32) {
+    let unique_query_terms = terms.iter().collect::<HashSet<&String>>();
+    if unique_query_terms.is_empty() {
+        // Never matches, where an empty alternation would match everywhere
+        return (Regex::new("\\b\\B").unwrap(), 0, 0);
+    }
+    let query = "\\b".to_owned()
+        + unique_query_terms
+            .iter()
+            .map(|term| {
+                if stemming && !term.contains(' ') {
+                    return format!(
+                        "(?:{}|{}\\w*)",
+                        regex::escape(term),
+                        regex::escape(&stemming::stem(term))
+                    );
+                }
+                term.split(' ')
+                    .map(regex::escape)
+                    .collect::<Vec<String>>()
+                    .join("\\W+")
+            })
+            .collect::<Vec<String>>()
+            .join("\\b|\\b")
+            .as_str()
+        + "\\b";
+    let scored_terms = unique_query_terms
+        .iter()
+        .filter(|term| !stopwords.contains(term.as_str()))
+        .collect::<Vec<_>>();
+    let term_length_sum: usize = scored_terms.iter().map(|term| term.len()).sum();
+    let term_length_sum = u32::try_from(term_length_sum).unwrap_or(u32::MAX);
+    let num_unique_terms = u8::try_from(scored_terms.len()).unwrap_or(u8::MAX);
+    (
+        Regex::new(&query).unwrap(),
+        num_unique_terms,
+        term_length_sum,
+    )
+}
+
+// The default scoring formula, step by step
+fn score_contributions(
+    features: &Features,
+    search_result: &SearchResult,
+    config: &RankerConfig,
+) -> Waterfall {
+    let length_penalty = f32::exp(-config.length_penalty() * search_result.url.len() as f32);
+    let [mut title_weight, mut extract_weight, domain_weight, path_weight] = config.field_weights();
+    // Move the share of the extract weight that a missing or short extract
+    // can't fill onto the title, so that results the backend has no extract
+    // for don't lose out. Short extracts that match better keep their weight.
+    if config.fill_missing_extracts
+        && !config.has_flag(BehaviorFlag::LegacyScoring)
+        && features.title_match.score > features.extract_match.score
+    {
+        let missing_weight = extract_weight * (1.0 - features.extract_completeness);
+        title_weight += missing_weight;
+        extract_weight -= missing_weight;
+    }
+    let mut contributions = Waterfall::default();
+    // TODO: use tokenized domain match as well
+    for (field, value) in [
+        ("title", title_weight * features.title_match.score),
+        ("extract", extract_weight * features.extract_match.score),
+        ("domain", domain_weight * features.domain_match.score),
+        ("path", path_weight * features.path_match.score),
+    ] {
+        contributions.add(field, config.cap_field(field, value));
+    }
+    if !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.add(
+            "semantic_similarity",
+            config.semantic_weight * features.semantic_similarity,
+        );
+        let num_numeric_matches =
+            features.title_numeric_match as u8 + features.extract_numeric_match as u8;
+        if num_numeric_matches > 0 {
+            contributions.add("numeric", config.numeric_weight * num_numeric_matches as f32);
+        }
+    }
+
+    // Demote titles that match the query but have little to do with their
+    // extract
+    if features.title_match.num_terms > 0
+        && features.title_extract_overlap < 1.0
+        && !config.has_flag(BehaviorFlag::LegacyScoring)
+    {
+        contributions.multiply(
+            "clickbait",
+            1.0 - config.clickbait_weight * (1.0 - features.title_extract_overlap),
+        );
+    }
+    let title_spam = TitleFormatting {
+        punctuation_ratio: features.title_punctuation_ratio,
+        num_emoji: features.title_num_emoji,
+        keyword_list: features.title_keyword_list,
+        all_caps: features.title_all_caps,
+        clickbait_template: features.title_clickbait_template,
+    }
+    .spam_score();
+    if title_spam > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("title_spam", 1.0 - config.title_spam_weight * title_spam);
+    }
+    let junk = TextQuality {
+        title_empty: features.title_empty,
+        title_length_in_range: features.title_length_in_range,
+        extract_chars: features.extract_chars,
+        alphabetic_ratio: features.alphabetic_ratio,
+        extract_punctuation_ratio: features.extract_punctuation_ratio,
+    }
+    .junk_score();
+    if junk > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("junk_text", 1.0 - config.junk_text_weight * junk);
+    }
+    if features.has_date
+        && config.freshness > 0.0
+        && !config.has_flag(BehaviorFlag::LegacyScoring)
+    {
+        contributions.multiply("freshness", 1.0 + config.freshness * features.recency);
+    }
+    if features.title_match.starts_with > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "title_starts_with",
+            1.0 + config.starts_with_weight * features.title_match.starts_with,
+        );
+    }
+    if features.exact_domain_match && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("exact_domain", 1.0 + config.exact_domain_weight);
+    }
+    if features.title_all_terms && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("all_terms_in_title", 1.0 + config.all_terms_weight);
+    }
+    let num_phrase_matches =
+        features.title_phrase_match as u8 + features.extract_phrase_match as u8;
+    if num_phrase_matches > 0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "phrase",
+            1.0 + config.phrase_weight * num_phrase_matches as f32,
+        );
+    }
+    let proximity = features
+        .title_match
+        .proximity
+        .max(features.extract_match.proximity);
+    if proximity > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("proximity", 1.0 + config.proximity_weight * proximity);
+    }
+    let in_order = features
+        .title_match
+        .in_order
+        .max(features.extract_match.in_order);
+    if in_order > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("in_order", 1.0 + config.in_order_weight * in_order);
+    }
+    if features.extract_clustering > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "clustering",
+            1.0 + config.clustering_weight * features.extract_clustering,
+        );
+    }
+    if !config.has_flag(BehaviorFlag::LegacyScoring) {
+        if features.noindex_detected {
+            contributions.multiply("noindex", config.noindex_factor);
+        }
+        if features.soft404_suspected {
+            contributions.multiply("soft404", config.soft404_factor);
+        }
+        if features.paywalled && config.prefer_open_access {
+            contributions.multiply("paywall", config.paywall_factor);
+        }
+    }
+    if features.host_penalty > 0.0 {
+        contributions.multiply(
+            "host_reputation",
+            f32::exp(-config.host_reputation_weight * features.host_penalty),
+        );
+    }
+
+    if features.term_coverage < config.min_term_coverage
+        && !config.has_flag(BehaviorFlag::LegacyScoring)
+    {
+        contributions.multiply("low_coverage", config.low_coverage_factor);
+    }
+    if features.num_terms_matched > 1 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "cross_field",
+            1.0 + config.cross_field_weight * (features.num_terms_matched - 1) as f32,
+        );
+    }
+
+    if features.domain_popularity > 0.0 {
+        contributions.multiply(
+            "domain_popularity",
+            1.0 + config.popularity_weight * features.domain_popularity,
+        );
+    }
+    if features.domain_authority > 0.0 {
+        contributions.multiply(
+            "domain_authority",
+            1.0 + config.authority_weight * features.domain_authority,
+        );
+    }
+
+    if !features.https && config.score_urls && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("not_https", 1.0 - config.https_weight);
+    }
+    if features.tracking_url && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply("tracking_url", 1.0 - config.tracking_url_penalty);
+    }
+    if features.language_mismatch && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "language_mismatch",
+            1.0 - config.language_mismatch_penalty,
+        );
+    }
+    if features.path_depth > 0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        contributions.multiply(
+            "path_depth",
+            f32::exp(-config.path_depth_penalty * features.path_depth as f32),
+        );
+    }
+
+    contributions.multiply("length_penalty", length_penalty);
+    contributions.multiply("scale", 0.1);
+    if features.url_spam_score > 0.0 && !config.has_flag(BehaviorFlag::LegacyScoring) {
+        let penalty = config.url_spam_penalty * features.url_spam_score;
+        contributions.add("url_spam", -penalty.min(contributions.total()));
+    }
+    contributions
+}
+
+// How the query matched one field of a result
+struct FieldMatch {
+    features: MatchFeatures,
+    // The query terms matched
+    terms: HashSet<String>,
+    // Matching stopped at `MAX_MATCHES_PER_FIELD` matches
+    limit_hit: bool,
+    // A count or length overflowed its feature
+    saturated: bool,
+}
+
+// Match the query against the text of one field
+fn match_field(
+    query_regex: &Regex,
+    text: &str,
+    total_possible_length: u32,
+    num_unique_terms: u8,
+    query_terms: &QueryTerms,
+    config: &RankerConfig,
+) -> FieldMatch {
+    let text = matchable_text(text, config.rewrite.fold_plurals);
+    let matches = query_regex.find_iter(text.as_str());
+    let mut last_match_char = 1;
+    let mut limit_hit = false;
+    let mut seen_terms = HashSet::new();
+    let mut match_length = 0;
+    let mut num_synonym_terms = 0;
+    for (i, m) in matches.enumerate() {
+        if i >= MAX_MATCHES_PER_FIELD {
+            limit_hit = true;
+            break;
+        }
+        // Count the match as the term it stands for, and its length as
+        // the term's length
+        let match_term = match query_terms.term_for_match(m.as_str()) {
+            Some(term) => term,
+            None => continue,
+        };
+        if seen_terms.contains(match_term) || query_terms.stopwords.contains(match_term) {
+            continue;
+        }
+        seen_terms.insert(match_term);
+        if query_terms.synonyms.contains(match_term) {
+            num_synonym_terms += 1;
+        }
+        last_match_char = m.end();
+        match_length += match_term.len();
+    }
+    // Then look for near misses of the terms that didn't match exactly
+    let mut num_fuzzy_terms = 0;
+    if query_terms.max_edit_distance > 0 && seen_terms.len() < query_terms.scored.len() {
+        let words = word_regex().find_iter(&text).take(MAX_MATCHES_PER_FIELD);
+        for word in words {
+            if let Some(term) = query_terms.fuzzy_term(word.as_str(), &seen_terms) {
+                seen_terms.insert(term);
+                last_match_char = last_match_char.max(word.end());
+                match_length += term.len();
+                num_fuzzy_terms += 1;
+            }
+        }
+    }
+
+    let saturated = match_length > u32::MAX as usize
+        || last_match_char > u32::MAX as usize
+        || seen_terms.len() > u8::MAX as usize;
+    let match_length = u32::try_from(match_length).unwrap_or(u32::MAX);
+    let last_match_char = u32::try_from(last_match_char).unwrap_or(u32::MAX);
+    let num_terms = u8::try_from(seen_terms.len()).unwrap_or(u8::MAX);
+
+    let score = f64::powf(
+        config.match_exponent(),
+        match_length as f64 - total_possible_length as f64,
+    ) / last_match_char as f64;
+    let score = score as f32
+        * (1.0 - config.fuzzy_discount).powi(num_fuzzy_terms)
+        * (1.0 - config.synonym_discount).powi(num_synonym_terms);
+
+    let features = MatchFeatures {
+        last_char: last_match_char,
+        length: match_length,
+        total_possible_length,
+        num_terms,
+        score,
+        term_proportion: num_terms as f32 / num_unique_terms as f32,
+        proximity: 0.0,
+        in_order: 0.0,
+        starts_with: 0.0,
+        num_fuzzy_terms: num_fuzzy_terms as u8,
+        num_synonym_terms: num_synonym_terms as u8,
+    };
+    FieldMatch {
+        features,
+        terms: seen_terms.into_iter().map(str::to_string).collect(),
+        limit_hit,
+        saturated,
+    }
+}
+
+// The domain and path of the result to match the query against, which are
+// empty when URLs aren't scored
+fn scored_domain_and_path(result: &SearchResult, config: &RankerConfig) -> (String, String, bool) {
+    if config.score_urls {
+        urls::domain_and_path(result.match_url())
+    } else {
+        (String::new(), String::new(), false)
+    }
+}
+
+fn get_features(
+    query_regex: &Regex,
+    search_result: SearchResult,
+    total_possible_length: u32,
+    num_unique_terms: u8,
+    query_terms: &QueryTerms,
+    config: &RankerConfig,
+) -> Features {
+    let (domain, path, url_parse_failed) = scored_domain_and_path(&search_result, config);
+
+    let title_formatting = title_quality::title_formatting(&search_result.title);
+    let text_quality = text_quality::text_quality(&search_result.title, &search_result.extract);
+    let mut features = Features {
+        url_parse_failed,
+        page_type: search_result.page_type,
+        redirect_depth: search_result.redirect_depth,
+        noindex_detected: search_result.noindex_detected,
+        soft404_suspected: search_result.soft404_suspected,
+        paywalled: search_result.paywalled,
+        title_punctuation_ratio: title_formatting.punctuation_ratio,
+        title_num_emoji: title_formatting.num_emoji,
+        title_keyword_list: title_formatting.keyword_list,
+        title_all_caps: title_formatting.all_caps,
+        title_clickbait_template: title_formatting.clickbait_template,
+        title_empty: text_quality.title_empty,
+        title_length_in_range: text_quality.title_length_in_range,
+        extract_chars: text_quality.extract_chars,
+        alphabetic_ratio: text_quality.alphabetic_ratio,
+        extract_punctuation_ratio: text_quality.extract_punctuation_ratio,
+        title_extract_overlap: snippet::title_extract_overlap(
+            &search_result.title,
+            &search_result.extract,
+        ),
+        ..Features::default()
+    };
+    features.exact_domain_match = !domain.is_empty() && query_terms.names_domain(&domain);
+    features.path_depth = u8::try_from(urls::path_depth(&path)).unwrap_or(u8::MAX);
+    features.https = urls::is_https(search_result.match_url());
+    if config.score_urls {
+        features.url_spam_score =
+            urls::url_spam_score(search_result.match_url(), &config.spam_tlds);
+    }
+    features.tracking_url =
+        config.score_urls && urls::has_tracking_noise(search_result.match_url());
+    let extract_chars = search_result.extract.trim().chars().count();
+    features.extract_completeness = (extract_chars as f32 / FULL_EXTRACT_CHARS as f32).min(1.0);
+    let mut matched_terms = HashSet::new();
+    for (part, name) in [
+        (search_result.title.as_str(), "title"),
+        (search_result.extract.as_str(), "extract"),
+        (domain.as_str(), "domain"),
+        (path.as_str(), "path"),
+    ]
+    .iter()
+    {
+        let FieldMatch {
+            features: match_features,
+            terms,
+            limit_hit,
+            saturated,
+        } = match_field(
+            query_regex,
+            part,
+            total_possible_length,
+            num_unique_terms,
+            query_terms,
+            config,
+        );
+        features.match_limit_hit |= limit_hit;
+        features.saturated |= saturated;
+        if (*name).eq("title") {
+            let original_terms = query_terms
+                .scored
+                .iter()
+                .filter(|term| !query_terms.synonyms.contains(*term))
+                .collect::<Vec<&String>>();
+            features.title_all_terms = original_terms.len() > 1
+                && original_terms
+                    .iter()
+                    .all(|term| terms.contains(*term));
+            features.title_match = match_features;
+        } else if (*name).eq("extract") {
+            features.extract_match = match_features;
+        } else if (*name).eq("domain") {
+            features.domain_match = match_features;
+        } else if (*name).eq("path") {
+            features.path_match = match_features;
+        } else {
+            panic!("Unknown part: {}", name);
+        }
+        matched_terms.extend(terms);
+    }
+    features.term_coverage = if num_unique_terms == 0 {
+        1.0
+    } else {
+        (matched_terms.len() as f32 / num_unique_terms as f32).min(1.0)
+    };
+    features.num_terms_matched = u8::try_from(matched_terms.len()).unwrap_or(u8::MAX);
+
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    fn query_regex(query: &str) -> (regex::Regex, u8, u32) {
+        super::get_query_regex(&super::query_tokens(query), false, &super::HashSet::new())
+    }
+
+    #[test]
+    fn construct_some_search_results() {
+        let mut ranker = super::Ranker::new("url");
+        ranker.add_search_result("https://en.wikipedia.org/wiki/URL", "URL", "A URL is a reference to a web resource that specifies its location on a computer network and a mechanism for retrieving it.");
+
+        assert_eq!(ranker.len(), 1);
+    }
+
+    #[test]
+    fn near_misses_match_with_a_discount() {
+        let mut ranker = super::Ranker::new("recieve email");
+        ranker.add_search_result("https://a.com/", "Receive email", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+
+        let config = super::RewriteConfig {
+            max_edit_distance: 2,
+            ..super::RewriteConfig::default()
+        };
+        ranker.set_query_rewrite(config);
+        ranker.add_search_result("https://b.com/", "Recieve email", "");
+        let fuzzy = ranker.score_at(0).features.title_match;
+        let exact = ranker.score_at(1).features.title_match;
+        assert_eq!(fuzzy.num_terms, 2);
+        assert_eq!(fuzzy.num_fuzzy_terms, 1);
+        assert_eq!(fuzzy.length, exact.length);
+        assert_eq!(fuzzy.score, exact.score * (1.0 - ranker.config.fuzzy_discount));
+    }
+
+    #[test]
+    fn synonyms_match_with_a_discount() {
+        let mut ranker = super::Ranker::new("js tutorial");
+        let synonyms = vec![("JS".to_string(), vec!["JavaScript".to_string()])];
+        ranker.load_synonyms(synonyms.into_iter().collect());
+        assert_eq!(ranker.rewrite.terms, vec!["js", "tutorial", "javascript"]);
+        ranker.add_search_result("https://a.com/", "JavaScript tutorial", "");
+        ranker.add_search_result("https://b.com/", "Cooking tutorial", "");
+        let synonym = ranker.score_at(0).features.title_match;
+        let original = ranker.score_at(1).features.title_match;
+        assert_eq!(synonym.num_terms, 2);
+        assert_eq!(synonym.num_synonym_terms, 1);
+        assert_eq!(original.num_synonym_terms, 0);
+        assert!(synonym.score > original.score);
+
+        ranker.config.synonym_discount = 0.0;
+        ranker.config_changed();
+        let undiscounted = ranker.score_at(0).features.title_match.score;
+        assert!((synonym.score - undiscounted * 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stopwords_are_not_scored() {
+        let mut ranker = super::Ranker::new("how to make a website");
+        assert_eq!(ranker.num_unique_terms, 2);
+        assert_eq!(ranker.total_possible_match_length, 11);
+        ranker.add_search_result("https://a.com/", "How to make a cake", "");
+        ranker.add_search_result("https://b.com/", "Website maker", "Make a website");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+        assert_eq!(ranker.score_at(0).features.term_coverage, 0.5);
+        assert_eq!(ranker.score_at(1).features.term_coverage, 1.0);
+        assert_eq!(ranker.ranked_results()[0].result.url.as_str(), "https://b.com/");
+
+        let config = super::RewriteConfig {
+            stopword_language: String::new(),
+            ..super::RewriteConfig::default()
+        };
+        ranker.set_query_rewrite(config);
+        assert_eq!(ranker.num_unique_terms, 5);
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 4);
+    }
+
+    #[test]
+    fn stemmed_words_match_the_query_term() {
+        let mut ranker = super::Ranker::new("running searches");
+        let config = super::RewriteConfig {
+            stemming: true,
+            ..super::RewriteConfig::default()
+        };
+        ranker.set_query_rewrite(config);
+        ranker.add_search_result("https://a.com/", "Run a search", "");
+        ranker.add_search_result("https://b.com/", "Runner searchers", "");
+
+        let features = ranker.score_at(0).features;
+        assert_eq!(features.title_match.num_terms, 2);
+        assert_eq!(features.title_match.length, 15);
+        assert_eq!(ranker.score_at(1).features.title_match.num_terms, 0);
+    }
+
+    #[test]
+    fn serp_bundles_reproduce_the_ranking() {
+        let mut ranker = super::Ranker::new("rust book");
+        ranker.add_search_result_with_type("https://a.com/", "Rust", "A book", "article");
+        ranker.add_search_result("https://b.com/", "The Rust book", "");
+        ranker.add_search_result("https://c.com/", "Cooking", "");
+
+        let json = ranker.export_serp();
+        let bundle: super::SerpBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(bundle.query, "rust book");
+        assert_eq!(bundle.results.len(), 3);
+        assert_eq!(bundle.results[1].position, Some(0));
+        assert_eq!(bundle.results[0].metadata.page_type, "article");
+        assert_eq!(bundle.results[1].features["title_phrase_match"], 1.0);
+
+        let replayed = super::Ranker::from_bundle(&bundle);
+        assert_eq!(replayed.serp_bundle(), bundle);
+    }
+
+    #[test]
+    fn slot_rules_reorder_the_top_results() {
+        use crate::page_type::PageType;
+        use crate::slotting::{SlotRule, SlotSelector};
+
+        let mut ranker = super::Ranker::new("rust");
+        for (url, title, page_type) in [
+            ("https://a.com/", "Rust Rust", "video"),
+            ("https://b.com/", "Rust Rust", "video"),
+            ("https://c.com/", "Rust", ""),
+            ("https://en.wikipedia.org/wiki/Rust", "Rust", ""),
+        ] {
+            ranker.add_search_result_with_type(url, title, "", page_type);
+        }
+        let urls = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.result.url.to_string())
+                .collect::<Vec<String>>()
+        };
+        let videos = SlotSelector {
+            page_type: Some(PageType::Video),
+            ..SlotSelector::default()
+        };
+        ranker.config.slot_rules = vec![SlotRule::AtMost {
+            selector: videos,
+            max: 1,
+            top: 2,
+        }];
+        assert_eq!(urls(&ranker)[..3], ["https://a.com/", "https://c.com/", "https://b.com/"]);
+
+        let wikipedia = SlotSelector {
+            domains: vec!["wikipedia.org".to_string()],
+            ..SlotSelector::default()
+        };
+        ranker.config.slot_rules.push(SlotRule::AtLeastOne {
+            selector: wikipedia.clone(),
+            top: 2,
+            min_score_ratio: 0.1,
+        });
+        assert_eq!(urls(&ranker)[1], "https://en.wikipedia.org/wiki/Rust");
+        ranker.config.slot_rules[1] = SlotRule::AtLeastOne {
+            selector: wikipedia,
+            top: 2,
+            min_score_ratio: 0.99,
+        };
+        assert_eq!(urls(&ranker)[1], "https://c.com/");
+    }
+
+    #[test]
+    fn slot_rule_score_ratios_use_the_highest_score() {
+        use crate::slotting::{SlotRule, SlotSelector};
+
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Rust Rust", "Rust programming in Rust");
+        ranker.add_search_result("https://b.com/", "", "Rust");
+        ranker.add_search_result("https://en.wikipedia.org/wiki/Rust", "", "Rust");
+        // Keep b.com above a.com, as it was in the last ranking
+        ranker.config.hysteresis_margin = 1000.0;
+        let mut ranked = ranker.ranked_results();
+        ranked.sort_by_key(|scored| [1, 0, 2].iter().position(|&index| index == scored.index));
+        ranker.cache_ranking(ranked);
+        ranker.config.slot_rules = vec![SlotRule::AtLeastOne {
+            selector: SlotSelector {
+                domains: vec!["wikipedia.org".to_string()],
+                ..SlotSelector::default()
+            },
+            top: 2,
+            min_score_ratio: 0.3,
+        }];
+        // Wikipedia scores above 0.3 times b.com's score, but not a.com's
+        let ranked = ranker.ranked_results();
+        let indexes = ranked.iter().map(|scored| scored.index).collect::<Vec<usize>>();
+        assert_eq!(indexes, vec![1, 0, 2]);
+        assert!(ranked[2].score > 0.3 * ranked[0].score);
+    }
+
+    #[test]
+    fn hysteresis_keeps_close_results_in_place() {
+        let mut ranker = super::Ranker::new_with_profile("rust", "default").unwrap();
+        ranker.config.hysteresis_margin = 0.5;
+        ranker.config.starts_with_weight = 0.0;
+        ranker.config.fill_missing_extracts = false;
+        ranker.config.junk_text_weight = 0.0;
+        ranker.add_search_result("https://a.com/", "Rust", "");
+        ranker.add_search_result("https://b.com/", "", "Rust");
+        ranker.cache_ranking(ranker.ranked_results());
+        let urls = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.result.url.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(urls(&ranker), vec!["https://a.com/", "https://b.com/"]);
+
+        // Slightly favour extracts, which would swap the results without
+        // hysteresis, then strongly favour them
+        ranker.config.extract_weight = 5.0;
+        assert_eq!(urls(&ranker), vec!["https://a.com/", "https://b.com/"]);
+        ranker.config.extract_weight = 10.0;
+        assert_eq!(urls(&ranker), vec!["https://b.com/", "https://a.com/"]);
+
+        ranker.add_search_result("https://c.com/rust", "Rust", "Rust");
+        assert_eq!(urls(&ranker)[0], "https://c.com/rust");
+    }
+
+    #[test]
+    fn rank_stability_compares_with_last_ranking() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.fill_missing_extracts = false;
+        ranker.add_search_result("https://a.com/rust", "Rust", "");
+        ranker.add_search_result("https://b.com/", "Other", "");
+        let stability = |ranker: &super::Ranker| {
+            let scored_results = ranker.ranked_results();
+            let stability = ranker.rank_output(&scored_results, false, false).stats.stability;
+            ranker.cache_ranking(scored_results);
+            stability
+        };
+        assert_eq!(stability(&ranker), None);
+        assert_eq!(stability(&ranker), Some(1.0));
+
+        ranker.add_search_result("https://c.com/rust", "Rust Rust", "Rust");
+        ranker.add_search_result("https://d.com/", "Other", "");
+        let scored_results = ranker.ranked_results();
+        assert_eq!(scored_results[0].result.url.as_str(), "https://c.com/rust");
+        assert_eq!(stability(&ranker), Some(1.0));
+
+        // Moving the last of four results to the top reverses half the pairs
+        ranker.pin_result("https://d.com/");
+        assert_eq!(stability(&ranker), Some(0.0));
+    }
+
+    #[test]
+    fn rank_output_includes_scores_and_stats() {
+        use crate::filter::UrlSafety;
+
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+
+        let scored_results = ranker.ranked_results();
+        let output = ranker.rank_output(&scored_results, false, false);
+        assert_eq!(output.results[0].url.as_str(), "https://rust-lang.org/");
+        assert_eq!(output.scores.len(), 2);
+        assert!(output.scores[0] > output.scores[1]);
+        assert_eq!(output.stats.num_results, 2);
+        assert_eq!(output.stats.num_matched, 1);
+        assert!(!output.stats.no_matches);
+        assert!(output.explanations.is_none());
+        assert!(ranker.rank_output(&scored_results, true, false).explanations.is_some());
+        assert!(!output.partial);
+        assert_eq!(output.revision, 2);
+        assert_eq!(ranker.results_revision(), 2);
+        assert_eq!(output.safety, vec![UrlSafety::Ok; 2]);
+
+        let blocked = "rust-lang.org".to_string();
+        ranker.config.url_filter.blocked_domains.insert(blocked);
+        let scored_results = ranker.ranked_results();
+        let output = ranker.rank_output(&scored_results, false, false);
+        assert_eq!(output.safety[0], UrlSafety::Blocked);
+    }
+
+    #[test]
+    fn rank_stats_count_anomalies() {
+        let mut ranker = super::Ranker::new("nothing");
+        ranker.add_search_result("example.com", "Example", "");
+        ranker.add_search_result(&format!("https://example.com/{}", "a".repeat(300)), "A", "");
+
+        let scored_results = ranker.ranked_results();
+        let stats = super::RankStats::new(&scored_results, false);
+        assert!(stats.no_matches);
+        assert_eq!(stats.num_url_parse_failures, 1);
+        assert_eq!(stats.num_ingest_truncations, 1);
+        assert_eq!(stats.num_saturated, 0);
+    }
+
+    #[test]
+    fn rank_with_budget_truncates() {
+        let mut ranker = super::Ranker::new("rust");
+        for i in 0..100 {
+            ranker.add_search_result(&format!("https://example.com/{}", i), "Example", "");
+        }
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+
+        let (ranked, truncated) = ranker.ranked_results_with_budget(0.0);
+        assert!(truncated);
+        assert_eq!(ranked.len(), 101);
+        assert_eq!(ranked[100].index, 100);
+        assert_eq!(ranked[100].score, 0.0);
+
+        let (ranked, truncated) = ranker.ranked_results_with_budget(f64::INFINITY);
+        assert!(!truncated);
+        assert_eq!(ranked[0].index, 100);
+    }
+
+    #[test]
+    fn rank_step_scores_in_chunks() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://example.org/", "Example", "Nothing here");
+
+        assert!(!ranker.score_step(2));
+        let (ranked, truncated) = ranker.stepped_results();
+        assert!(truncated);
+        assert_eq!(ranked.iter().map(|r| r.index).collect::<Vec<_>>(), vec![1, 0, 2]);
+
+        assert!(ranker.score_step(2));
+        let (_, truncated) = ranker.stepped_results();
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn result_embeddings_affect_ranking() {
+        let mut ranker = super::Ranker::new("programming language");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "Fast and safe");
+        ranker.set_query_embedding(vec![1.0, 0.0]).unwrap();
+        ranker.result_embeddings = super::Embeddings::new(vec![0.0, 1.0, 1.0, 0.0], 2).unwrap();
+
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].index, 1);
+        assert_eq!(ranked[0].features.semantic_similarity, 1.0);
+    }
+
+    #[test]
+    fn invalid_query_embeddings_are_rejected() {
+        let mut ranker = super::Ranker::new("rust");
+        assert!(ranker.set_query_embedding(Vec::new()).is_err());
+        assert!(ranker.set_query_embedding(vec![f32::NAN, 0.0]).is_err());
+        ranker.set_result_embeddings(vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(
+            ranker.set_query_embedding(vec![1.0, 0.0, 0.0]).unwrap_err(),
+            "Query embedding has dimension 3, but result embeddings have 2"
+        );
+        ranker.set_query_embedding(vec![1.0, 0.0]).unwrap();
+        assert!(ranker.set_result_embeddings(vec![1.0, 0.0, 0.0], 3).is_err());
+    }
+
+    #[test]
+    fn removing_a_result_removes_its_embedding() {
+        let mut ranker = super::Ranker::new("programming language");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://go.dev/", "Go", "Simple and fast");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "Fast and safe");
+        ranker.set_query_embedding(vec![1.0, 0.0]).unwrap();
+        ranker
+            .set_result_embeddings(vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0], 2)
+            .unwrap();
+
+        assert!(ranker.remove_search_result(0));
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://rust-lang.org/");
+        assert_eq!(ranked[0].features.semantic_similarity, 1.0);
+        assert_eq!(ranked[1].features.semantic_similarity, 0.0);
+    }
+
+    #[test]
+    fn more_like_this_uses_token_overlap() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A systems programming language");
+        ranker.add_search_result("https://example.com/", "Cooking", "Recipes for pasta");
+        ranker.add_search_result("https://go.dev/", "Go", "A programming language");
+
+        let similar = ranker.similar_results(0);
+        assert_eq!(similar.iter().map(|r| r.index).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(ranker.similar_results(3).is_empty());
+    }
+
+    #[test]
+    fn curation_pins_and_excludes_results() {
+        let mut ranker = super::Ranker::new("Rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://spam.example/", "Rust rust", "Rust");
+        ranker.pin_result("https://example.com/");
+        ranker.exclude_result("https://spam.example/");
+
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked.iter().map(|r| r.index).collect::<Vec<_>>(), vec![1, 0]);
+
+        let mut other_ranker = super::Ranker::new("rust");
+        other_ranker.import_curations(&ranker.export_curations()).unwrap();
+        assert_eq!(other_ranker.curations, ranker.curations);
+    }
+
+    #[test]
+    fn curated_results_report_the_curated_score() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.pin_result("https://example.com/");
+
+        let scored_results = ranker.ranked_results();
+        assert_eq!(scored_results[0].score, super::CURATED_SCORE);
+        let stats = super::RankStats::new(&scored_results, false);
+        assert_eq!(stats.top_score, scored_results[1].score);
+
+        ranker.cache_ranking(scored_results);
+        let explanation = ranker.explain(1).unwrap();
+        assert!(explanation.curated);
+        assert_ne!(explanation.contributions.total(), explanation.score);
+        assert!(!ranker.explain(0).unwrap().curated);
+    }
+
+    #[test]
+    fn feature_clamps_cap_scores() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust.org/", "Rust", "");
+        let unclamped = ranker.score_at(0).features.domain_match.score;
+        ranker.set_feature_clamp("domain_score", 0.0, 0.01).unwrap();
+
+        let scored = ranker.score_at(0);
+        assert!(unclamped > 0.01);
+        assert_eq!(scored.features.domain_match.score, 0.01);
+
+        assert!(ranker.set_feature_clamp("domain_scor", 0.0, 1.0).is_err());
+        assert!(ranker.set_feature_clamp("https", 0.0, 1.0).is_err());
+        assert!(ranker.set_feature_clamp("domain_score", 1.0, 0.0).is_err());
+        assert!(ranker.set_feature_clamp("domain_score", f32::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn one_sided_clamps_survive_serp_bundles() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust.org/", "Rust", "");
+        ranker.set_feature_clamp("domain_score", 0.0, f32::INFINITY).unwrap();
+
+        let json = ranker.export_serp();
+        assert!(json.contains(r#""domain_score":{"min":0.0}"#));
+        let report = super::replay_serp(&json, None, None).unwrap();
+        assert!(!report.is_empty());
+    }
+
+    #[test]
+    fn score_rules_adjust_scores() {
+        let mut ranker = super::Ranker::new_with_profile("rust", "default").unwrap();
+        ranker.config.fill_missing_extracts = false;
+        ranker.add_search_result("https://learn.edu/", "Learn Rust", "");
+        let unadjusted = ranker.score_at(0).score;
+        ranker.set_score_rules("if domain ends_with \".edu\" then * 2").unwrap();
+        assert_eq!(ranker.score_at(0).score, unadjusted * 2.0);
+
+        ranker.set_flags(super::BehaviorFlag::LegacyScoring as u32);
+        assert_eq!(ranker.score_at(0).score, unadjusted);
+    }
+
+    #[test]
+    fn loaded_model_replaces_the_formula() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.add_search_result("https://example.com/rust", "Example", "");
+        let model = |features: &[&str], weights: &[f32]| super::Model::Linear {
+            version: "test".to_string(),
+            features: features.iter().map(|name| name.to_string()).collect(),
+            weights: weights.to_vec(),
+            bias: 0.0,
+            standardization: None,
+        };
+        let features = ["title_num_terms", "extract_num_terms", "path_num_terms"];
+        assert_eq!(ranker.load_model(model(&features, &[1.0, 1.0, 10.0])), Ok(None));
+
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].index, 1);
+        assert_eq!(ranked[0].score, 10.0);
+
+        // Fails the sanity suite, since it only rewards matches in the path
+        let warning = ranker.load_model(model(&features, &[0.0, 0.0, 1.0])).unwrap();
+        assert!(warning.unwrap().starts_with("Model not activated"));
+        assert!(ranker.model.is_none());
+
+        assert!(ranker.load_model(model(&["unknown"], &[1.0])).is_err());
+        assert!(ranker.load_model(model(&["title_score"], &[-1.0])).is_err());
+    }
+
+    #[test]
+    fn ensemble_models_report_their_confidence() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.add_search_result("https://example.com/", "Example", "");
+        let member = |title_weight: f32| super::Model::Linear {
+            version: String::new(),
+            features: ["title_num_terms", "extract_num_terms", "path_num_terms"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            weights: vec![title_weight, 1.0, 10.0],
+            bias: 0.0,
+            standardization: None,
+        };
+        let ensemble = super::Model::Ensemble {
+            version: "test".to_string(),
+            members: vec![member(1.0), member(3.0)],
+        };
+        assert_eq!(ranker.load_model(ensemble), Ok(None));
+
+        let scored_results = ranker.ranked_results();
+        assert_eq!(scored_results[0].score, 2.0);
+        assert!(ranker.rank_output(&scored_results, false, false).score_stds.is_none());
+
+        ranker.config.score_confidence = true;
+        let scored_results = ranker.ranked_results();
+        let output = ranker.rank_output(&scored_results, false, false);
+        assert_eq!(output.score_stds, Some(vec![1.0, 0.0]));
+    }
+
+    #[test]
+    fn explain_reads_the_last_ranking() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        assert!(ranker.explain(1).is_none());
+
+        ranker.cache_ranking(ranker.ranked_results());
+        let explanation = ranker.explain(1).unwrap();
+        assert_eq!(explanation.position, 0);
+        assert_eq!(explanation.features.title_match.num_terms, 1);
+    }
+
+    #[test]
+    fn serp_quality_summarises_the_last_ranking() {
+        let mut ranker = super::Ranker::new("rust language");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://rust-lang.org/learn", "Learn Rust", "");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        assert_eq!(ranker.serp_quality(), super::SerpQuality::default());
+
+        ranker.cache_ranking(ranker.ranked_results());
+        let quality = ranker.serp_quality();
+        assert_eq!(quality.top_term_coverage, 0.5);
+        assert_eq!(quality.diversity, 2.0 / 3.0);
+        assert!(quality.score > 0.4 && quality.score < 0.5);
+        assert_eq!(quality.top_score, ranker.explain(0).unwrap().score);
+    }
+
+    #[test]
+    fn explain_all_lists_results_in_ranked_order() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://example.com/", "Example", "Nothing here");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        assert!(ranker.explain_all(None).results.is_empty());
+
+        ranker.cache_ranking(ranker.ranked_results());
+        let explained = ranker.explain_all(None);
+        let indexes = explained.results.iter().map(|r| r.index).collect::<Vec<_>>();
+        assert_eq!(indexes, vec![1, 0]);
+        assert_eq!(explained.results[0].features.len(), explained.feature_names.len());
+        assert_eq!(ranker.explain_all(Some(1)).results.len(), 1);
+    }
+
+    #[test]
+    fn explanation_text_is_stable() {
+        let mut ranker = super::Ranker::new_with_profile("rust", "default").unwrap();
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.cache_ranking(ranker.ranked_results());
+        let text = ranker.render_explanation_text();
+        let expected = "#0 https://rust-lang.org/ score=0.1970
+  title_extract_overlap=1.0000
+  title_length_in_range=1.0000
+  alphabetic_ratio=1.0000
+  https=1.0000
+  term_coverage=1.0000
+  num_terms_matched=1.0000
+  title_score=0.2500
+  title_num_terms=1.0000
+  title_term_proportion=1.0000
+  title_length=4.0000
+  title_last_char=4.0000
+  title_starts_with=1.0000
+  extract_score=0.0625
+  extract_last_char=1.0000
+  domain_score=0.2500
+  domain_num_terms=1.0000
+  domain_term_proportion=1.0000
+  domain_length=4.0000
+  domain_last_char=4.0000
+  domain_starts_with=1.0000
+  path_score=0.0625
+  path_last_char=1.0000
+  + title 1.2500 = 1.2500
+  + extract 0.0000 = 1.2500
+  + domain 1.0000 = 2.2500
+  + path 0.1250 = 2.3750
+  + semantic_similarity 0.0000 = 2.3750
+  * title_starts_with 2.0000 = 4.7500
+  * length_penalty 0.4148 = 1.9702
+  * scale 0.1000 = 0.1970
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn contributions_add_up_to_the_score() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.diversity = 0.5;
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        ranker.add_search_result("https://rust-lang.org/learn", "Learn Rust", "");
+
+        let ranked = ranker.ranked_results();
+        let labels = ranked[1]
+            .contributions
+            .steps
+            .iter()
+            .map(|step| step.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels.first(), Some(&"title"));
+        assert_eq!(labels.last(), Some(&"diversity"));
+        for scored in ranked {
+            assert_eq!(scored.contributions.total(), scored.score);
+        }
+    }
+
+    #[test]
+    fn long_fields_keep_their_match_positions() {
+        let (regex, num_unique_terms, total_possible_length) = query_regex("rust");
+        let field_match = |text: &str| {
+            super::match_field(
+                &regex,
+                text,
+                total_possible_length,
+                num_unique_terms,
+                &super::QueryTerms::default(),
+                &super::RankerConfig::default(),
+            )
+        };
+        let padding = "filler ".repeat(500);
+        let near = field_match(&format!("{}rust", &padding[..1001]));
+        let far = field_match(&format!("{}rust", padding));
+        assert_eq!(near.features.last_char, 1005);
+        assert_eq!(far.features.last_char, 3504);
+        assert!(!far.saturated);
+        assert!(near.features.score > far.features.score);
+
+        let text = format!("Über {}Rust", padding);
+        let spans = super::highlight_spans(&regex, &text, false);
+        assert_eq!(spans, vec![(3505, 3509)]);
+    }
+
+    #[test]
+    fn query_rewrite_changes_the_matched_terms() {
+        let mut ranker = super::Ranker::new("Rsut -python");
+        assert_eq!(ranker.rewrite.terms, vec!["rsut"]);
+        assert_eq!(ranker.rewrite.operators.excluded, vec!["python"]);
+
+        let mut config = super::RewriteConfig::default();
+        config.stages.push(super::rewrite::RewriteStage::Spelling);
+        config
+            .spelling_corrections
+            .insert("rsut".to_string(), "rust".to_string());
+        ranker.set_query_rewrite(config);
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+    }
+
+    #[test]
+    fn revision_tracks_changes() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.add_search_result("https://example.com/", "Example", "");
+        ranker.set_flags(0);
+        assert_eq!(ranker.revision(), 3);
+        assert_eq!(ranker.results_revision(), 2);
+
+        assert!(ranker.remove_search_result(0));
+        assert!(!ranker.remove_search_result(1));
+        assert_eq!(ranker.len(), 1);
+        assert_eq!(ranker.search_results[0].url.as_str(), "https://example.com/");
+        assert_eq!(ranker.revision(), 4);
+        assert_eq!(ranker.results_revision(), 3);
+    }
+
+    #[test]
+    fn results_changes_are_notified_on_request() {
+        let mut ranker = super::Ranker::new("rust");
+        let revisions = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = revisions.clone();
+        ranker.on_results_changed(move |revision| {
+            seen.borrow_mut().push(revision);
+            if revision > 2 {
+                return Err("Too many results".to_string());
+            }
+            Ok(())
+        });
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.add_search_result("https://example.com/", "Example", "");
+        assert!(revisions.borrow().is_empty());
+
+        assert_eq!(ranker.notify_results_changed(), Ok(()));
+        assert_eq!(ranker.notify_results_changed(), Ok(()));
+        assert_eq!(*revisions.borrow(), vec![2]);
+
+        assert!(ranker.remove_search_result(0));
+        assert_eq!(ranker.notify_results_changed(), Err("Too many results".to_string()));
+        assert_eq!(*revisions.borrow(), vec![2, 3]);
+    }
+
+    #[test]
+    fn rank_callback_errors_are_kept() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        let completed = std::rc::Rc::new(std::cell::Cell::new(0));
+        let seen = completed.clone();
+        ranker.on_rank_start(|| Err("Start failed".to_string()));
+        ranker.on_rank_complete(move |stats| {
+            seen.set(stats.num_results);
+            Err("Complete failed".to_string())
+        });
+        assert_eq!(ranker.take_rank_callback_error(), None);
+
+        assert_eq!(ranker.rank().results.len(), 1);
+        assert_eq!(completed.get(), 1);
+        assert!(ranker.last_rank_stats().is_some());
+        assert_eq!(ranker.take_rank_callback_error(), Some("Start failed".to_string()));
+        assert_eq!(ranker.take_rank_callback_error(), None);
+    }
+
+    #[test]
+    fn queries_without_terms_score_zero() {
+        let mut ranker = super::Ranker::new("!g");
+        ranker.add_search_result("https://example.com/", "Example", "");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "A language");
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 1]);
+        assert!(ranked.iter().all(|scored| scored.score == 0.0));
+        assert_eq!(ranker.rank_with_explanations().scores, vec![0.0, 0.0]);
+        assert_eq!(ranker.explain(1).unwrap().features.title_match.term_proportion, 0.0);
+        assert!(ranker.highlights(1).unwrap().title.is_empty());
+    }
+
+    #[test]
+    fn url_filter_rejects_results() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.url_filter.blocked_domains.insert("spam.com".to_string());
+        let urls = vec![
+            "https://rust-lang.org/".to_string(),
+            "https://www.spam.com/rust".to_string(),
+        ];
+        assert_eq!(ranker.filter_urls(&urls), vec![true, false]);
+
+        assert!(!ranker.add_search_result(&urls[1], "Rust", ""));
+        assert!(!ranker.add_search_result("data:text/html,rust", "Rust", ""));
+        assert!(ranker.is_empty());
+        assert_eq!(ranker.ingest_errors.len(), 2);
+    }
+
+    #[test]
+    fn concatenated_queries_are_segmented() {
+        let mut ranker = super::Ranker::new("rustwasmranker");
+        ranker.add_search_result("https://a.com/", "Rust and Wasm", "");
+        assert_eq!(ranker.rewrite.terms, vec!["rustwasmranker"]);
+        ranker.add_search_result("https://ranker.dev/", "Ranking in the browser", "");
+        assert_eq!(ranker.rewrite.terms, vec!["rust", "wasm", "ranker"]);
+        assert_eq!(ranker.rewrite.steps.last().unwrap().stage, "segments");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 2);
+
+        // Once the term itself turns up, it's taken as typed
+        ranker.add_search_result("https://c.com/", "RustWasmRanker", "");
+        assert_eq!(ranker.rewrite.terms, vec!["rustwasmranker"]);
+        assert_eq!(ranker.score_at(2).features.title_match.num_terms, 1);
+    }
+
+    #[test]
+    fn popular_domains_rank_above_mirrors() {
+        let mut ranker = super::Ranker::new("rust documentation");
+        ranker.add_search_result("https://rust-docs.example/", "Rust documentation", "");
+        ranker.add_search_result("https://doc.rust-lang.org/", "Rust documentation", "");
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://rust-docs.example/");
+
+        let bytes = super::DomainPopularity::to_bytes(&["google.com", "rust-lang.org"]);
+        ranker.load_domain_popularity(&bytes).unwrap();
+        assert!(ranker.score_at(1).features.domain_popularity > 0.9);
+        assert_eq!(ranker.score_at(0).features.domain_popularity, 0.0);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://doc.rust-lang.org/");
+    }
+
+    #[test]
+    fn linked_domains_rank_above_unlinked_ones() {
+        let mut ranker = super::Ranker::new("rust documentation");
+        ranker.add_search_result("https://rust-docs.example/", "Rust documentation", "");
+        ranker.add_search_result("https://doc.rust-lang.org/", "Rust documentation", "");
+
+        let bytes = super::DomainAuthority::to_bytes(&[("rust-lang.org", 120.0), ("b.com", 3.0)]);
+        ranker.load_domain_authority(&bytes).unwrap();
+        assert_eq!(ranker.score_at(1).features.domain_authority, 1.0);
+        assert_eq!(ranker.score_at(0).features.domain_authority, 0.0);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://doc.rust-lang.org/");
+
+        ranker.config.authority_weight = 0.0;
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://rust-docs.example/");
+    }
+
+    #[test]
+    fn host_reputation_demotes_results() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://farm.example/rust", "Rust", "");
+        let unpenalized = ranker.score_at(0).score;
+        ranker.record_skip("https://farm.example/other");
+
+        let scored = ranker.score_at(0);
+        assert!(scored.features.host_penalty > 0.9);
+        assert!(scored.score < unpenalized);
+    }
+
+    #[test]
+    fn clickbait_titles_are_demoted() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Rust tips", "Rust tips and tricks");
+        ranker.add_search_result("https://b.com/", "Rust tips", "Win a free phone today");
+
+        let honest = ranker.score_at(0);
+        let clickbait = ranker.score_at(1);
+        assert_eq!(clickbait.features.title_extract_overlap, 0.0);
+        assert!(clickbait.score < honest.score);
+    }
+
+    #[test]
+    fn page_type_hints_become_features() {
+        let mut ranker = super::Ranker::new("pancakes");
+        ranker.add_search_result_with_type("https://a.com/", "Pancakes", "", "Recipe");
+        ranker.add_search_result("https://b.com/", "Pancakes", "");
+
+        let features = ranker.score_at(0).features;
+        assert_eq!(features.value("page_type_recipe"), Some(1.0));
+        assert_eq!(features.value("page_type_video"), Some(0.0));
+        assert_eq!(ranker.search_results[1].page_type, super::PageType::Unknown);
+    }
+
+    #[test]
+    fn freshness_boosts_dated_results() {
+        let config = super::RankerConfig {
+            freshness: 1.0,
+            ..super::RankerConfig::default()
+        };
+        let mut ranker = super::Ranker::with_config("rust", config, "custom");
+        ranker.add_search_result("https://a.com/2020/01/rust", "Rust", "");
+        ranker.add_search_result("https://b.com/xxxx/01/rust", "Rust", "");
+
+        let dated = ranker.score_at(0);
+        let undated = ranker.score_at(1);
+        assert!(dated.features.has_date && dated.features.age_days > 0.0);
+        assert!(!undated.features.has_date);
+        assert!(dated.score > undated.score);
+    }
+
+    #[test]
+    fn published_dates_set_the_recency() {
+        use crate::metadata::ResultMetadata;
+
+        let config = super::RankerConfig {
+            freshness: 1.0,
+            ..super::RankerConfig::default()
+        };
+        let mut ranker = super::Ranker::with_config("rust", config, "custom");
+        let published = |date: &str| ResultMetadata {
+            published_date: Some(date.to_string()),
+            ..ResultMetadata::default()
+        };
+        ranker.add_result("https://a.com/2001/01/rust", "Rust", "", &published("2024-03-01"));
+        ranker.add_result("https://b.com/2001/01/rust", "Rust", "", &published("not a date"));
+
+        let recent = ranker.score_at(0);
+        let old = ranker.score_at(1);
+        assert!(recent.features.recency > old.features.recency);
+        assert!(old.features.recency > 0.0);
+        assert!(recent.score > old.score);
+        assert_eq!(
+            ranker.search_results[0].metadata().published_date.as_deref(),
+            Some("2024-03-01")
+        );
+        assert_eq!(ranker.search_results[1].metadata().published_date, None);
+    }
+
+    #[test]
+    fn numbers_in_range_are_matched() {
+        let mut ranker = super::Ranker::new("laptops under 500");
+        ranker.add_search_result("https://a.com/", "Laptops", "Now only $449");
+        ranker.add_search_result("https://b.com/", "Laptops", "From $1,299");
+
+        let in_range = ranker.score_at(0);
+        let out_of_range = ranker.score_at(1);
+        assert!(in_range.features.extract_numeric_match);
+        assert!(!out_of_range.features.extract_numeric_match);
+        assert!(in_range.score > out_of_range.score);
+    }
+
+    #[test]
+    fn currency_matches_across_formats() {
+        let mut ranker = super::Ranker::new("500usd phones");
+        ranker.add_search_result("https://a.com/", "Phones for $500", "");
+        ranker.add_search_result("https://b.com/", "Phones", "");
+
+        let features = ranker.score_at(0).features;
+        assert_eq!(features.title_match.num_terms, 3);
+        assert_eq!(ranker.score_at(1).features.title_match.num_terms, 1);
+    }
+
+    #[test]
+    fn low_coverage_results_are_demoted() {
+        let mut ranker = super::Ranker::new("rust async await tokio runtime");
+        ranker.add_search_result("https://a.com/", "Rust", "");
+        ranker.add_search_result("https://b.com/", "Docs", "Async await in the tokio runtime");
+
+        assert_eq!(ranker.score_at(0).features.term_coverage, 0.2);
+        assert_eq!(ranker.score_at(1).features.term_coverage, 0.8);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://b.com/");
+        let steps = &ranked[1].contributions.steps;
+        assert!(steps.iter().any(|step| step.label == "low_coverage"));
+
+        ranker.config.drop_low_coverage = true;
+        assert_eq!(ranker.ranked_results().len(), 1);
+    }
+
+    #[test]
+    fn filtered_results_are_returned_on_request() {
+        let mut ranker = super::Ranker::new("rust async await tokio runtime");
+        ranker.config.drop_low_coverage = true;
+        ranker.config.url_filter.blocked_domains.insert("spam.com".to_string());
+        ranker.add_search_result("https://a.com/", "Rust", "");
+        ranker.add_search_result("https://b.com/", "Docs", "Async await in the tokio runtime");
+        ranker.add_search_result("https://spam.com/", "Rust", "Async await in the tokio runtime");
+        assert!(ranker.rank().filtered.is_empty());
+
+        ranker.config.return_filtered = true;
+        let output = ranker.rank();
+        assert_eq!(output.results.len(), 1);
+        let filtered = output
+            .filtered
+            .iter()
+            .map(|filtered| (filtered.result.url.as_str(), filtered.reason.as_str()))
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(
+            filtered,
+            vec![
+                ("https://spam.com/", "Domain spam.com is blocked"),
+                ("https://a.com/", "Matches too few of the query terms"),
+            ]
+        );
+    }
+
+    #[test]
+    fn site_and_exclusion_operators_filter_results() {
+        let mut ranker = super::Ranker::new("rust site:docs.rs -python");
+        ranker.config.return_filtered = true;
+        ranker.add_search_result("https://docs.rs/tokio", "Rust tokio", "");
+        ranker.add_search_result("https://www.docs.rs/pyo3", "Rust for Python", "");
+        ranker.add_search_result("https://example.com/", "Rust", "");
+        let output = ranker.rank();
+        let urls = output
+            .results
+            .iter()
+            .map(|result| result.url.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(urls, vec!["https://docs.rs/tokio"]);
+        let reasons = output
+            .filtered
+            .iter()
+            .map(|filtered| filtered.reason.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(reasons, vec!["Contains the excluded term python", "Not on docs.rs"]);
+    }
+
+    #[test]
+    fn paywalled_results_are_demoted_when_preferring_open_access() {
+        use crate::metadata::ResultMetadata;
+
+        let mut ranker = super::Ranker::new("rust");
+        let metadata = ResultMetadata {
+            paywalled: true,
+            ..ResultMetadata::default()
+        };
+        ranker.add_result("https://a.com/rust", "Rust", "", &metadata);
+        ranker.add_search_result("https://b.com/rust", "Rust", "");
+        let score = ranker.score_at(0).score;
+        assert_eq!(score, ranker.score_at(1).score);
+        assert!(ranker.search_results[0].paywalled);
+
+        ranker.set_prefer_open_access(true);
+        assert!(ranker.score_at(0).features.paywalled);
+        assert_eq!(ranker.score_at(0).score, score * ranker.config.paywall_factor);
+    }
+
+    #[test]
+    fn crawler_flags_demote_results() {
+        use crate::metadata::ResultMetadata;
+
+        let mut ranker = super::Ranker::new("rust");
+        let metadata = ResultMetadata {
+            soft404_suspected: true,
+            ..ResultMetadata::default()
+        };
+        ranker.add_result("https://a.com/rust", "Rust", "", &metadata);
+        ranker.add_search_result("https://b.com/rust", "Rust", "");
+
+        assert!(ranker.score_at(0).features.soft404_suspected);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://b.com/rust");
+        assert!(ranked[1].score < ranked[0].score * 0.2);
+    }
+
+    #[test]
+    fn exact_phrases_are_boosted() {
+        let mut ranker = super::Ranker::new("rust book");
+        ranker.add_search_result("https://a.com/", "Book of Rust", "");
+        ranker.add_search_result("https://b.com/", "The Rust Book", "");
+
+        assert!(!ranker.score_at(0).features.title_phrase_match);
+        let features = ranker.score_at(1).features;
+        assert!(features.title_phrase_match);
+        assert!(!features.extract_phrase_match);
+        let contributions = ranker.score_at(1).contributions;
+        assert!(contributions.steps.iter().any(|step| step.label == "phrase"));
+    }
+
+    #[test]
+    fn terms_in_query_order_rank_higher() {
+        let mut ranker = super::Ranker::new("new york hotels");
+        ranker.add_search_result("https://a.com/", "Hotels: York is new", "");
+        ranker.add_search_result("https://b.com/", "New York hotels", "");
+
+        assert_eq!(ranker.score_at(1).features.title_match.in_order, 1.0);
+        assert_eq!(ranker.score_at(0).features.title_match.in_order, 1.0 / 3.0);
+        let contributions = ranker.score_at(1).contributions;
+        assert!(contributions.steps.iter().any(|step| step.label == "in_order"));
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://b.com/");
+    }
+
+    #[test]
+    fn urls_can_be_left_unscored() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("notes/rust/ownership.md", "Ownership", "Moves and rust");
+        assert_eq!(ranker.score_at(0).features.path_match.num_terms, 1);
+
+        ranker.set_score_urls(false);
+        let scored = ranker.score_at(0);
+        assert_eq!(scored.features.path_match.num_terms, 0);
+        assert_eq!(scored.features.extract_match.num_terms, 1);
+        let length_penalty = scored
+            .contributions
+            .steps
+            .iter()
+            .find(|step| step.label == "length_penalty")
+            .unwrap();
+        assert_eq!(length_penalty.value, 1.0);
+        assert!(scored.score > 0.0);
+    }
+
+    #[test]
+    fn titles_starting_with_the_query_get_a_bonus() {
+        let mut ranker = super::Ranker::new("the matrix");
+        ranker.add_search_result("https://a.com/", "The Matrix (1999)", "");
+        ranker.add_search_result("https://b.com/", "Matrix multiplication", "");
+        ranker.add_search_result("https://c.com/", "Reviews of The Matrix", "");
+
+        let starts_with = |i| ranker.score_at(i).features.title_match.starts_with;
+        assert_eq!(starts_with(0), 1.0);
+        assert_eq!(starts_with(1), 0.5);
+        assert_eq!(starts_with(2), 0.0);
+        let contributions = ranker.score_at(0).contributions;
+        assert!(contributions.steps.iter().any(|step| step.label == "title_starts_with"));
+    }
+
+    #[test]
+    fn exact_domain_matches_rank_first() {
+        let mut ranker = super::Ranker::new("github");
+        ranker.add_search_result(
+            "https://en.wikipedia.org/wiki/GitHub",
+            "GitHub - Wikipedia",
+            "GitHub is a platform for hosting code",
+        );
+        ranker.add_search_result("https://github.com/", "Build software better", "");
+
+        assert!(!ranker.score_at(0).features.exact_domain_match);
+        assert!(ranker.score_at(1).features.exact_domain_match);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://github.com/");
+    }
+
+    #[test]
+    fn missing_extracts_move_their_weight_to_the_title() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Rust ownership", "");
+        ranker.add_search_result(
+            "https://b.com/",
+            "Rust ownership",
+            "Every value has a single owner, and it is dropped when the owner goes out of scope",
+        );
+        assert_eq!(ranker.score_at(0).features.extract_completeness, 0.0);
+        assert_eq!(ranker.score_at(1).features.extract_completeness, 1.0);
+        let filled = ranker.score_at(0).score;
+        let full = ranker.score_at(1).score;
+
+        ranker.config.fill_missing_extracts = false;
+        assert!(filled > ranker.score_at(0).score);
+        assert_eq!(full, ranker.score_at(1).score);
+    }
+
+    #[test]
+    fn https_is_preferred_when_scores_are_close() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.length_penalty = 0.0;
+        ranker.add_search_result("http://example.com/", "Rust", "");
+        ranker.add_search_result("https://example.com/", "Rust", "");
+
+        assert!(!ranker.score_at(0).features.https);
+        assert!(ranker.score_at(1).features.https);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://example.com/");
+
+        ranker.config.https_weight = 0.0;
+        assert_eq!(ranker.score_at(0).score, ranker.score_at(1).score);
+    }
+
+    #[test]
+    fn tracking_urls_are_demoted() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.length_penalty = 0.0;
+        ranker.add_search_result("https://example.com/?utm_source=feed", "Rust", "");
+        ranker.add_search_result("https://example.com/", "Rust", "");
+
+        assert!(ranker.score_at(0).features.tracking_url);
+        assert!(!ranker.score_at(1).features.tracking_url);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://example.com/");
+
+        ranker.config.tracking_url_penalty = 0.0;
+        assert_eq!(ranker.score_at(0).score, ranker.score_at(1).score);
+    }
+
+    #[test]
+    fn spammy_urls_are_penalised() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.length_penalty = 0.0;
+        ranker.add_search_result("https://rust.example.xyz/", "Rust", "");
+        ranker.add_search_result("https://rust.example.org/", "Rust", "");
+
+        let spam = ranker.score_at(0);
+        let honest = ranker.score_at(1);
+        assert_eq!(spam.features.url_spam_score, 1.0 / 3.0);
+        let penalty = spam.contributions.steps.last().unwrap();
+        assert_eq!(penalty.label, "url_spam");
+        assert_eq!(spam.score, honest.score - 0.1 / 3.0);
+    }
+
+    #[test]
+    fn results_in_another_language_are_demoted() {
+        let english = "A language empowering everyone to build reliable and efficient software";
+        let german = "Eine Sprache, die jeden befähigt, zuverlässige Software zu bauen";
+        let mut ranker = super::Ranker::new("rust software");
+        ranker.add_search_result("https://example.de/", "Rust", german);
+        ranker.add_search_result("https://example.uk/", "Rust", english);
+        assert!(!ranker.score_at(0).features.language_mismatch);
+
+        ranker.set_locale("en-GB");
+        assert!(ranker.score_at(0).features.language_mismatch);
+        assert!(!ranker.score_at(1).features.language_mismatch);
+        let demoted = ranker.score_at(0).score;
+        ranker.config.language_mismatch_penalty = 0.0;
+        assert_eq!(demoted, ranker.score_at(0).score * 0.5);
+    }
+
+    #[test]
+    fn shallow_paths_rank_above_deep_ones() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.config.length_penalty = 0.0;
+        ranker.add_search_result("https://example.com/a/b/c/d/foo", "Rust", "");
+        ranker.add_search_result("https://example.com/foo", "Rust", "");
+
+        assert_eq!(ranker.score_at(0).features.path_depth, 5);
+        assert_eq!(ranker.score_at(1).features.path_depth, 1);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://example.com/foo");
+        assert!(ranked[0].score > ranked[1].score);
+    }
+
+    #[test]
+    fn titles_with_every_term_get_a_bonus() {
+        let mut ranker = super::Ranker::new("rust borrow checker");
+        ranker.add_search_result("https://a.com/", "Rust", "");
+        ranker.add_search_result("https://b.com/", "Fighting the borrow checker in Rust", "");
+
+        assert!(!ranker.score_at(0).features.title_all_terms);
+        assert!(ranker.score_at(1).features.title_all_terms);
+        let contributions = ranker.score_at(1).contributions;
+        assert!(contributions.steps.iter().any(|step| step.label == "all_terms_in_title"));
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://b.com/");
+
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Rust", "");
+        assert!(!ranker.score_at(0).features.title_all_terms);
+    }
+
+    #[test]
+    fn adjacent_terms_rank_above_scattered_terms() {
+        let mut ranker = super::Ranker::new("rust book");
+        ranker.add_search_result("https://a.com/", "Book reviews about Rust", "");
+        ranker.add_search_result("https://b.com/", "The Rust book", "");
+
+        assert_eq!(ranker.score_at(1).features.title_match.proximity, 1.0);
+        assert_eq!(ranker.score_at(0).features.title_match.proximity, 0.5);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://b.com/");
+    }
+
+    #[test]
+    fn redirects_match_and_dedup_on_final_url() {
+        use crate::metadata::ResultMetadata;
+
+        let redirect = |final_url: &str| ResultMetadata {
+            final_url: Some(final_url.to_string()),
+            redirect_depth: 2,
+            ..ResultMetadata::default()
+        };
+        let mut ranker = super::Ranker::new("rust");
+        let metadata = redirect("https://rust-lang.org/");
+        ranker.add_result("https://t.co/abc", "Home", "", &metadata);
+        ranker.add_result("https://bit.ly/xyz", "Home page", "", &metadata);
+        ranker.add_search_result("https://example.com/", "Home", "");
+
+        let features = ranker.score_at(0).features;
+        assert_eq!(features.domain_match.num_terms, 1);
+        assert_eq!(features.redirect_depth, 2);
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].result.url.as_str(), "https://t.co/abc");
+        assert_eq!(ranked[0].result.match_url(), "https://rust-lang.org/");
+
+        ranker.config.url_filter.safe_search = true;
+        assert!(!ranker.add_result("https://t.co/d", "", "", &redirect("https://xxx.com/")));
+
+        // Whichever comes first, only the higher ranked of a redirect and the
+        // page it leads to is kept
+        for redirect_first in [true, false] {
+            let mut ranker = super::Ranker::new("rust");
+            ranker.config.dedup_locale_variants = false;
+            let metadata = redirect("https://Rust-lang.org#home");
+            if redirect_first {
+                ranker.add_result("https://t.co/abc", "Rust", "", &metadata);
+            }
+            ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+            if !redirect_first {
+                ranker.add_result("https://t.co/abc", "Rust", "", &metadata);
+            }
+            let best = ranker.score_at(0).score.max(ranker.score_at(1).score);
+            let ranked = ranker.ranked_results();
+            assert_eq!(ranked.len(), 1);
+            assert_eq!(ranked[0].score, best);
+        }
+    }
+
+    #[test]
+    fn hyphenated_terms_match_joined_terms() {
+        let mut ranker = super::Ranker::new("email");
+        ranker.add_search_result("https://a.com/", "E-mail", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+
+        let mut ranker = super::Ranker::new("e-mail");
+        ranker.add_search_result("https://a.com/", "Email", "");
+        ranker.add_search_result("https://b.com/", "E-mail", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+        assert_eq!(ranker.score_at(1).features.title_match.num_terms, 3);
+    }
+
+    #[test]
+    fn plurals_are_folded_when_enabled() {
+        let mut ranker = super::Ranker::new("cheap laptop");
+        ranker.add_search_result("https://a.com/", "Cheap laptops", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 1);
+
+        let mut config = ranker.config.clone();
+        config.rewrite.fold_plurals = true;
+        let mut ranker = super::Ranker::with_config("cheap laptops", config, "default");
+        ranker.add_search_result("https://a.com/", "Cheap laptop", "");
+        assert_eq!(ranker.rewrite.terms, vec!["cheap", "laptop"]);
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 2);
+    }
+
+    #[test]
+    fn token_positions_follow_the_results() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Learn Rust", "Nothing here");
+        ranker.add_search_result("https://b.com/", "Rust", "");
+        assert_eq!(ranker.score_at(0).features.title_first_position, 1.0);
+        assert_eq!(ranker.score_at(0).features.extract_first_position, 2.0);
+
+        assert!(ranker.remove_search_result(0));
+        assert_eq!(ranker.score_at(0).features.title_first_position, 0.0);
+        assert_eq!(ranker.token_positions.len(), 1);
+    }
+
+    #[test]
+    fn locale_variants_are_deduplicated() {
+        let mut ranker = super::Ranker::new("rust");
+        let extract = "Rust is a programming language";
+        ranker.add_search_result("https://en.m.wikipedia.org/wiki/Rust", "Rust", extract);
+        ranker.add_search_result("https://de.wikipedia.org/wiki/Rust", "Rust", extract);
+        ranker.add_search_result("https://en.wikipedia.org/wiki/Rust", "Rust", extract);
+        ranker.add_search_result("https://en.wikipedia.org/wiki/Rust_(fungus)", "Rust", "");
+        let urls = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.result.url.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(urls(&ranker).len(), 2);
+        assert!(urls(&ranker).contains(&"https://en.wikipedia.org/wiki/Rust_(fungus)".into()));
+
+        ranker.set_locale("en-GB");
+        assert_eq!(urls(&ranker)[0], "https://en.wikipedia.org/wiki/Rust");
+    }
+
+    #[test]
+    fn backend_order_is_blended_in() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/", "Other", "");
+        ranker.add_search_result("https://b.com/", "Unrelated", "");
+        ranker.add_search_result("https://c.com/", "Rust", "");
+        let order = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.index)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(order(&ranker)[0], 2);
+
+        ranker.config.backend_rank_weight = 1.0;
+        assert_eq!(order(&ranker), vec![0, 1, 2]);
+        ranker.config.backend_rank_weight = 0.6;
+        assert_eq!(order(&ranker)[0], 0);
+        ranker.config.backend_rank_weight = 0.2;
+        assert_eq!(order(&ranker)[0], 2);
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_fused_order() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://x.com/", "Other", "");
+        ranker.add_search_result("https://rust-lang.org/", "Rust", "");
+        ranker.config.backend_rank_weight = 1.0;
+        ranker.config.hysteresis_margin = 0.1;
+        let order = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.index)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(order(&ranker), vec![0, 1]);
+
+        // Moving a result up the fused ranking by more than the margin
+        // passes one that was above it last time
+        ranker.cache_ranking(ranker.ranked_results());
+        ranker.config.backend_rank_weight = 0.2;
+        assert_eq!(order(&ranker), vec![1, 0]);
+    }
+
+    #[test]
+    fn bm25_scoring_does_not_saturate() {
+        let query = "rust async runtime tutorial";
+        let flags = super::BehaviorFlag::Bm25Scoring as u32;
+        let mut ranker = super::Ranker::new_with_flags(query, flags);
+        ranker.add_search_result("https://a.com/", "Rust async runtime tutorial", "");
+        ranker.add_search_result("https://b.com/", "Rust tutorial", "");
+        ranker.add_search_result("https://c.com/", "Python", "");
+        let scores = (0..3)
+            .map(|index| ranker.score_at(index).features.title_match.score)
+            .collect::<Vec<f32>>();
+        assert!(scores[0] > scores[1]);
+        assert!(scores[1] > 0.0);
+        assert_eq!(scores[2], 0.0);
+
+        ranker.add_search_result("https://d.com/", "Rust", "");
+        let score = ranker.score_at(1).features.title_match.score;
+        assert!(score != scores[1]);
+    }
+
+    #[test]
+    fn entities_only_match_as_a_whole() {
+        let mut ranker = super::Ranker::new("new york times");
+        ranker.add_search_result("https://a.com/", "New times", "");
+        ranker.add_search_result("https://b.com/", "The New York Times", "");
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 2);
+
+        ranker.set_entities(vec!["New York Times".to_string()]);
+        assert_eq!(ranker.score_at(0).features.title_match.num_terms, 0);
+        let features = ranker.score_at(1).features;
+        assert_eq!(features.title_match.num_terms, 1);
+        assert_eq!(features.title_match.length, 14);
+    }
+
+    #[test]
+    fn scoring_constants_are_configurable() {
+        let mut ranker = super::Ranker::new("rust book");
+        ranker.config.starts_with_weight = 0.0;
+        ranker.config.fill_missing_extracts = false;
+        ranker.add_search_result("https://example.com/", "Rust", "");
+        let default_score = ranker.score_at(0).score;
+
+        let config = super::RankerConfig {
+            match_exponent: 1.0,
+            length_penalty: 0.0,
+            ..super::RankerConfig::default()
+        };
+        ranker.set_config(config).unwrap();
+        let scored = ranker.score_at(0);
+        assert_eq!(scored.features.title_match.score, 0.25);
+        assert!(scored.score > default_score);
+        assert_eq!(ranker.profile, "custom");
+
+        ranker.set_flags(super::BehaviorFlag::LegacyScoring as u32);
+        assert_eq!(ranker.score_at(0).score, default_score);
+    }
+
+    #[test]
+    fn spammy_titles_are_demoted() {
+        let mut ranker = super::Ranker::new("shoes");
+        ranker.add_search_result("https://a.com/", "Cheap shoes | Best shoes | Buy shoes", "Shoes");
+        ranker.add_search_result("https://b.com/", "Running shoes", "Shoes");
+
+        let spam = ranker.score_at(0);
+        assert!(spam.features.title_keyword_list);
+        assert!(spam.contributions.steps.iter().any(|step| step.label == "title_spam"));
+        assert!(spam.score < ranker.score_at(1).score);
+    }
+
+    #[test]
+    fn ties_are_sorted_by_title() {
+        let mut ranker = super::Ranker::new("recipes");
+        for title in ["Soup", "bake", "Cake"] {
+            ranker.add_search_result(&format!("https://{}.example/", title), title, "");
+        }
+        let titles = |ranker: &super::Ranker| {
+            ranker
+                .ranked_results()
+                .iter()
+                .map(|scored| scored.result.title.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(titles(&ranker), vec!["Soup", "bake", "Cake"]);
+
+        ranker.config.sort_ties_by_title = true;
+        assert_eq!(titles(&ranker), vec!["bake", "Cake", "Soup"]);
+    }
+
+    #[test]
+    fn terms_matched_across_fields_beat_one_term_everywhere() {
+        let mut ranker = super::Ranker::new("rust tokio");
+        ranker.add_search_result("https://rust.com/rust", "Rust", "Rust is a language");
+        ranker.add_search_result("https://example.com/tokio", "Rust", "");
+
+        let everywhere = ranker.score_at(0);
+        let split = ranker.score_at(1);
+        assert_eq!(everywhere.features.num_terms_matched, 1);
+        assert_eq!(split.features.num_terms_matched, 2);
+        assert!(split.score > everywhere.score);
+
+        ranker.config.cross_field_weight = 0.0;
+        assert!(ranker.score_at(1).score < ranker.score_at(0).score);
+    }
+
+    #[test]
+    fn terms_clustered_in_the_extract_are_boosted() {
+        let mut ranker = super::Ranker::new("rust tokio");
+        ranker.add_search_result(
+            "https://a.com/notes",
+            "Notes",
+            "Rust is fast. The weather is nice. Tokio is popular.",
+        );
+        ranker.add_search_result(
+            "https://b.com/notes",
+            "Notes",
+            "Rust and Tokio make async easy. The weather is nice.",
+        );
+
+        let spread = ranker.score_at(0);
+        let clustered = ranker.score_at(1);
+        assert_eq!(spread.features.extract_clustering, 0.0);
+        assert_eq!(clustered.features.extract_clustering, 1.0);
+        assert!(clustered.score > spread.score);
+    }
+
+    #[test]
+    fn junk_text_is_demoted() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://a.com/rust", "Rust", "1.70 | 1.71 | 1.72 | 1.73 >>");
+        ranker.add_search_result("https://b.com/rust", "Rust", "Notes on each release");
+
+        let junk = ranker.score_at(0);
+        assert!(junk.features.alphabetic_ratio < 0.5);
+        assert!(junk.contributions.steps.iter().any(|step| step.label == "junk_text"));
+        assert!(junk.score < ranker.score_at(1).score);
+    }
+
+    #[test]
+    fn field_contributions_are_capped() {
+        let mut ranker = super::Ranker::new("rust");
+        ranker.add_search_result("https://example.com/", "Rust", "Rust");
+        let uncapped = ranker.score_at(0).contributions;
+        assert!(uncapped.steps[0].value > 0.5);
+
+        ranker.config.field_caps.insert("title".to_string(), 0.5);
+        let capped = ranker.score_at(0).contributions;
+        assert_eq!(capped.steps[0].value, 0.5);
+        assert_eq!(capped.steps[1].value, uncapped.steps[1].value);
+    }
+
+    #[test]
+    fn profile_follows_query_intent() {
+        assert_eq!(super::Ranker::new("github.com").profile, "navigational");
+        assert_eq!(super::Ranker::new("github").profile, "navigational");
+        assert_eq!(super::Ranker::new("github").intent().name(), "navigational");
+        assert_eq!(super::Ranker::new("rust web framework").profile, "default");
+        let ranker = super::Ranker::new_with_profile("github.com", "research").unwrap();
+        assert_eq!(ranker.profile, "research");
+
+        let mut ranker = super::Ranker::new("github.com");
+        let config = super::RankerConfig {
+            profile: Some("research".to_string()),
+            ..super::RankerConfig::default()
+        };
+        ranker.set_config(config).unwrap();
+        assert_eq!(ranker.profile, "research");
+        assert_eq!(ranker.config.diversity, 0.3);
+        assert!(ranker.set_config(super::RankerConfig::default()).is_ok());
+        assert_eq!(ranker.profile, "custom");
+    }
+
+    #[test]
+    fn strict_validation_rejects_invalid_results() {
+        let mut ranker = super::Ranker::new("url");
+        assert!(ranker.add_search_result("example.com", "", ""));
+        ranker.set_strict_validation(true);
+        assert!(ranker.add_search_result("https://example.com/", "Example", ""));
+        assert!(!ranker.add_search_result("example.com", "Example", ""));
+
+        assert_eq!(ranker.len(), 2);
+        assert_eq!(ranker.ingest_errors.len(), 1);
+        assert_eq!(ranker.ingest_errors[0].url, "example.com");
+    }
+
+    #[test]
+    fn diversity_demotes_repeated_domains() {
+        let config = super::RankerConfig {
+            diversity: 0.9,
+            ..super::RankerConfig::default()
+        };
+        let mut ranker = super::Ranker::with_config("rust", config, "custom");
+        ranker.add_search_result("https://a.com/rust", "Rust", "Rust");
+        ranker.add_search_result("https://a.com/rust-lang", "Rust", "Rust");
+        ranker.add_search_result("https://bb.com/about", "Rust", "");
+
+        let ranked = ranker.ranked_results();
+        assert_eq!(ranked[0].result.url.as_str(), "https://a.com/rust");
+        assert_eq!(ranked[1].result.url.as_str(), "https://bb.com/about");
+    }
+
+    #[test]
+    fn test_get_query_regex() {
+        let query = "web web";
+        let (regex, num_unique_terms, max_length) = query_regex(query);
+        assert_eq!(regex.as_str(), "\\bweb\\b");
+        assert_eq!(max_length, 3);
+        assert_eq!(num_unique_terms, 1);
+    }
+
+    #[test]
+    fn test_get_features_without_a_valid_url() {
+        let (regex, num_unique_terms, total_possible_length) = query_regex("example");
+        let search_result = super::SearchResult::new("example.com/page", "Page", "");
+        let features = super::get_features(
+            &regex,
+            search_result,
+            total_possible_length,
+            num_unique_terms,
+            &super::QueryTerms::default(),
+            &super::RankerConfig::default(),
+        );
+        assert!(features.url_parse_failed);
+        assert_eq!(features.domain_match.num_terms, 1);
+    }
+
+    #[test]
+    fn test_get_features_limits_matches() {
+        let (regex, num_unique_terms, total_possible_length) = query_regex("a b");
+        let search_result = super::SearchResult::new("https://example.com/", "", &"a ".repeat(100));
+        let features = super::get_features(
+            &regex,
+            search_result,
+            total_possible_length,
+            num_unique_terms,
+            &super::QueryTerms::default(),
+            &super::RankerConfig::default(),
+        );
+        assert!(features.match_limit_hit);
+        assert_eq!(features.extract_match.num_terms, 1);
+
+        let (_, num_unique_terms, _) = query_regex(&"term ".repeat(10_000));
+        assert_eq!(num_unique_terms, 1);
+    }
+
+    #[test]
+    fn test_get_features() {
+        let query = "url";
+        let (regex, num_unique_terms, total_possible_length) = query_regex(query);
+        let search_result = super::SearchResult::new("https://en.wikipedia.org/wiki/URL", " URL", "A URL is a reference to a web resource that specifies its location on a computer network and a mechanism for retrieving it.");
+        let features = super::get_features(
+            &regex,
+            search_result,
+            total_possible_length,
+            num_unique_terms,
+            &super::QueryTerms::default(),
+            &super::RankerConfig::default(),
+        );
+        println!("{:#?}", features);
+        assert_eq!(features.title_match.length, 3);
+        assert_eq!(features.title_match.last_char, 4);
+        assert_eq!(features.title_match.num_terms, 1);
+        assert_eq!(features.title_match.score, 0.25);
+        assert_eq!(features.title_match.term_proportion, 1.0);
+    }
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+// Optional details about a result that the backend knows from crawling it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultMetadata {
+    // A hint of the kind of page, as for `add_search_result_with_type`
+    pub page_type: String,
+    // Where the URL ends up after following redirects. Results are matched
+    // and deduplicated on this URL, but the original is displayed.
+    pub final_url: Option<String>,
+    // How many redirects were followed to reach `final_url`
+    pub redirect_depth: u8,
+    // The crawler found a robots noindex directive on the page
+    pub noindex_detected: bool,
+    // The crawler suspects the page is an error page served with a 200
+    pub soft404_suspected: bool,
+    // The page is behind a paywall
+    pub paywalled: bool,
+    // When the page was published, as an ISO 8601 date such as "2024-03-01",
+    // optionally with a time. Used in place of a date in the URL or title.
+    pub published_date: Option<String>,
+}
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Means and standard deviations of each feature in the training data, so
+// that features can be standardized exactly as they were during training
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Standardization {
+    pub means: Vec<f32>,
+    pub stds: Vec<f32>,
+}
+
+// How the score must respond to an increase in a feature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Monotonicity {
+    Increasing,
+    Decreasing,
+}
+
+// A node of a regression tree. Values less than or equal to the threshold go
+// to the left child. Children are indices into the tree's nodes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TreeNode {
+    Split {
+        feature: usize,
+        threshold: f32,
+        left: usize,
+        right: usize,
+    },
+    Leaf {
+        value: f32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tree {
+    // The root is the first node
+    pub nodes: Vec<TreeNode>,
+}
+
+// The `type` of each kind of model, as tagged in JSON
+pub const MODEL_TYPES: [&str; 3] = ["linear", "trees", "ensemble"];
+
+// A model trained offline over named features. Feature indices in weights,
+// standardization and tree splits refer to positions in `features`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Model {
+    Linear {
+        #[serde(default)]
+        version: String,
+        features: Vec<String>,
+        weights: Vec<f32>,
+        #[serde(default)]
+        bias: f32,
+        #[serde(default)]
+        standardization: Option<Standardization>,
+    },
+    // An ensemble of regression trees whose outputs are summed
+    Trees {
+        #[serde(default)]
+        version: String,
+        features: Vec<String>,
+        trees: Vec<Tree>,
+        #[serde(default)]
+        base_score: f32,
+        #[serde(default)]
+        standardization: Option<Standardization>,
+    },
+    // Models trained separately over the same features, e.g. on bootstrap
+    // samples of the training data, whose scores are averaged. How much the
+    // members disagree shows how confident the score is.
+    Ensemble {
+        #[serde(default)]
+        version: String,
+        members: Vec<Model>,
+    },
+}
+
+impl Tree {
+    fn predict(&self, values: &[f32]) -> f32 {
+        let mut index = 0;
+        // A valid tree reaches a leaf within one step per node
+        for _ in 0..self.nodes.len() {
+            match &self.nodes[index] {
+                TreeNode::Leaf { value } => return *value,
+                TreeNode::Split {
+                    feature,
+                    threshold,
+                    left,
+                    right,
+                } => {
+                    index = if values[*feature] <= *threshold {
+                        *left
+                    } else {
+                        *right
+                    };
+                }
+            }
+        }
+        0.0
+    }
+
+    fn validate(&self, num_features: usize) -> Result<(), String> {
+        if self.nodes.is_empty() {
+            return Err("Model has an empty tree".to_string());
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let TreeNode::Split {
+                feature,
+                left,
+                right,
+                ..
+            } = node
+            {
+                // Children must come after their parent, which rules out cycles
+                if *feature >= num_features
+                    || *left <= index
+                    || *right <= index
+                    || *left >= self.nodes.len()
+                    || *right >= self.nodes.len()
+                {
+                    return Err(format!("Invalid split at node {} of tree", index));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The smallest and largest leaf values reachable from each node. Nodes
+    // may share children, so each range is built once from those of the
+    // children, which come after their parent.
+    fn leaf_ranges(&self) -> Vec<(f32, f32)> {
+        let mut ranges = vec![(0.0, 0.0); self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            ranges[index] = match node {
+                TreeNode::Leaf { value } => (*value, *value),
+                TreeNode::Split { left, right, .. } => {
+                    let (left_min, left_max) = ranges[*left];
+                    let (right_min, right_max) = ranges[*right];
+                    (left_min.min(right_min), left_max.max(right_max))
+                }
+            };
+        }
+        ranges
+    }
+
+    // Whether the tree respects the monotonicity of the feature. This is
+    // conservative: every split on the feature must send all higher values to
+    // leaves at least as high (or low) as every leaf for the lower values.
+    fn is_monotonic(&self, feature_index: usize, monotonicity: Monotonicity) -> bool {
+        let ranges = self.leaf_ranges();
+        self.nodes.iter().all(|node| match node {
+            TreeNode::Split {
+                feature,
+                left,
+                right,
+                ..
+            } if *feature == feature_index => {
+                let (left_min, left_max) = ranges[*left];
+                let (right_min, right_max) = ranges[*right];
+                match monotonicity {
+                    Monotonicity::Increasing => left_max <= right_min,
+                    Monotonicity::Decreasing => left_min >= right_max,
+                }
+            }
+            _ => true,
+        })
+    }
+}
+
+impl Model {
+    pub fn version(&self) -> &str {
+        match self {
+            Model::Linear { version, .. }
+            | Model::Trees { version, .. }
+            | Model::Ensemble { version, .. } => version,
+        }
+    }
+
+    pub fn features(&self) -> &[String] {
+        match self {
+            Model::Linear { features, .. } | Model::Trees { features, .. } => features,
+            Model::Ensemble { members, .. } => members.first().map_or(&[], Model::features),
+        }
+    }
+
+    fn standardization(&self) -> Option<&Standardization> {
+        match self {
+            Model::Linear {
+                standardization, ..
+            }
+            | Model::Trees {
+                standardization, ..
+            } => standardization.as_ref(),
+            // Each member standardizes the values itself
+            Model::Ensemble { .. } => None,
+        }
+    }
+
+    pub fn validate(&self, is_known_feature: impl Fn(&str) -> bool) -> Result<(), String> {
+        if let Model::Ensemble { members, .. } = self {
+            if members.is_empty() {
+                return Err("Model ensemble has no members".to_string());
+            }
+            let is_known_feature: &dyn Fn(&str) -> bool = &is_known_feature;
+            for member in members {
+                if let Model::Ensemble { .. } = member {
+                    return Err("Model ensembles can't be nested".to_string());
+                }
+                if member.features() != self.features() {
+                    return Err("Model ensemble members use different features".to_string());
+                }
+                member.validate(is_known_feature)?;
+            }
+            return Ok(());
+        }
+        let features = self.features();
+        if let Some(name) = features.iter().find(|name| !is_known_feature(name)) {
+            return Err(format!("Unknown feature in model: {}", name));
+        }
+        if let Some(standardization) = self.standardization() {
+            if standardization.means.len() != features.len()
+                || standardization.stds.len() != features.len()
+            {
+                return Err("Model standardization doesn't match its features".to_string());
+            }
+            if standardization.stds.iter().any(|std| *std <= 0.0) {
+                return Err("Model standardization has a non-positive std".to_string());
+            }
+        }
+        match self {
+            Model::Linear { weights, .. } if weights.len() != features.len() => Err(format!(
+                "Model has {} weights for {} features",
+                weights.len(),
+                features.len()
+            )),
+            Model::Trees { trees, .. } => trees
+                .iter()
+                .try_for_each(|tree| tree.validate(features.len())),
+            _ => Ok(()),
+        }
+    }
+
+    // Check that the score responds to each constrained feature in the
+    // required direction, returning the first feature that violates it.
+    // Features the model doesn't use are trivially monotonic.
+    pub fn check_monotonicity(
+        &self,
+        constraints: &BTreeMap<String, Monotonicity>,
+    ) -> Result<(), String> {
+        for (name, monotonicity) in constraints {
+            let index = match self.features().iter().position(|feature| feature == name) {
+                Some(index) => index,
+                None => continue,
+            };
+            let is_monotonic = match self {
+                Model::Linear { weights, .. } => match monotonicity {
+                    Monotonicity::Increasing => weights[index] >= 0.0,
+                    Monotonicity::Decreasing => weights[index] <= 0.0,
+                },
+                Model::Trees { trees, .. } => trees
+                    .iter()
+                    .all(|tree| tree.is_monotonic(index, *monotonicity)),
+                Model::Ensemble { members, .. } => members
+                    .iter()
+                    .all(|member| member.check_monotonicity(constraints).is_ok()),
+            };
+            if !is_monotonic {
+                return Err(format!(
+                    "Model violates the {:?} constraint on {}",
+                    monotonicity, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Score the values of `features`, in the same order, along with the
+    // standard deviation of the scores of an ensemble's members
+    pub fn predict_with_std(&self, values: &[f32]) -> (f32, Option<f32>) {
+        match self {
+            Model::Ensemble { members, .. } if !members.is_empty() => {
+                let scores = members
+                    .iter()
+                    .map(|member| member.predict(values))
+                    .collect::<Vec<f32>>();
+                let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+                let variance = scores
+                    .iter()
+                    .map(|score| (score - mean).powi(2))
+                    .sum::<f32>()
+                    / scores.len() as f32;
+                (mean, Some(variance.sqrt()))
+            }
+            _ => (self.predict(values), None),
+        }
+    }
+
+    // Score the values of `features`, in the same order
+    pub fn predict(&self, values: &[f32]) -> f32 {
+        let values = match self.standardization() {
+            Some(standardization) => values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (value - standardization.means[i]) / standardization.stds[i])
+                .collect(),
+            None => values.to_vec(),
+        };
+        match self {
+            Model::Linear { weights, bias, .. } => {
+                bias + values
+                    .iter()
+                    .zip(weights)
+                    .map(|(value, weight)| value * weight)
+                    .sum::<f32>()
+            }
+            Model::Trees {
+                trees, base_score, ..
+            } => base_score + trees.iter().map(|tree| tree.predict(&values)).sum::<f32>(),
+            Model::Ensemble { members, .. } => {
+                let total = members.iter().map(|member| member.predict(&values)).sum::<f32>();
+                total / members.len().max(1) as f32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear_model() -> Model {
+        Model::Linear {
+            version: "test".to_string(),
+            features: vec!["a".to_string(), "b".to_string()],
+            weights: vec![1.0, -2.0],
+            bias: 0.5,
+            standardization: Some(Standardization {
+                means: vec![1.0, 0.0],
+                stds: vec![2.0, 1.0],
+            }),
+        }
+    }
+
+    fn tree_model(left: f32, right: f32) -> Model {
+        Model::Trees {
+            version: "test".to_string(),
+            features: vec!["a".to_string()],
+            trees: vec![Tree {
+                nodes: vec![
+                    TreeNode::Split {
+                        feature: 0,
+                        threshold: 0.5,
+                        left: 1,
+                        right: 2,
+                    },
+                    TreeNode::Leaf { value: left },
+                    TreeNode::Leaf { value: right },
+                ],
+            }],
+            base_score: 0.0,
+            standardization: None,
+        }
+    }
+
+    #[test]
+    fn test_predict_standardizes() {
+        assert_eq!(linear_model().predict(&[3.0, 1.0]), 0.5 + 1.0 - 2.0);
+    }
+
+    #[test]
+    fn test_predict_trees() {
+        let model = tree_model(1.0, 2.0);
+        assert_eq!(model.predict(&[0.0]), 1.0);
+        assert_eq!(model.predict(&[1.0]), 2.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(linear_model().validate(|_| true).is_ok());
+        assert!(linear_model().validate(|name| name == "a").is_err());
+        assert!(tree_model(1.0, 2.0).validate(|_| true).is_ok());
+        let cyclic = Model::Trees {
+            version: String::new(),
+            features: vec!["a".to_string()],
+            trees: vec![Tree {
+                nodes: vec![TreeNode::Split {
+                    feature: 0,
+                    threshold: 0.0,
+                    left: 0,
+                    right: 0,
+                }],
+            }],
+            base_score: 0.0,
+            standardization: None,
+        };
+        assert!(cyclic.validate(|_| true).is_err());
+    }
+
+    #[test]
+    fn test_ensemble() {
+        let ensemble = Model::Ensemble {
+            version: "test".to_string(),
+            members: vec![tree_model(1.0, 2.0), tree_model(1.0, 4.0)],
+        };
+        assert!(ensemble.validate(|_| true).is_ok());
+        assert_eq!(ensemble.features(), ["a".to_string()]);
+        assert_eq!(ensemble.predict_with_std(&[0.0]), (1.0, Some(0.0)));
+        assert_eq!(ensemble.predict_with_std(&[1.0]), (3.0, Some(1.0)));
+        assert_eq!(ensemble.predict(&[1.0]), 3.0);
+        assert_eq!(tree_model(1.0, 2.0).predict_with_std(&[1.0]), (2.0, None));
+
+        let mixed = Model::Ensemble {
+            version: String::new(),
+            members: vec![tree_model(1.0, 2.0), linear_model()],
+        };
+        assert!(mixed.validate(|_| true).is_err());
+        let empty = Model::Ensemble {
+            version: String::new(),
+            members: Vec::new(),
+        };
+        assert!(empty.validate(|_| true).is_err());
+    }
+
+    #[test]
+    fn test_check_monotonicity() {
+        let mut constraints = BTreeMap::new();
+        constraints.insert("a".to_string(), Monotonicity::Increasing);
+        assert!(linear_model().check_monotonicity(&constraints).is_ok());
+        assert!(tree_model(1.0, 2.0).check_monotonicity(&constraints).is_ok());
+        assert!(tree_model(2.0, 1.0).check_monotonicity(&constraints).is_err());
+
+        constraints.insert("b".to_string(), Monotonicity::Increasing);
+        assert!(linear_model().check_monotonicity(&constraints).is_err());
+    }
+
+    #[test]
+    fn test_check_monotonicity_of_shared_children() {
+        // Each split sends both sides to the next, so there are 2^64 paths
+        let mut nodes = (0..64)
+            .map(|index| TreeNode::Split {
+                feature: 0,
+                threshold: 0.5,
+                left: index + 1,
+                right: index + 1,
+            })
+            .collect::<Vec<TreeNode>>();
+        nodes.push(TreeNode::Leaf { value: 1.0 });
+        let model = Model::Trees {
+            version: String::new(),
+            features: vec!["a".to_string()],
+            trees: vec![Tree { nodes }],
+            base_score: 0.0,
+            standardization: None,
+        };
+        assert!(model.validate(|_| true).is_ok());
+        let mut constraints = BTreeMap::new();
+        constraints.insert("a".to_string(), Monotonicity::Increasing);
+        assert!(model.check_monotonicity(&constraints).is_ok());
+    }
+}
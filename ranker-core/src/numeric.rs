@@ -0,0 +1,140 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Only this many numbers in a field are compared against the query
+const MAX_NUMBERS_PER_FIELD: usize = 64;
+
+const BELOW_WORDS: [&str; 4] = ["under", "below", "<", "<="];
+const ABOVE_WORDS: [&str; 4] = ["over", "above", ">", ">="];
+// Words that mean the same as the above when followed by "than"
+const BELOW_THAN_WORDS: [&str; 3] = ["less", "cheaper", "fewer"];
+const ABOVE_THAN_WORDS: [&str; 3] = ["more", "greater", "higher"];
+
+// A number or range of numbers the query asks for, e.g. "under 500"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumericConstraint {
+    Exact(f64),
+    AtMost(f64),
+    AtLeast(f64),
+    Between(f64, f64),
+}
+
+impl NumericConstraint {
+    fn accepts(&self, value: f64) -> bool {
+        match *self {
+            NumericConstraint::Exact(number) => value == number,
+            NumericConstraint::AtMost(max) => value <= max,
+            NumericConstraint::AtLeast(min) => value >= min,
+            NumericConstraint::Between(min, max) => min <= value && value <= max,
+        }
+    }
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    let token = token.trim_start_matches(['$', '£', '€']).replace(',', "");
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn parse_range(token: &str) -> Option<(f64, f64)> {
+    let (min, max) = token.split_once('-')?;
+    Some((parse_number(min)?, parse_number(max)?))
+}
+
+// Find the numbers and ranges in the query
+pub fn query_constraints(query: &str) -> Vec<NumericConstraint> {
+    let query = query.to_lowercase();
+    let tokens = query.split_whitespace().collect::<Vec<&str>>();
+    let number_at = |i: usize| tokens.get(i).and_then(|token| parse_number(token));
+    let mut constraints = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let followed_by_than = tokens.get(i + 1) == Some(&"than");
+        let (constraint, length) = if BELOW_WORDS.contains(&token) {
+            (number_at(i + 1).map(NumericConstraint::AtMost), 2)
+        } else if ABOVE_WORDS.contains(&token) {
+            (number_at(i + 1).map(NumericConstraint::AtLeast), 2)
+        } else if BELOW_THAN_WORDS.contains(&token) && followed_by_than {
+            (number_at(i + 2).map(NumericConstraint::AtMost), 3)
+        } else if ABOVE_THAN_WORDS.contains(&token) && followed_by_than {
+            (number_at(i + 2).map(NumericConstraint::AtLeast), 3)
+        } else if token == "between" && tokens.get(i + 2) == Some(&"and") {
+            let range = number_at(i + 1).zip(number_at(i + 3));
+            (
+                range.map(|(min, max)| NumericConstraint::Between(min, max)),
+                4,
+            )
+        } else if let Some((min, max)) = parse_range(token) {
+            (Some(NumericConstraint::Between(min, max)), 1)
+        } else if let (Some(min), Some(&"to"), Some(max)) =
+            (parse_number(token), tokens.get(i + 1), number_at(i + 2))
+        {
+            (Some(NumericConstraint::Between(min, max)), 3)
+        } else {
+            (parse_number(token).map(NumericConstraint::Exact), 1)
+        };
+        match constraint {
+            Some(constraint) => {
+                constraints.push(constraint);
+                i += length;
+            }
+            None => i += 1,
+        }
+    }
+    constraints
+}
+
+// Whether any number in the text satisfies any of the constraints
+pub fn matches_constraints(constraints: &[NumericConstraint], text: &str) -> bool {
+    static NUMBER_REGEX: OnceLock<Regex> = OnceLock::new();
+    let number_regex = NUMBER_REGEX.get_or_init(|| Regex::new(r"\d[\d,]*(?:\.\d+)?").unwrap());
+    number_regex
+        .find_iter(text)
+        .take(MAX_NUMBERS_PER_FIELD)
+        .filter_map(|m| parse_number(m.as_str().trim_end_matches(',')))
+        .any(|value| {
+            constraints
+                .iter()
+                .any(|constraint| constraint.accepts(value))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use NumericConstraint::*;
+
+    #[test]
+    fn test_query_constraints() {
+        assert_eq!(query_constraints("laptops under $500"), vec![AtMost(500.0)]);
+        assert_eq!(
+            query_constraints("marathon 2024 results"),
+            vec![Exact(2024.0)]
+        );
+        assert_eq!(
+            query_constraints("tv more than 1,000"),
+            vec![AtLeast(1000.0)]
+        );
+        assert_eq!(
+            query_constraints("between 10 and 20 kg"),
+            vec![Between(10.0, 20.0)]
+        );
+        assert_eq!(
+            query_constraints("1990-1999 films"),
+            vec![Between(1990.0, 1999.0)]
+        );
+        assert_eq!(query_constraints("5 to 10"), vec![Between(5.0, 10.0)]);
+        assert!(query_constraints("under the sea").is_empty());
+    }
+
+    #[test]
+    fn test_matches_constraints() {
+        let constraints = query_constraints("laptops under 500");
+        assert!(matches_constraints(&constraints, "Now only $449.99!"));
+        assert!(!matches_constraints(&constraints, "From $1,299"));
+        assert!(!matches_constraints(&constraints, "No prices here"));
+    }
+}
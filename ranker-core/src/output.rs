@@ -0,0 +1,224 @@
+use crate::features::{serialize_values, FeatureValues, FEATURES};
+use crate::filter::UrlSafety;
+use crate::intent::QueryIntent;
+use crate::waterfall::{Operation, Waterfall};
+use crate::{urls, Features, ScoredResult, SearchResult};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Explanation {
+    // The position of the result in the ranking, starting from zero
+    pub position: usize,
+    pub score: f32,
+    #[serde(serialize_with = "serialize_values")]
+    pub(crate) features: Features,
+    pub contributions: Waterfall,
+    // Pinned by a curation, so `score` is `CURATED_SCORE` and the
+    // contributions end at the score it would have had
+    pub curated: bool,
+}
+
+// Round to a fixed number of decimal places so text renderings are stable
+// across platforms, and never show "-0.0000"
+fn format_value(value: f32) -> String {
+    format!("{:.4}", value + 0.0)
+}
+
+impl Explanation {
+    // A plain text rendering for snapshot tests: the position, URL and score,
+    // every non-zero feature in registry order, then each contribution
+    pub fn render_text(&self, url: &str) -> String {
+        let mut text = format!("#{} {} score={}", self.position, url, format_value(self.score));
+        text.push_str(if self.curated { " curated\n" } else { "\n" });
+        for (definition, value) in FEATURES.iter().zip(self.features.values()) {
+            if value != 0.0 {
+                writeln!(text, "  {}={}", definition.name, format_value(value)).unwrap();
+            }
+        }
+        for step in &self.contributions.steps {
+            let operation = match step.operation {
+                Operation::Add => '+',
+                Operation::Multiply => '*',
+            };
+            writeln!(
+                text,
+                "  {} {} {} = {}",
+                operation,
+                step.label,
+                format_value(step.value),
+                format_value(step.total)
+            )
+            .unwrap();
+        }
+        text
+    }
+}
+
+// A result in `ExplainAll`, with its features as a row of the feature matrix
+#[derive(Clone, Debug, Serialize)]
+pub struct ExplainedResult {
+    // The index the result was added at
+    pub index: usize,
+    pub position: usize,
+    pub score: f32,
+    pub features: Vec<f32>,
+    pub contributions: Waterfall,
+    pub curated: bool,
+}
+
+// The explanations of every result in a ranking in one go
+#[derive(Debug, Serialize)]
+pub struct ExplainAll {
+    // The name of each column of the feature matrix
+    pub feature_names: Vec<&'static str>,
+    // In ranked order
+    pub results: Vec<ExplainedResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Highlights {
+    pub title: Vec<(u32, u32)>,
+    pub extract: Vec<(u32, u32)>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RankStats {
+    pub num_results: usize,
+    // The number of results matching at least one query term in any field
+    pub num_matched: usize,
+    // The highest score of a result that isn't pinned
+    pub top_score: f32,
+
+    // Data quality anomalies, to detect regressions in what the backend sends.
+    // `no_matches` means no result matched any query term at all.
+    pub no_matches: bool,
+    // Results with match features capped at the limit of their type
+    pub num_saturated: usize,
+    pub num_url_parse_failures: usize,
+    // Results with a url, title or extract shortened when added
+    pub num_ingest_truncations: usize,
+    // Scoring stopped early, leaving some results unscored
+    pub truncated: bool,
+    // Kendall's tau between this ranking and the previous one of the same
+    // results, from -1.0 for reversed to 1.0 for unchanged, so the frontend
+    // can skip small reshuffles. Missing if fewer than two results are in both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stability: Option<f32>,
+}
+
+impl RankStats {
+    pub(crate) fn new(scored_results: &[ScoredResult], truncated: bool) -> RankStats {
+        let count = |predicate: fn(&ScoredResult) -> bool| {
+            scored_results.iter().filter(|scored| predicate(scored)).count()
+        };
+        let num_matched = count(|scored| scored.features.num_matched_terms() > 0);
+        RankStats {
+            no_matches: !scored_results.is_empty() && num_matched == 0,
+            num_saturated: count(|scored| scored.features.saturated),
+            num_url_parse_failures: count(|scored| scored.features.url_parse_failed),
+            num_ingest_truncations: count(|scored| scored.result.truncated),
+            truncated,
+            stability: None,
+            num_results: scored_results.len(),
+            num_matched,
+            top_score: scored_results
+                .iter()
+                .filter(|scored| !scored.curated)
+                .map(|scored| scored.score)
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+// The number of top results whose coverage of the query terms is averaged
+const COVERAGE_RESULTS: usize = 3;
+// The number of top results whose domains are counted for diversity
+const DIVERSITY_RESULTS: usize = 10;
+
+// A summary of how good a ranking looks, for the frontend to decide whether
+// to query fallback sources or show a low confidence notice
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SerpQuality {
+    // The highest score of a result that isn't pinned
+    pub top_score: f32,
+    // The mean proportion of unique query terms matched by each of the top
+    // three results, from 0.0 to 1.0
+    pub top_term_coverage: f32,
+    // The proportion of the top ten results on distinct registrable domains,
+    // from 0.0 to 1.0
+    pub diversity: f32,
+    // The coverage, discounted by up to half for a lack of diversity, from
+    // 0.0 for no results to 1.0
+    pub score: f32,
+}
+
+impl SerpQuality {
+    // From the results of a ranking in ranked order
+    pub(crate) fn new(ranked: &[(&SearchResult, &Explanation)]) -> SerpQuality {
+        if ranked.is_empty() {
+            return SerpQuality::default();
+        }
+        let top = &ranked[..ranked.len().min(COVERAGE_RESULTS)];
+        let top_term_coverage = top
+            .iter()
+            .map(|(_, explanation)| explanation.features.term_coverage)
+            .sum::<f32>()
+            / top.len() as f32;
+        let top = &ranked[..ranked.len().min(DIVERSITY_RESULTS)];
+        let domains = top
+            .iter()
+            .map(|(result, _)| {
+                urls::parse_url_features(result.match_url())
+                    .map(|features| features.registrable_domain)
+                    .unwrap_or_default()
+            })
+            .collect::<HashSet<String>>();
+        let diversity = domains.len() as f32 / top.len() as f32;
+        SerpQuality {
+            top_score: ranked
+                .iter()
+                .filter(|(_, explanation)| !explanation.curated)
+                .map(|(_, explanation)| explanation.score)
+                .fold(0.0, f32::max),
+            top_term_coverage,
+            diversity,
+            score: top_term_coverage * (0.5 + 0.5 * diversity),
+        }
+    }
+}
+
+// A result that the user's filters left out of the ranking, for the frontend
+// to offer to show anyway
+#[derive(Serialize)]
+pub struct FilteredResult<'a> {
+    pub result: &'a SearchResult,
+    pub reason: String,
+}
+
+// The output of every ranking entry point. New fields can be added here
+// without breaking existing callers.
+#[derive(Serialize)]
+pub struct RankOutput<'a> {
+    pub intent: QueryIntent,
+    pub profile: &'a str,
+    // Whether scoring stopped early, leaving some results unscored
+    pub truncated: bool,
+    // Whether more results are still expected, see `Ranker::rank_available`
+    pub partial: bool,
+    // The `Ranker::revision` this output was ranked at
+    pub revision: u32,
+    pub results: Vec<&'a SearchResult>,
+    pub scores: Vec<f32>,
+    // How safe the URL of each result looks
+    pub safety: Vec<UrlSafety>,
+    pub stats: RankStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanations: Option<Vec<FeatureValues>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_stds: Option<Vec<f32>>,
+    // The results the filters left out, when `return_filtered` is configured
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filtered: Vec<FilteredResult<'a>>,
+}
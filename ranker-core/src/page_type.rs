@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+// The kind of page a result is, from the schema.org type the backend found
+// in its structured data
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageType {
+    // No hint, or a type we don't distinguish
+    #[default]
+    Unknown,
+    Article,
+    Product,
+    Recipe,
+    Video,
+}
+
+impl PageType {
+    // Map a hint, either one of our names or a schema.org type such as
+    // "NewsArticle" or "VideoObject", to a page type
+    pub fn from_hint(hint: &str) -> PageType {
+        match hint.trim().to_lowercase().as_str() {
+            "article" | "newsarticle" | "blogposting" | "scholarlyarticle" | "techarticle" => {
+                PageType::Article
+            }
+            "product" | "offer" | "productgroup" => PageType::Product,
+            "recipe" => PageType::Recipe,
+            "video" | "videoobject" | "movie" => PageType::Video,
+            _ => PageType::Unknown,
+        }
+    }
+
+    // The hint that maps back to this page type
+    pub fn hint(&self) -> &'static str {
+        match self {
+            PageType::Unknown => "",
+            PageType::Article => "article",
+            PageType::Product => "product",
+            PageType::Recipe => "recipe",
+            PageType::Video => "video",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hint() {
+        assert_eq!(PageType::from_hint("article"), PageType::Article);
+        assert_eq!(PageType::from_hint("NewsArticle"), PageType::Article);
+        assert_eq!(PageType::from_hint("VideoObject"), PageType::Video);
+        assert_eq!(PageType::from_hint("Product"), PageType::Product);
+        assert_eq!(PageType::from_hint("Event"), PageType::Unknown);
+        assert_eq!(
+            PageType::from_hint(PageType::Recipe.hint()),
+            PageType::Recipe
+        );
+    }
+}
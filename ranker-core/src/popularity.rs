@@ -0,0 +1,94 @@
+use crate::encoding::Reader;
+use crate::urls::registrable_domain;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// Bump when the encoding changes, so old tables can still be read
+pub const FORMAT_VERSION: u8 = 1;
+
+// Ranks beyond this are as good as unknown
+const MAX_RANK: f32 = 1_000_000.0;
+
+// How popular domains are, from their rank in a list such as Tranco's where
+// the most popular domain is 1
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DomainPopularity {
+    ranks: HashMap<String, u32>,
+}
+
+impl DomainPopularity {
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    // The rank of the host, or failing that of its registrable domain
+    pub fn rank(&self, host: &str) -> Option<u32> {
+        self.ranks
+            .get(host)
+            .or_else(|| self.ranks.get(registrable_domain(host)))
+            .copied()
+    }
+
+    // From 1.0 for the most popular domain down to 0.0 at `MAX_RANK`, on a
+    // log scale, and 0.0 for domains that aren't listed
+    pub fn popularity(&self, host: &str) -> f32 {
+        match self.rank(host) {
+            Some(rank) => (1.0 - (rank.max(1) as f32).ln() / MAX_RANK.ln()).max(0.0),
+            None => 0.0,
+        }
+    }
+
+    // Encode as a version byte followed by the domains from most to least
+    // popular, so each domain's rank is its position and needn't be stored.
+    // Tables are built by the backend, so this is only for tests.
+    #[cfg(test)]
+    pub fn to_bytes(domains: &[&str]) -> Vec<u8> {
+        use crate::encoding::{write_string, write_varint};
+        let mut bytes = vec![FORMAT_VERSION];
+        write_varint(&mut bytes, domains.len());
+        for domain in domains {
+            write_string(&mut bytes, domain);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<DomainPopularity, String> {
+        let mut reader = Reader::new(bytes, "domain popularity");
+        let version = reader.read_byte()?;
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported domain popularity format version {}",
+                version
+            ));
+        }
+        let mut popularity = DomainPopularity::default();
+        for rank in 1..=reader.read_varint()? {
+            let domain = reader.read_string()?.to_lowercase();
+            let rank = u32::try_from(rank).unwrap_or(u32::MAX);
+            popularity.ranks.entry(domain).or_insert(rank);
+        }
+        reader.finish()?;
+        Ok(popularity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popularity_round_trip() {
+        let bytes = DomainPopularity::to_bytes(&["google.com", "wikipedia.org", "GitHub.com"]);
+        let popularity = DomainPopularity::from_bytes(&bytes).unwrap();
+        assert_eq!(popularity.rank("wikipedia.org"), Some(2));
+        assert_eq!(popularity.rank("en.wikipedia.org"), Some(2));
+        assert_eq!(popularity.rank("github.com"), Some(3));
+        assert_eq!(popularity.rank("example.com"), None);
+
+        assert_eq!(popularity.popularity("google.com"), 1.0);
+        assert!(popularity.popularity("github.com") < 1.0);
+        assert!(popularity.popularity("github.com") > 0.9);
+        assert_eq!(popularity.popularity("example.com"), 0.0);
+        assert!(DomainPopularity::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
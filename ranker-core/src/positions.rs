@@ -0,0 +1,269 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use std::collections::HashMap;
+
+// Where each token occurs in a field, counted in tokens from the start, so
+// that features such as proximity, term order and phrase matches can look up
+// positions rather than scanning the text again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenPositions {
+    positions: HashMap<String, Vec<u32>>,
+    num_tokens: u32,
+    // The position of the first token of each sentence after the first
+    sentence_starts: Vec<u32>,
+}
+
+impl TokenPositions {
+    // Index text that has already been normalized for matching
+    pub fn new(text: &str) -> TokenPositions {
+        let options = TokenizeOptions {
+            lowercase: false,
+            max_tokens: None,
+        };
+        let mut index = TokenPositions::default();
+        let mut previous_end = 0;
+        for token in tokenize(text, &options) {
+            if index.num_tokens > 0 && ends_sentence(&text[previous_end..token.start]) {
+                index.sentence_starts.push(index.num_tokens);
+            }
+            previous_end = token.end;
+            index
+                .positions
+                .entry(token.text)
+                .or_default()
+                .push(index.num_tokens);
+            index.num_tokens += 1;
+        }
+        index
+    }
+
+    pub fn num_tokens(&self) -> u32 {
+        self.num_tokens
+    }
+
+    // The positions of the term in increasing order
+    pub fn positions(&self, term: &str) -> &[u32] {
+        self.positions
+            .get(term)
+            .map(|positions| positions.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // The positions where the term starts. Terms of several words, such as
+    // entities, must appear word for word.
+    pub fn term_positions(&self, term: &str) -> Vec<u32> {
+        let mut words = term.split(' ');
+        let starts = self.positions(words.next().unwrap_or(""));
+        let rest = words.collect::<Vec<&str>>();
+        starts
+            .iter()
+            .copied()
+            .filter(|start| {
+                rest.iter()
+                    .zip(start + 1..)
+                    .all(|(word, position)| self.positions(word).binary_search(&position).is_ok())
+            })
+            .collect()
+    }
+
+    // Whether the text starts with the term
+    pub fn starts_with(&self, term: &str) -> bool {
+        self.term_positions(term).first() == Some(&0)
+    }
+
+    // The position of the earliest occurrence of any of the terms
+    pub fn first_position(&self, terms: &[String]) -> Option<u32> {
+        terms
+            .iter()
+            .filter_map(|term| self.term_positions(term).first().copied())
+            .min()
+    }
+
+    // How close together the terms appear: the number of distinct terms found
+    // divided by the length in tokens of the shortest span containing them
+    // all, so 1 when they are adjacent. Zero if fewer than two terms appear.
+    pub fn proximity(&self, terms: &[String]) -> f32 {
+        let mut occurrences = Vec::new();
+        let mut num_found = 0;
+        for (i, term) in terms.iter().enumerate() {
+            if terms[..i].contains(term) {
+                continue;
+            }
+            let positions = self.term_positions(term);
+            if !positions.is_empty() {
+                occurrences.extend(positions.into_iter().map(|position| (position, num_found)));
+                num_found += 1;
+            }
+        }
+        if num_found < 2 {
+            return 0.0;
+        }
+        occurrences.sort_unstable();
+
+        // Slide a window over the occurrences, keeping it as short as
+        // possible while it contains every term
+        let mut counts = vec![0; num_found];
+        let mut num_in_window = 0;
+        let mut start = 0;
+        let mut shortest = u32::MAX;
+        for &(position, term) in &occurrences {
+            counts[term] += 1;
+            if counts[term] == 1 {
+                num_in_window += 1;
+            }
+            while num_in_window == num_found {
+                let (start_position, start_term) = occurrences[start];
+                shortest = shortest.min(position - start_position + 1);
+                counts[start_term] -= 1;
+                if counts[start_term] == 0 {
+                    num_in_window -= 1;
+                }
+                start += 1;
+            }
+        }
+        (num_found as f32 / shortest as f32).min(1.0)
+    }
+
+    // How much of the terms appear in the same order as in the query: the
+    // longest run of the terms found, in query order, with each appearing
+    // after the one before, divided by the number of terms found. Zero if
+    // fewer than two terms appear.
+    pub fn in_order(&self, terms: &[String]) -> f32 {
+        // Each occurrence, with the longest run of terms in order ending there
+        let mut runs: Vec<(u32, usize)> = Vec::new();
+        let mut num_found = 0;
+        for (i, term) in terms.iter().enumerate() {
+            if terms[..i].contains(term) {
+                continue;
+            }
+            let positions = self.term_positions(term);
+            if positions.is_empty() {
+                continue;
+            }
+            num_found += 1;
+            let ends = positions
+                .into_iter()
+                .map(|position| {
+                    let before = runs
+                        .iter()
+                        .filter(|(earlier, _)| *earlier < position)
+                        .map(|(_, length)| *length)
+                        .max()
+                        .unwrap_or(0);
+                    (position, before + 1)
+                })
+                .collect::<Vec<(u32, usize)>>();
+            runs.extend(ends);
+        }
+        if num_found < 2 {
+            return 0.0;
+        }
+        let longest = runs.iter().map(|(_, length)| *length).max().unwrap_or(0);
+        longest as f32 / num_found as f32
+    }
+
+    // How much the terms found gather in a single sentence rather than being
+    // spread across several: the most distinct terms starting in any one
+    // sentence, less one, divided by the number of terms found less one, so 1
+    // when they share a sentence and 0 when each is in a different one. Zero
+    // if fewer than two terms appear.
+    pub fn clustering(&self, terms: &[String]) -> f32 {
+        let mut counts = vec![0; self.sentence_starts.len() + 1];
+        let mut num_found = 0;
+        for (i, term) in terms.iter().enumerate() {
+            if terms[..i].contains(term) {
+                continue;
+            }
+            let mut sentences = self
+                .term_positions(term)
+                .into_iter()
+                .map(|position| {
+                    self.sentence_starts
+                        .partition_point(|start| *start <= position)
+                })
+                .collect::<Vec<usize>>();
+            if sentences.is_empty() {
+                continue;
+            }
+            num_found += 1;
+            sentences.dedup();
+            for sentence in sentences {
+                counts[sentence] += 1;
+            }
+        }
+        if num_found < 2 {
+            return 0.0;
+        }
+        let most = counts.into_iter().max().unwrap_or(0);
+        (most - 1) as f32 / (num_found - 1) as f32
+    }
+}
+
+// Whether the text between two tokens ends a sentence, such as ". " but not
+// the point in "1.5"
+fn ends_sentence(gap: &str) -> bool {
+    gap.contains(['.', '!', '?']) && gap.ends_with(char::is_whitespace)
+}
+
+// The token positions of the fields of a search result, built when it is added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResultPositions {
+    pub title: TokenPositions,
+    pub extract: TokenPositions,
+    pub domain: TokenPositions,
+    pub path: TokenPositions,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_positions() {
+        let index = TokenPositions::new("the rust book, the rust way");
+        assert_eq!(index.num_tokens(), 6);
+        assert_eq!(index.positions("rust"), &[1, 4]);
+        assert!(index.positions("python").is_empty());
+        let terms = vec!["way".to_string(), "book".to_string()];
+        assert_eq!(index.first_position(&terms), Some(2));
+        assert_eq!(index.first_position(&["python".to_string()]), None);
+        assert_eq!(index.term_positions("the rust"), vec![0, 3]);
+        assert!(index.term_positions("rust the").is_empty());
+        assert!(index.starts_with("the rust book"));
+        assert!(!index.starts_with("rust"));
+    }
+
+    #[test]
+    fn test_proximity() {
+        let terms = |terms: &str| terms.split(',').map(str::to_string).collect::<Vec<_>>();
+        let index = TokenPositions::new("rust is a language, the rust book is free");
+        assert_eq!(index.proximity(&terms("rust,book")), 1.0);
+        assert_eq!(index.proximity(&terms("language,free")), 2.0 / 6.0);
+        assert_eq!(index.proximity(&terms("rust,python")), 0.0);
+        assert_eq!(index.proximity(&terms("rust,rust")), 0.0);
+    }
+
+    #[test]
+    fn test_in_order() {
+        let terms = |terms: &str| terms.split(',').map(str::to_string).collect::<Vec<_>>();
+        let index = TokenPositions::new("hotels: york is new");
+        assert_eq!(index.in_order(&terms("new,york,hotels")), 1.0 / 3.0);
+        assert_eq!(index.in_order(&terms("hotels,york,new")), 1.0);
+        assert_eq!(index.in_order(&terms("york,new,paris")), 1.0);
+        assert_eq!(index.in_order(&terms("new,paris")), 0.0);
+
+        let index = TokenPositions::new("new hotels in new york");
+        assert_eq!(index.in_order(&terms("new,york,hotels")), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_clustering() {
+        let terms = |terms: &str| terms.split(',').map(str::to_string).collect::<Vec<_>>();
+        let index = TokenPositions::new("rust 1.5 is out. tokio is async");
+        assert_eq!(index.clustering(&terms("rust,tokio")), 0.0);
+        let index = TokenPositions::new("rust 1.5 is out. tokio is async! the rust tokio guide");
+        assert_eq!(index.clustering(&terms("rust,tokio")), 1.0);
+        assert_eq!(index.clustering(&terms("rust,async,out")), 0.5);
+        assert_eq!(index.clustering(&terms("out,is,python")), 1.0);
+        assert_eq!(index.clustering(&terms("rust,python")), 0.0);
+    }
+}
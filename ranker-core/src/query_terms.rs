@@ -0,0 +1,163 @@
+use crate::fuzzy::{allowed_edits, edit_distance};
+use crate::rewrite::{QueryRewrite, RewriteConfig};
+use crate::{stemming, stopwords, urls};
+use std::collections::{HashMap, HashSet};
+
+// What matching needs to know about the query terms beyond the query regex
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryTerms {
+    // The unique terms that are scored, so not stopwords, in query order
+    pub scored: Vec<String>,
+    // The terms by their stem when stemming is enabled, otherwise empty
+    pub stems: HashMap<String, String>,
+    // The terms that are stopwords, which match but aren't scored
+    pub stopwords: HashSet<String>,
+    // Words up to this many edits from a term also match it, see `fuzzy`
+    pub max_edit_distance: u8,
+    // The terms only there as synonyms of other terms, whose matches count
+    // for less
+    pub synonyms: HashSet<String>,
+    // The query as it would be spelled in a domain name, with and without
+    // stopwords, e.g. "stackoverflow" for "stack overflow"
+    pub domain_names: Vec<String>,
+}
+
+// Lowercase alphanumeric characters only, e.g. "stackoverflow" for
+// "Stack-Overflow"
+fn compact(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl QueryTerms {
+    pub fn new(
+        rewrite: &QueryRewrite,
+        config: &RewriteConfig,
+        ignore_stopwords: bool,
+    ) -> QueryTerms {
+        let terms = &rewrite.terms;
+        let stopwords = if ignore_stopwords {
+            stopwords::query_stopwords(terms, &config.stopword_language)
+        } else {
+            HashSet::new()
+        };
+        let mut scored: Vec<String> = Vec::new();
+        for term in terms {
+            if !stopwords.contains(term) && !scored.contains(term) {
+                scored.push(term.clone());
+            }
+        }
+        let original_terms = scored
+            .iter()
+            .filter(|term| !rewrite.expanded.contains(*term))
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+        let mut domain_names = vec![compact(&rewrite.normalized_query())];
+        domain_names.push(compact(&original_terms.concat()));
+        domain_names.retain(|name| !name.is_empty());
+        domain_names.dedup();
+        QueryTerms {
+            scored,
+            stems: if config.stemming {
+                stemming::term_stems(terms)
+            } else {
+                HashMap::new()
+            },
+            stopwords,
+            max_edit_distance: config.max_edit_distance,
+            synonyms: rewrite.expanded.iter().cloned().collect(),
+            domain_names,
+        }
+    }
+
+    // The term that text matched by the query regex stands for. With
+    // stemming, that's the term with the same stem, or None for words that
+    // only share the start of a stem.
+    pub fn term_for_match<'a>(&'a self, text: &'a str) -> Option<&'a str> {
+        let is_word = text.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if self.stems.is_empty() || !is_word {
+            return Some(text);
+        }
+        self.stems
+            .get(stemming::stem(text).as_ref())
+            .map(String::as_str)
+    }
+
+    // Whether the registrable domain spells out the query, e.g. "github.com"
+    // for "github" or "stackoverflow.com" for "stack overflow"
+    pub fn names_domain(&self, domain: &str) -> bool {
+        let registrable = urls::registrable_domain(domain);
+        let name = registrable.split('.').next().unwrap_or("");
+        [compact(name), compact(registrable)]
+            .iter()
+            .any(|candidate| self.domain_names.contains(candidate))
+    }
+
+    // The scored single-word term, other than those already matched, that
+    // the word is a near miss of
+    pub fn fuzzy_term(&self, word: &str, matched: &HashSet<&str>) -> Option<&str> {
+        if self.max_edit_distance == 0 {
+            return None;
+        }
+        self.scored
+            .iter()
+            .filter(|term| !term.contains(' ') && !matched.contains(term.as_str()))
+            .find(|term| {
+                let allowed = allowed_edits(term, self.max_edit_distance);
+                allowed > 0
+                    && word.len().abs_diff(term.len()) <= allowed
+                    && edit_distance(word, term) <= allowed
+            })
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_terms() {
+        let rewrite = QueryRewrite {
+            terms: ["how", "to", "receive", "rust", "rust"]
+                .map(str::to_string)
+                .to_vec(),
+            ..QueryRewrite::default()
+        };
+        let config = RewriteConfig {
+            max_edit_distance: 2,
+            ..RewriteConfig::default()
+        };
+        let query_terms = QueryTerms::new(&rewrite, &config, true);
+        assert_eq!(query_terms.scored, vec!["receive", "rust"]);
+        assert!(query_terms.stopwords.contains("to"));
+
+        let mut matched = HashSet::new();
+        assert_eq!(query_terms.fuzzy_term("recieve", &matched), Some("receive"));
+        assert_eq!(query_terms.fuzzy_term("rusty", &matched), Some("rust"));
+        assert_eq!(query_terms.fuzzy_term("dust", &matched), Some("rust"));
+        assert_eq!(query_terms.fuzzy_term("deceiver", &matched), None);
+        matched.insert("rust");
+        assert_eq!(query_terms.fuzzy_term("rusty", &matched), None);
+    }
+
+    #[test]
+    fn test_names_domain() {
+        let config = RewriteConfig::default();
+        let query_terms = |query: &str| {
+            let rewrite = crate::rewrite::rewrite_query(query, &config, 32);
+            QueryTerms::new(&rewrite, &config, true)
+        };
+        assert!(query_terms("github").names_domain("github.com"));
+        assert!(query_terms("GitHub").names_domain("www.github.com"));
+        assert!(query_terms("github.com").names_domain("github.com"));
+        assert!(query_terms("stack overflow").names_domain("stackoverflow.com"));
+        assert!(query_terms("the guardian").names_domain("theguardian.com"));
+        assert!(query_terms("bbc").names_domain("news.bbc.co.uk"));
+        assert!(!query_terms("github").names_domain("github.io.example.com"));
+        assert!(!query_terms("git").names_domain("github.com"));
+        assert!(!query_terms("").names_domain("github.com"));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::serp::SerpBundle;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Where one candidate was ranked in a bundle and when ranked again
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionChange {
+    pub url: String,
+    // Zero-based positions, or None if the result was left out
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+    pub score_before: f32,
+    // None if the result was dropped before scoring, e.g. by the URL filter
+    pub score_after: Option<f32>,
+}
+
+impl PositionChange {
+    // How far the result moved, e.g. "+2" for up two places, "=" for not at
+    // all, "new" for into the ranking and "out" for out of it
+    fn movement(&self) -> String {
+        match (self.before, self.after) {
+            (Some(before), Some(after)) if before == after => "=".to_string(),
+            (Some(before), Some(after)) => format!("{:+}", before as i64 - after as i64),
+            (None, Some(_)) => "new".to_string(),
+            (Some(_), None) => "out".to_string(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+// Pair each result of `before` with the result of `after` with the same URL,
+// in the new ranking order, followed by the results left out of it
+pub fn position_changes(before: &SerpBundle, after: &SerpBundle) -> Vec<PositionChange> {
+    let after_results = after
+        .results
+        .iter()
+        .map(|result| (result.url.as_str(), result))
+        .collect::<HashMap<_, _>>();
+    let mut changes = before
+        .results
+        .iter()
+        .map(|result| {
+            let replayed = after_results.get(result.url.as_str());
+            PositionChange {
+                url: result.url.clone(),
+                before: result.position,
+                after: replayed.and_then(|replayed| replayed.position),
+                score_before: result.score,
+                score_after: replayed.map(|replayed| replayed.score),
+            }
+        })
+        .collect::<Vec<PositionChange>>();
+    changes.sort_by_key(|change| (change.after.unwrap_or(usize::MAX), change.before));
+    changes
+}
+
+fn format_position(position: Option<usize>) -> String {
+    position.map_or("-".to_string(), |position| (position + 1).to_string())
+}
+
+// A plain text report of how the ranking changed, with one-based positions
+pub fn diff_report(before: &SerpBundle, after: &SerpBundle, notes: &[String]) -> String {
+    let changes = position_changes(before, after);
+    let mut report = String::new();
+    writeln!(report, "query:  {}", before.query).unwrap();
+    writeln!(
+        report,
+        "config: {} -> {}",
+        before.config_hash, after.config_hash
+    )
+    .unwrap();
+    let model = |version: &Option<String>| version.clone().unwrap_or_else(|| "none".to_string());
+    writeln!(
+        report,
+        "model:  {} -> {}",
+        model(&before.model_version),
+        model(&after.model_version)
+    )
+    .unwrap();
+    for note in notes {
+        writeln!(report, "note:   {}", note).unwrap();
+    }
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "{:>5} {:>6} {:>6} {:>9} {:>9}  url",
+        "after", "before", "change", "score", "was"
+    )
+    .unwrap();
+    for change in &changes {
+        let score_after = change
+            .score_after
+            .map_or("-".to_string(), |score| format!("{:.4}", score));
+        writeln!(
+            report,
+            "{:>5} {:>6} {:>6} {:>9} {:>9.4}  {}",
+            format_position(change.after),
+            format_position(change.before),
+            change.movement(),
+            score_after,
+            change.score_before,
+            change.url
+        )
+        .unwrap();
+    }
+    let moved = changes
+        .iter()
+        .filter(|change| change.before != change.after)
+        .count();
+    writeln!(report).unwrap();
+    write!(report, "{} of {} results moved", moved, changes.len()).unwrap();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RankerConfig;
+    use crate::metadata::ResultMetadata;
+    use crate::serp::BundledResult;
+
+    fn bundle(results: &[(&str, Option<usize>, f32)]) -> SerpBundle {
+        SerpBundle {
+            version: 1,
+            api_version: "0.1.0".to_string(),
+            query: "rust".to_string(),
+            profile: "default".to_string(),
+            config: RankerConfig::default(),
+            config_hash: "0".to_string(),
+            model_version: None,
+            results: results
+                .iter()
+                .map(|&(url, position, score)| BundledResult {
+                    url: url.to_string(),
+                    title: String::new(),
+                    extract: String::new(),
+                    metadata: ResultMetadata::default(),
+                    position,
+                    score,
+                    features: Default::default(),
+                    contributions: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn changes_follow_the_new_ranking() {
+        let before = bundle(&[("a", Some(0), 2.0), ("b", Some(1), 1.0), ("c", None, 0.5)]);
+        let after = bundle(&[("a", Some(1), 1.0), ("b", Some(0), 3.0)]);
+        let changes = position_changes(&before, &after);
+        let urls = changes.iter().map(|c| c.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["b", "a", "c"]);
+        let movements = changes.iter().map(|c| c.movement()).collect::<Vec<_>>();
+        assert_eq!(movements, vec!["+1", "-1", ""]);
+        assert_eq!(changes[2].score_after, None);
+
+        let report = diff_report(&before, &after, &[]);
+        assert!(report.ends_with("2 of 3 results moved"), "{}", report);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::encoding::{write_f64, write_string, write_varint, Reader};
+use std::collections::BTreeMap;
+
+// Bump when the encoding changes, so old exports can still be read
+pub const FORMAT_VERSION: u8 = 1;
+
+// How long it takes for a skip or bounce to count half as much
+const HALF_LIFE_MS: f64 = 30.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+// Hosts whose penalty has decayed below this are forgotten on export
+const MIN_PENALTY: f64 = 0.01;
+
+pub const SKIP_PENALTY: f64 = 1.0;
+// Going back to the results straight after opening one is a stronger signal
+// than passing it over
+pub const BOUNCE_PENALTY: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct HostRecord {
+    penalty: f64,
+    updated_ms: f64,
+}
+
+impl HostRecord {
+    fn penalty_at(&self, now_ms: f64) -> f64 {
+        let elapsed_ms = (now_ms - self.updated_ms).max(0.0);
+        self.penalty * 0.5f64.powf(elapsed_ms / HALF_LIFE_MS)
+    }
+}
+
+// How often the user skipped or bounced from results on each host, decaying
+// over time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostReputation {
+    hosts: BTreeMap<String, HostRecord>,
+}
+
+impl HostReputation {
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    pub fn record(&mut self, host: &str, penalty: f64, now_ms: f64) {
+        let record = self.hosts.entry(host.to_string()).or_insert(HostRecord {
+            penalty: 0.0,
+            updated_ms: now_ms,
+        });
+        *record = HostRecord {
+            penalty: record.penalty_at(now_ms) + penalty,
+            updated_ms: now_ms,
+        };
+    }
+
+    pub fn penalty(&self, host: &str, now_ms: f64) -> f64 {
+        self.hosts
+            .get(host)
+            .map(|record| record.penalty_at(now_ms))
+            .unwrap_or(0.0)
+    }
+
+    // Encode as a version byte followed by, for each host, the host, its
+    // penalty and when it was last updated. Hosts whose penalty has decayed
+    // away are left out.
+    pub fn to_bytes(&self, now_ms: f64) -> Vec<u8> {
+        let hosts = self
+            .hosts
+            .iter()
+            .filter(|(_, record)| record.penalty_at(now_ms) >= MIN_PENALTY)
+            .collect::<Vec<_>>();
+        let mut bytes = vec![FORMAT_VERSION];
+        write_varint(&mut bytes, hosts.len());
+        for (host, record) in hosts {
+            write_string(&mut bytes, host);
+            write_f64(&mut bytes, record.penalty);
+            write_f64(&mut bytes, record.updated_ms);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<HostReputation, String> {
+        let mut reader = Reader::new(bytes, "host reputation");
+        let version = reader.read_byte()?;
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported host reputation format version {}",
+                version
+            ));
+        }
+        let mut reputation = HostReputation::default();
+        for _ in 0..reader.read_varint()? {
+            let host = reader.read_string()?;
+            let record = HostRecord {
+                penalty: reader.read_f64()?,
+                updated_ms: reader.read_f64()?,
+            };
+            reputation.hosts.insert(host, record);
+        }
+        reader.finish()?;
+        Ok(reputation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn penalties_decay() {
+        let mut reputation = HostReputation::default();
+        reputation.record("farm.example", SKIP_PENALTY, 0.0);
+        reputation.record("farm.example", BOUNCE_PENALTY, 0.0);
+        assert_eq!(reputation.penalty("farm.example", 0.0), 3.0);
+        assert_eq!(reputation.penalty("farm.example", HALF_LIFE_MS), 1.5);
+        assert_eq!(reputation.penalty("other.example", 0.0), 0.0);
+    }
+
+    #[test]
+    fn reputation_round_trip() {
+        let mut reputation = HostReputation::default();
+        reputation.record("farm.example", SKIP_PENALTY, 0.0);
+        reputation.record("old.example", SKIP_PENALTY, -100.0 * HALF_LIFE_MS);
+
+        let bytes = reputation.to_bytes(0.0);
+        let imported = HostReputation::from_bytes(&bytes).unwrap();
+        assert_eq!(imported.penalty("farm.example", 0.0), 1.0);
+        assert_eq!(imported.hosts.len(), 1);
+        assert!(HostReputation::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use crate::stopwords::stopwords;
+use crate::text::plurals::singular;
+use crate::text::tokenizer::{
+    add_joined_hyphenations, strip_apostrophes, tokenize, TokenizeOptions,
+};
+use crate::text::units::normalize_units;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+// Only terms of this many characters or more are split into words, see
+// `segment_term`
+pub const MIN_SEGMENTED_LENGTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RewriteStage {
+    // Take `site:`, `-term` and `!bang` operators out of the terms
+    Operators,
+    Lowercase,
+    // Drop the stopwords of the stopword language, unless the query is nothing
+    // but stopwords
+    Stopwords,
+    // Replace terms using the configured spelling corrections
+    Spelling,
+    // Add the configured synonyms of each term
+    Synonyms,
+}
+
+impl RewriteStage {
+    fn name(&self) -> &'static str {
+        match self {
+            RewriteStage::Operators => "operators",
+            RewriteStage::Lowercase => "lowercase",
+            RewriteStage::Stopwords => "stopwords",
+            RewriteStage::Spelling => "spelling",
+            RewriteStage::Synonyms => "synonyms",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewriteConfig {
+    // The stages to run, in order. Results are matched in lowercase, so
+    // without the lowercase stage terms with capitals never match.
+    pub stages: Vec<RewriteStage>,
+    pub spelling_corrections: BTreeMap<String, String>,
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    // Match plurals with their singular, e.g. "cities" with "city", in both
+    // the query and results. Lighter than stemming, so it changes less.
+    pub fold_plurals: bool,
+    // Multi-word names such as "new york times". Where the query contains
+    // one, results only match the whole name, not its words separately.
+    pub entities: Vec<String>,
+    // Match words with the same English stem as a query term, e.g. "run"
+    // for "running", counting them as the term itself
+    pub stemming: bool,
+    // The language whose stopwords, e.g. "to" and "a" in "how to make a
+    // website", count for nothing when matching and scoring, as an ISO 639-1
+    // code: "en", "de", "fr" or "es". Empty to treat stopwords like any
+    // other term.
+    pub stopword_language: String,
+    // Split a query of a single long term, such as "rustwasmranker", into
+    // words that appear in the results, such as "rust wasm ranker"
+    pub segment_terms: bool,
+    // Let words match a query term of four or more letters despite a typo:
+    // one edit away, or two for terms of eight or more letters, up to this
+    // maximum. 0 disables fuzzy matching.
+    pub max_edit_distance: u8,
+}
+
+impl Default for RewriteConfig {
+    fn default() -> Self {
+        RewriteConfig {
+            stages: vec![RewriteStage::Operators, RewriteStage::Lowercase],
+            spelling_corrections: BTreeMap::new(),
+            synonyms: BTreeMap::new(),
+            fold_plurals: false,
+            entities: Vec::new(),
+            stemming: false,
+            stopword_language: "en".to_string(),
+            segment_terms: true,
+            max_edit_distance: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct QueryOperators {
+    // Domains from `site:` operators. Results on other domains are left out.
+    pub sites: Vec<String>,
+    // Terms from `-term` operators, which results mustn't contain. Negative
+    // numbers such as "-5" are terms rather than operators.
+    pub excluded: Vec<String>,
+    // Bangs such as `!w`, without the `!`, which redirect the search
+    // elsewhere so aren't matched
+    pub bangs: Vec<String>,
+}
+
+// The terms after one stage of the rewrite
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RewriteStep {
+    pub stage: &'static str,
+    pub terms: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct QueryRewrite {
+    // The terms to match results against
+    pub terms: Vec<String>,
+    pub operators: QueryOperators,
+    // The output of every stage, starting with the query split on whitespace
+    // and ending with the final terms
+    pub steps: Vec<RewriteStep>,
+    // The terms only there as synonyms of other terms
+    pub expanded: Vec<String>,
+}
+
+impl QueryRewrite {
+    // The query after every stage but before tokenizing, e.g. "rust book"
+    // for "  Rust site:docs.rs BOOK !w"
+    pub fn normalized_query(&self) -> String {
+        let tokenize_step = self.steps.iter().position(|step| step.stage == "tokenize");
+        match tokenize_step.and_then(|i| i.checked_sub(1)) {
+            Some(i) => self.steps[i].terms.join(" "),
+            None => String::new(),
+        }
+    }
+
+    // The rewrite with its terms replaced by the words of a segmented term
+    pub fn segmented(&self, segments: &[String]) -> QueryRewrite {
+        let mut rewrite = self.clone();
+        rewrite.terms = segments.to_vec();
+        rewrite.steps.push(RewriteStep {
+            stage: "segments",
+            terms: rewrite.terms.clone(),
+        });
+        rewrite
+    }
+}
+
+// Split a term typed without spaces into as few words from the vocabulary as
+// possible, each of two or more characters, e.g. "rustwasmranker" into "rust",
+// "wasm" and "ranker". None if it can't be split into two or more words.
+pub fn segment_term(term: &str, vocabulary: &HashSet<String>) -> Option<Vec<String>> {
+    let boundaries = term
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(term.len()))
+        .collect::<Vec<usize>>();
+    // The fewest words the term up to each boundary splits into, and the
+    // boundary the last of those words starts at
+    let mut best: Vec<Option<(usize, usize)>> = vec![None; boundaries.len()];
+    best[0] = Some((0, 0));
+    for end in 1..boundaries.len() {
+        for start in 0..end - 1 {
+            let word = &term[boundaries[start]..boundaries[end]];
+            let num_words = match best[start] {
+                Some((num_words, _)) if vocabulary.contains(word) && word != term => num_words + 1,
+                _ => continue,
+            };
+            if best[end].is_none_or(|(fewest, _)| num_words < fewest) {
+                best[end] = Some((num_words, start));
+            }
+        }
+    }
+    let mut end = boundaries.len() - 1;
+    let mut words = Vec::new();
+    while end > 0 {
+        let (_, start) = best[end]?;
+        words.push(term[boundaries[start]..boundaries[end]].to_string());
+        end = start;
+    }
+    words.reverse();
+    Some(words).filter(|words| words.len() > 1)
+}
+
+// Run the query through the configured stages, then normalize apostrophes,
+// units and hyphenation the same way as result text, split the terms into
+// tokens, keeping at most `max_terms`, and join the tokens of any entities
+// back into single terms
+pub fn rewrite_query(query: &str, config: &RewriteConfig, max_terms: usize) -> QueryRewrite {
+    let mut terms = query
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    let mut operators = QueryOperators::default();
+    let mut synonyms = Vec::new();
+    let mut steps = vec![RewriteStep {
+        stage: "split",
+        terms: terms.clone(),
+    }];
+    for stage in &config.stages {
+        terms = match stage {
+            RewriteStage::Operators => extract_operators(terms, &mut operators),
+            RewriteStage::Lowercase => terms.iter().map(|term| term.to_lowercase()).collect(),
+            RewriteStage::Stopwords => remove_stopwords(terms, &config.stopword_language),
+            RewriteStage::Spelling => terms
+                .into_iter()
+                .map(|term| match config.spelling_corrections.get(&term) {
+                    Some(correction) => correction.clone(),
+                    None => term,
+                })
+                .collect(),
+            RewriteStage::Synonyms => {
+                let expanded = add_synonyms(terms.clone(), &config.synonyms);
+                synonyms.extend_from_slice(&expanded[terms.len()..]);
+                expanded
+            }
+        };
+        steps.push(RewriteStep {
+            stage: stage.name(),
+            terms: terms.clone(),
+        });
+    }
+
+    let expanded = if synonyms.is_empty() {
+        Vec::new()
+    } else {
+        let original = terms
+            .iter()
+            .filter(|term| !synonyms.contains(term))
+            .cloned()
+            .collect::<Vec<String>>();
+        let original = tokenize_terms(&original.join(" "), config, max_terms);
+        tokenize_terms(&synonyms.join(" "), config, max_terms)
+            .into_iter()
+            .filter(|term| !original.contains(term))
+            .collect()
+    };
+    let terms = tokenize_terms(&terms.join(" "), config, max_terms);
+    steps.push(RewriteStep {
+        stage: "tokenize",
+        terms: terms.clone(),
+    });
+    let terms = if config.entities.is_empty() {
+        terms
+    } else {
+        let terms = group_entities(terms, config, max_terms);
+        steps.push(RewriteStep {
+            stage: "entities",
+            terms: terms.clone(),
+        });
+        terms
+    };
+    QueryRewrite {
+        terms,
+        operators,
+        steps,
+        expanded,
+    }
+}
+
+fn tokenize_terms(text: &str, config: &RewriteConfig, max_terms: usize) -> Vec<String> {
+    let options = TokenizeOptions {
+        lowercase: false,
+        max_tokens: Some(max_terms),
+    };
+    let normalized = strip_apostrophes(text);
+    let normalized = add_joined_hyphenations(&normalize_units(&normalized)).into_owned();
+    tokenize(&normalized, &options)
+        .into_iter()
+        .map(|token| {
+            if config.fold_plurals {
+                singular(&token.text).into_owned()
+            } else {
+                token.text
+            }
+        })
+        .collect()
+}
+
+// Replace each run of terms that spells out an entity with a single term of
+// the entity's tokens separated by spaces, preferring the longest entity
+fn group_entities(terms: Vec<String>, config: &RewriteConfig, max_terms: usize) -> Vec<String> {
+    let mut entities = config
+        .entities
+        .iter()
+        .map(|entity| tokenize_terms(&entity.to_lowercase(), config, max_terms))
+        .filter(|tokens| tokens.len() > 1)
+        .collect::<Vec<Vec<String>>>();
+    entities.sort_by_key(|tokens| std::cmp::Reverse(tokens.len()));
+    let mut grouped = Vec::with_capacity(terms.len());
+    let mut i = 0;
+    while i < terms.len() {
+        match entities
+            .iter()
+            .find(|tokens| terms[i..].starts_with(tokens))
+        {
+            Some(tokens) => {
+                grouped.push(tokens.join(" "));
+                i += tokens.len();
+            }
+            None => {
+                grouped.push(terms[i].clone());
+                i += 1;
+            }
+        }
+    }
+    grouped
+}
+
+fn extract_operators(terms: Vec<String>, operators: &mut QueryOperators) -> Vec<String> {
+    terms
+        .into_iter()
+        .filter(|term| {
+            if let Some(site) = term.strip_prefix("site:").filter(|site| !site.is_empty()) {
+                operators.sites.push(site.to_lowercase());
+                false
+            } else if let Some(excluded) = term
+                .strip_prefix('-')
+                .filter(|t| !t.is_empty() && t.parse::<f64>().is_err())
+            {
+                operators.excluded.push(excluded.to_lowercase());
+                false
+            } else if let Some(bang) = term.strip_prefix('!').filter(|bang| !bang.is_empty()) {
+                operators.bangs.push(bang.to_lowercase());
+                false
+            } else {
+                true
+            }
+        })
+        .collect()
+}
+
+fn remove_stopwords(terms: Vec<String>, language: &str) -> Vec<String> {
+    let stopwords = stopwords(language);
+    let is_stopword = |term: &String| stopwords.contains(&term.to_lowercase().as_str());
+    if terms.iter().all(is_stopword) {
+        return terms;
+    }
+    terms
+        .into_iter()
+        .filter(|term| !is_stopword(term))
+        .collect()
+}
+
+fn add_synonyms(terms: Vec<String>, synonyms: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut expanded = terms.clone();
+    for synonym in terms.iter().filter_map(|term| synonyms.get(term)).flatten() {
+        if !expanded.contains(synonym) {
+            expanded.push(synonym.clone());
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_rewrite_extracts_operators() {
+        let rewrite = rewrite_query(
+            "Rust site:docs.rs -Python async/await",
+            &RewriteConfig::default(),
+            32,
+        );
+        assert_eq!(rewrite.terms, vec!["rust", "async", "await"]);
+        assert_eq!(rewrite.operators.sites, vec!["docs.rs"]);
+        assert_eq!(rewrite.operators.excluded, vec!["python"]);
+        let numbers = rewrite_query("-5 celsius", &RewriteConfig::default(), 32);
+        assert_eq!(numbers.terms, vec!["5", "celsius"]);
+        assert!(numbers.operators.excluded.is_empty());
+        let stages = rewrite
+            .steps
+            .iter()
+            .map(|step| step.stage)
+            .collect::<Vec<&str>>();
+        assert_eq!(stages, vec!["split", "operators", "lowercase", "tokenize"]);
+        assert_eq!(rewrite.normalized_query(), "rust async/await");
+    }
+
+    #[test]
+    fn entities_are_single_terms() {
+        let config = RewriteConfig {
+            entities: vec!["New York Times".to_string(), "New York".to_string()],
+            ..RewriteConfig::default()
+        };
+        let rewrite = rewrite_query("new york times crossword", &config, 32);
+        assert_eq!(rewrite.terms, vec!["new york times", "crossword"]);
+        assert_eq!(rewrite.normalized_query(), "new york times crossword");
+        let rewrite = rewrite_query("new york weather", &config, 32);
+        assert_eq!(rewrite.terms, vec!["new york", "weather"]);
+    }
+
+    #[test]
+    fn bangs_are_removed() {
+        let rewrite = rewrite_query("  !W Rust  BOOK ", &RewriteConfig::default(), 32);
+        assert_eq!(rewrite.terms, vec!["rust", "book"]);
+        assert_eq!(rewrite.operators.bangs, vec!["w"]);
+        assert_eq!(rewrite.normalized_query(), "rust book");
+    }
+
+    #[test]
+    fn configured_stages_run_in_order() {
+        let config = RewriteConfig {
+            stages: vec![
+                RewriteStage::Lowercase,
+                RewriteStage::Stopwords,
+                RewriteStage::Spelling,
+                RewriteStage::Synonyms,
+            ],
+            spelling_corrections: vec![("rsut".to_string(), "rust".to_string())]
+                .into_iter()
+                .collect(),
+            synonyms: vec![("rust".to_string(), vec!["rustlang".to_string()])]
+                .into_iter()
+                .collect(),
+            fold_plurals: false,
+            entities: Vec::new(),
+            stemming: false,
+            stopword_language: "en".to_string(),
+            segment_terms: true,
+            max_edit_distance: 0,
+        };
+        let rewrite = rewrite_query("The Rsut book", &config, 32);
+        assert_eq!(rewrite.terms, vec!["rust", "book", "rustlang"]);
+        assert_eq!(rewrite.expanded, vec!["rustlang"]);
+        assert_eq!(rewrite.steps[2].terms, vec!["rsut", "book"]);
+
+        assert_eq!(rewrite_query("the", &config, 32).terms, vec!["the"]);
+    }
+
+    #[test]
+    fn test_segment_term() {
+        let vocabulary = ["rust", "wasm", "ranker", "rank", "er", "a"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<HashSet<String>>();
+        assert_eq!(
+            segment_term("rustwasmranker", &vocabulary),
+            Some(vec![
+                "rust".to_string(),
+                "wasm".to_string(),
+                "ranker".to_string()
+            ])
+        );
+        assert_eq!(segment_term("rustpython", &vocabulary), None);
+        assert_eq!(segment_term("rust", &vocabulary), None);
+        assert_eq!(segment_term("rusta", &vocabulary), None);
+    }
+
+    #[test]
+    fn units_are_normalized() {
+        let rewrite = rewrite_query("SSD 512GB", &RewriteConfig::default(), 32);
+        assert_eq!(rewrite.terms, vec!["ssd", "512", "gb"]);
+
+        let rewrite = rewrite_query("wi-fi", &RewriteConfig::default(), 32);
+        assert_eq!(rewrite.terms, vec!["wi", "fi", "wifi"]);
+
+        let rewrite = rewrite_query("Rust's don't", &RewriteConfig::default(), 32);
+        assert_eq!(rewrite.terms, vec!["rust", "dont"]);
+    }
+}
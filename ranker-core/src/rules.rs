@@ -0,0 +1,370 @@
+use crate::waterfall::Waterfall;
+
+// A tiny language for user defined score adjustments, e.g.
+//
+//     if domain ends_with ".edu" then * 1.2
+//     if title contains "review" and not domain equals "example.com" then - 0.1
+//
+// Each rule is on its own line (or separated by ';'). Fields are `url`,
+// `domain`, `path`, `title` and `extract`, operators are `contains`,
+// `starts_with`, `ends_with` and `equals`, and comparisons ignore case.
+// Conditions combine with `and`, `or` and `not`, with `and` binding tighter.
+// Actions multiply (`*`), add to (`+`) or subtract from (`-`) the score.
+//
+// Rules can only compare fields to constants and adjust the score, so there
+// is nothing unsafe or unbounded that a user can express.
+
+const MAX_RULES: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Url,
+    Domain,
+    Path,
+    Title,
+    Extract,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Equals,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Compare(Field, Operator, String),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Multiply(f32),
+    Add(f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    condition: Condition,
+    action: Action,
+}
+
+// The values of a result that rules can test
+pub struct RuleContext<'a> {
+    pub url: &'a str,
+    pub domain: &'a str,
+    pub path: &'a str,
+    pub title: &'a str,
+    pub extract: &'a str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(f32),
+    Symbol(char),
+}
+
+// Split the source into the tokens of each rule. Rules end at a newline or
+// ';' outside a quoted string, and empty rules are skipped.
+fn lex(source: &str) -> Result<Vec<Vec<Token>>, String> {
+    let mut rules = Vec::new();
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let rule_error = |e: String| format!("Rule {}: {}", rules.len() + 1, e);
+        if c == '\n' || c == ';' {
+            chars.next();
+            if !tokens.is_empty() {
+                rules.push(std::mem::take(&mut tokens));
+            }
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => s.extend(chars.next()),
+                    Some(c) => s.push(c),
+                    None => return Err(rule_error("Unterminated string".to_string())),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            // Literals too long for an f32 parse as infinity
+            let value = number
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| rule_error(format!("Invalid number: {}", number)))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else if matches!(c, '*' | '+' | '-') {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(rule_error(format!("Unexpected character: {}", c)));
+        }
+    }
+    if !tokens.is_empty() {
+        rules.push(tokens);
+    }
+    Ok(rules)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.position), Some(Token::Word(w)) if w == word)
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Word(w)) if w == word => Ok(()),
+            other => Err(format!("Expected '{}', found {:?}", word, other)),
+        }
+    }
+
+    fn rule(&mut self) -> Result<Rule, String> {
+        self.expect_word("if")?;
+        let condition = self.or()?;
+        self.expect_word("then")?;
+        let action = match (self.next(), self.next()) {
+            (Some(Token::Symbol('*')), Some(Token::Number(n))) => Action::Multiply(n),
+            (Some(Token::Symbol('+')), Some(Token::Number(n))) => Action::Add(n),
+            (Some(Token::Symbol('-')), Some(Token::Number(n))) => Action::Add(-n),
+            _ => return Err("Expected an action like '* 1.2', '+ 0.1' or '- 0.1'".to_string()),
+        };
+        if self.position < self.tokens.len() {
+            return Err("Unexpected input after the action".to_string());
+        }
+        Ok(Rule { condition, action })
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.peek_word("or") {
+            self.position += 1;
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.comparison()?;
+        while self.peek_word("and") {
+            self.position += 1;
+            condition = Condition::And(Box::new(condition), Box::new(self.comparison()?));
+        }
+        Ok(condition)
+    }
+
+    fn comparison(&mut self) -> Result<Condition, String> {
+        // Fold any run of `not`s into at most one, rather than recursing
+        // once for each
+        let mut negated = false;
+        while self.peek_word("not") {
+            self.position += 1;
+            negated = !negated;
+        }
+        let comparison = self.compare()?;
+        if negated {
+            return Ok(Condition::Not(Box::new(comparison)));
+        }
+        Ok(comparison)
+    }
+
+    fn compare(&mut self) -> Result<Condition, String> {
+        let field = match self.next() {
+            Some(Token::Word(w)) => match w.as_str() {
+                "url" => Field::Url,
+                "domain" => Field::Domain,
+                "path" => Field::Path,
+                "title" => Field::Title,
+                "extract" => Field::Extract,
+                _ => return Err(format!("Unknown field: {}", w)),
+            },
+            other => return Err(format!("Expected a field, found {:?}", other)),
+        };
+        let operator = match self.next() {
+            Some(Token::Word(w)) => match w.as_str() {
+                "contains" => Operator::Contains,
+                "starts_with" => Operator::StartsWith,
+                "ends_with" => Operator::EndsWith,
+                "equals" => Operator::Equals,
+                _ => return Err(format!("Unknown operator: {}", w)),
+            },
+            other => return Err(format!("Expected an operator, found {:?}", other)),
+        };
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Condition::Compare(field, operator, s.to_lowercase())),
+            other => Err(format!("Expected a quoted string, found {:?}", other)),
+        }
+    }
+}
+
+pub fn parse_rules(source: &str) -> Result<Vec<Rule>, String> {
+    let rules = lex(source)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, tokens)| {
+            let mut parser = Parser {
+                tokens,
+                position: 0,
+            };
+            parser.rule().map_err(|e| format!("Rule {}: {}", i + 1, e))
+        })
+        .collect::<Result<Vec<Rule>, String>>()?;
+    if rules.len() > MAX_RULES {
+        return Err(format!("At most {} rules are allowed", MAX_RULES));
+    }
+    Ok(rules)
+}
+
+impl Condition {
+    fn matches(&self, context: &RuleContext) -> bool {
+        match self {
+            Condition::Compare(field, operator, value) => {
+                let text = match field {
+                    Field::Url => context.url,
+                    Field::Domain => context.domain,
+                    Field::Path => context.path,
+                    Field::Title => context.title,
+                    Field::Extract => context.extract,
+                }
+                .to_lowercase();
+                match operator {
+                    Operator::Contains => text.contains(value.as_str()),
+                    Operator::StartsWith => text.starts_with(value.as_str()),
+                    Operator::EndsWith => text.ends_with(value.as_str()),
+                    Operator::Equals => text == *value,
+                }
+            }
+            Condition::Not(condition) => !condition.matches(context),
+            Condition::And(a, b) => a.matches(context) && b.matches(context),
+            Condition::Or(a, b) => a.matches(context) || b.matches(context),
+        }
+    }
+}
+
+// Apply every matching rule to the score, in order, labelling each step with
+// the number of the rule
+pub fn apply_rules(rules: &[Rule], context: &RuleContext, contributions: &mut Waterfall) {
+    for (i, rule) in rules.iter().enumerate() {
+        if rule.condition.matches(context) {
+            let label = format!("rule {}", i + 1);
+            match rule.action {
+                Action::Multiply(factor) => contributions.multiply(&label, factor),
+                Action::Add(amount) => contributions.add(&label, amount),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RuleContext<'static> {
+        RuleContext {
+            url: "https://cs.mit.edu/courses",
+            domain: "cs.mit.edu",
+            path: "/courses",
+            title: "Course Reviews",
+            extract: "",
+        }
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let rules = parse_rules(
+            "if domain ends_with \".edu\" then * 2\n\
+             if title contains \"review\" and not path equals \"/\" then - 0.5; \
+             if title contains \"nothing\" or url starts_with \"http:\" then + 10",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        let mut contributions = Waterfall::single("base", 1.0);
+        apply_rules(&rules, &context(), &mut contributions);
+        assert_eq!(contributions.total(), 1.5);
+        assert_eq!(contributions.steps[2].label, "rule 2");
+    }
+
+    #[test]
+    fn test_separators_in_strings() {
+        let rules = parse_rules("if title contains \"c++; go\" then * 2;\n;").unwrap();
+        assert_eq!(rules.len(), 1);
+        let context = RuleContext {
+            title: "C++; Go",
+            ..context()
+        };
+        let mut contributions = Waterfall::single("base", 1.0);
+        apply_rules(&rules, &context, &mut contributions);
+        assert_eq!(contributions.total(), 2.0);
+    }
+
+    #[test]
+    fn test_repeated_nots() {
+        let source = format!("if {}title equals \"a\" then * 2", "not ".repeat(200_000));
+        let rules = parse_rules(&source).unwrap();
+        assert_eq!(
+            rules,
+            parse_rules("if title equals \"a\" then * 2").unwrap()
+        );
+        let odd = format!("if {}title equals \"a\" then * 2", "not ".repeat(3));
+        assert_eq!(
+            parse_rules(&odd).unwrap(),
+            parse_rules("if not title equals \"a\" then * 2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_rules("if domain ends_with \".edu\" then").is_err());
+        assert!(parse_rules("if host equals \"a\" then * 2").is_err());
+        assert!(parse_rules("if domain equals a then * 2").is_err());
+        assert!(parse_rules("if domain equals \"a then * 2").is_err());
+        assert!(parse_rules("if domain equals \"a\" then * 2 extra").is_err());
+        assert_eq!(
+            parse_rules("if title equals \"a\" then * 2\nif title equals \"b then * 2")
+                .unwrap_err(),
+            "Rule 2: Unterminated string"
+        );
+        let huge = format!("if domain equals \"a\" then * 1{}", "0".repeat(40));
+        assert!(parse_rules(&huge).is_err());
+    }
+}
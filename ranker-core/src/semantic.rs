@@ -0,0 +1,172 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use serde::{Deserialize, Serialize};
+
+// How to compare embeddings. Use whichever metric the embedding model was
+// trained for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimilarityMetric {
+    // The dot product of the L2 normalized vectors, in [-1, 1]
+    #[default]
+    Cosine,
+    // The raw dot product. Models trained for it encode meaning in the vector
+    // length, so the vectors must not be normalized.
+    Dot,
+    // 1 / (1 + d) for the euclidean distance d, in (0, 1]
+    Euclidean,
+}
+
+// Embeddings for the semantic path, stored as one contiguous buffer with a row
+// per search result in the order the results were added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Embeddings {
+    dim: usize,
+    data: Vec<f32>,
+}
+
+impl Embeddings {
+    pub fn new(data: Vec<f32>, dim: usize) -> Result<Embeddings, String> {
+        if dim == 0 || !data.len().is_multiple_of(dim) {
+            return Err(format!(
+                "Embedding buffer of length {} is not a whole number of rows of dimension {}",
+                data.len(),
+                dim
+            ));
+        }
+        check_finite(&data)?;
+        Ok(Embeddings { dim, data })
+    }
+
+    // The length of each row, or 0 if no embeddings were given
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn row(&self, index: usize) -> Option<&[f32]> {
+        if self.dim == 0 {
+            return None;
+        }
+        self.data.get(index * self.dim..(index + 1) * self.dim)
+    }
+
+    // Remove the row at `index`, if there is one, moving later rows up by one
+    pub fn remove_row(&mut self, index: usize) {
+        if self.row(index).is_some() {
+            self.data.drain(index * self.dim..(index + 1) * self.dim);
+        }
+    }
+}
+
+// NaN or infinite values would make every similarity NaN
+pub fn check_finite(embedding: &[f32]) -> Result<(), String> {
+    match embedding.iter().position(|value| !value.is_finite()) {
+        Some(position) => Err(format!(
+            "Embedding value {} at position {} is not finite",
+            embedding[position], position
+        )),
+        None => Ok(()),
+    }
+}
+
+pub const HASHED_EMBEDDING_DIM: usize = 256;
+const MAX_EMBEDDING_TOKENS: usize = 512;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// A crude bag-of-words embedding using the hashing trick: each token adds +1
+// or -1 to a dimension chosen by its hash. The sign, also taken from the hash,
+// makes collisions cancel out rather than accumulate.
+pub fn hashed_embedding(text: &str, dim: usize) -> Vec<f32> {
+    let mut embedding = vec![0.0; dim];
+    if dim == 0 {
+        return embedding;
+    }
+    let options = TokenizeOptions {
+        max_tokens: Some(MAX_EMBEDDING_TOKENS),
+        ..TokenizeOptions::default()
+    };
+    for token in tokenize(text, &options) {
+        let hash = fnv1a(token.text.as_bytes());
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        embedding[(hash % dim as u64) as usize] += sign;
+    }
+    embedding
+}
+
+pub fn similarity(metric: SimilarityMetric, a: &[f32], b: &[f32]) -> f32 {
+    match metric {
+        SimilarityMetric::Cosine => cosine_similarity(a, b),
+        SimilarityMetric::Dot => a.iter().zip(b).map(|(x, y)| x * y).sum(),
+        SimilarityMetric::Euclidean => {
+            let distance = a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt();
+            1.0 / (1.0 + distance)
+        }
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_rows() {
+        let embeddings = Embeddings::new(vec![1.0, 0.0, 0.0, 1.0], 2).unwrap();
+        assert_eq!(embeddings.row(1), Some(&[0.0, 1.0][..]));
+        assert_eq!(embeddings.row(2), None);
+        assert_eq!(Embeddings::default().row(0), None);
+        assert!(Embeddings::new(vec![1.0, 0.0, 0.0], 2).is_err());
+        assert_eq!(
+            Embeddings::new(vec![1.0, f32::NAN], 2).unwrap_err(),
+            "Embedding value NaN at position 1 is not finite"
+        );
+        assert!(Embeddings::new(vec![f32::INFINITY, 0.0], 2).is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), 1.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_hashed_embedding() {
+        let query = hashed_embedding("rust language", HASHED_EMBEDDING_DIM);
+        let related = hashed_embedding("The Rust programming language", HASHED_EMBEDDING_DIM);
+        let unrelated = hashed_embedding("Cooking pasta", HASHED_EMBEDDING_DIM);
+        assert_eq!(query.len(), HASHED_EMBEDDING_DIM);
+        assert!(cosine_similarity(&query, &related) > cosine_similarity(&query, &unrelated));
+        assert_eq!(hashed_embedding("Rust", 16), hashed_embedding("rust", 16));
+    }
+
+    #[test]
+    fn test_similarity_metrics() {
+        let (a, b) = ([3.0, 0.0], [1.0, 0.0]);
+        assert_eq!(similarity(SimilarityMetric::Cosine, &a, &b), 1.0);
+        assert_eq!(similarity(SimilarityMetric::Dot, &a, &b), 3.0);
+        assert_eq!(similarity(SimilarityMetric::Euclidean, &a, &b), 1.0 / 3.0);
+        assert_eq!(similarity(SimilarityMetric::Euclidean, &a, &a), 1.0);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::RankerConfig;
+use crate::metadata::ResultMetadata;
+use crate::waterfall::Contribution;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Bump when the bundle format changes, so old bundles can be recognised
+pub const BUNDLE_VERSION: u32 = 1;
+
+// A ranked SERP with what's needed to rank its results again, so a user can
+// attach it to a bug report. Embeddings, host reputation, curations, score
+// rules and the model itself aren't included, only the model's version.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SerpBundle {
+    pub version: u32,
+    // The `api_version` of the ranker that made the bundle
+    pub api_version: String,
+    pub query: String,
+    pub profile: String,
+    pub config: RankerConfig,
+    // A hash of `config`, to tell at a glance whether two bundles were ranked
+    // with the same configuration
+    pub config_hash: String,
+    pub model_version: Option<String>,
+    // The results in the order they were added
+    pub results: Vec<BundledResult>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundledResult {
+    pub url: String,
+    pub title: String,
+    pub extract: String,
+    pub metadata: ResultMetadata,
+    // The position in the ranking, or None if the result was left out, e.g.
+    // as a duplicate
+    pub position: Option<usize>,
+    pub score: f32,
+    pub features: BTreeMap<String, f32>,
+    pub contributions: Vec<Contribution>,
+}
+
+// FNV-1a of the text in hex, which unlike `DefaultHasher` is the same in
+// every build
+pub fn stable_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// The hash of the config as JSON
+pub fn config_hash(config: &RankerConfig) -> String {
+    stable_hash(&serde_json::to_string(config).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_hash_changes_with_config() {
+        let config = RankerConfig::default();
+        assert_eq!(config_hash(&config), config_hash(&config.clone()));
+        assert_eq!(config_hash(&config).len(), 16);
+        let other = RankerConfig {
+            title_weight: 5.0,
+            ..RankerConfig::default()
+        };
+        assert_ne!(config_hash(&config), config_hash(&other));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::page_type::PageType;
+use crate::urls;
+use crate::ScoredResult;
+use serde::{Deserialize, Serialize};
+
+// Which results a slot rule applies to. Results must match every field that
+// is set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlotSelector {
+    pub page_type: Option<PageType>,
+    // Results from these domains or their subdomains
+    pub domains: Vec<String>,
+}
+
+impl SlotSelector {
+    fn matches(&self, scored: &ScoredResult) -> bool {
+        if self
+            .page_type
+            .is_some_and(|page_type| page_type != scored.result.page_type)
+        {
+            return false;
+        }
+        if self.domains.is_empty() {
+            return true;
+        }
+        let (host, _, _) = urls::domain_and_path(scored.result.match_url());
+        self.domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+// A constraint on which results may appear in the top of the ranking
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SlotRule {
+    // At most `max` selected results in the top `top`. The rest move down to
+    // just below the top.
+    AtMost {
+        selector: SlotSelector,
+        max: usize,
+        top: usize,
+    },
+    // At least one selected result in the top `top`, as long as one scores
+    // at least `min_score_ratio` times the highest score. It takes the last
+    // place in the top.
+    AtLeastOne {
+        selector: SlotSelector,
+        top: usize,
+        min_score_ratio: f32,
+    },
+}
+
+// Re-order the ranked results to satisfy each rule in turn
+pub fn apply_slot_rules(scored_results: &mut Vec<ScoredResult>, rules: &[SlotRule]) {
+    for rule in rules {
+        match rule {
+            SlotRule::AtMost { selector, max, top } => {
+                let mut demoted = Vec::new();
+                let mut count = 0;
+                let mut i = 0;
+                while i < (*top).min(scored_results.len()) {
+                    if selector.matches(&scored_results[i]) {
+                        if count == *max {
+                            demoted.push(scored_results.remove(i));
+                            continue;
+                        }
+                        count += 1;
+                    }
+                    i += 1;
+                }
+                let position = (*top).min(scored_results.len());
+                scored_results.splice(position..position, demoted);
+            }
+            SlotRule::AtLeastOne {
+                selector,
+                top,
+                min_score_ratio,
+            } => {
+                let top = (*top).min(scored_results.len());
+                if top == 0 || scored_results[..top].iter().any(|s| selector.matches(s)) {
+                    continue;
+                }
+                // The list isn't always ordered by score, e.g. after hysteresis
+                let top_score = scored_results
+                    .iter()
+                    .map(|scored| scored.score)
+                    .fold(f32::NEG_INFINITY, f32::max);
+                let min_score = top_score * min_score_ratio;
+                let promoted = scored_results[top..]
+                    .iter()
+                    .position(|scored| selector.matches(scored) && scored.score >= min_score);
+                if let Some(offset) = promoted {
+                    let scored = scored_results.remove(top + offset);
+                    scored_results.insert(top - 1, scored);
+                }
+            }
+        }
+    }
+}
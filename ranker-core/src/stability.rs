@@ -0,0 +1,36 @@
+// Kendall's tau between two orderings of the same items, given as the
+// position of each item in the first and second ordering. 1.0 means the
+// order is unchanged and -1.0 that it is reversed. None if there are fewer
+// than two items to compare.
+pub fn kendall_tau(positions: &[(usize, usize)]) -> Option<f32> {
+    let mut num_pairs = 0;
+    let mut score = 0i64;
+    for (i, (before_a, after_a)) in positions.iter().enumerate() {
+        for (before_b, after_b) in &positions[i + 1..] {
+            num_pairs += 1;
+            if (before_a < before_b) == (after_a < after_b) {
+                score += 1;
+            } else {
+                score -= 1;
+            }
+        }
+    }
+    if num_pairs == 0 {
+        None
+    } else {
+        Some(score as f32 / num_pairs as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kendall_tau() {
+        assert_eq!(kendall_tau(&[(0, 0), (1, 1), (2, 2)]), Some(1.0));
+        assert_eq!(kendall_tau(&[(0, 2), (1, 1), (2, 0)]), Some(-1.0));
+        assert_eq!(kendall_tau(&[(0, 1), (1, 0), (2, 2)]), Some(1.0 / 3.0));
+        assert_eq!(kendall_tau(&[(0, 0)]), None);
+    }
+}
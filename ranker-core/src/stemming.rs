@@ -0,0 +1,39 @@
+use rust_stemmers::{Algorithm, Stemmer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// The English stem of a lowercase word, e.g. "run" for "running"
+pub fn stem(word: &str) -> Cow<'_, str> {
+    static STEMMER: OnceLock<Stemmer> = OnceLock::new();
+    STEMMER
+        .get_or_init(|| Stemmer::create(Algorithm::English))
+        .stem(word)
+}
+
+// The query terms by their stem, so a word matched through its stem can be
+// traced back to the term it stands for. Multi-word terms aren't stemmed.
+pub fn term_stems(terms: &[String]) -> HashMap<String, String> {
+    terms
+        .iter()
+        .filter(|term| !term.contains(' '))
+        .map(|term| (stem(term).into_owned(), term.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("searches"), "search");
+        assert_eq!(stem("search"), "search");
+
+        let terms = vec!["searches".to_string(), "new york".to_string()];
+        let stems = term_stems(&terms);
+        assert_eq!(stems.get("search").map(String::as_str), Some("searches"));
+        assert_eq!(stems.len(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+const ENGLISH: [&str; 32] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "in", "is", "it", "of", "on", "or", "that", "the", "this", "to", "was", "what", "when",
+    "where", "which", "why", "with",
+];
+const GERMAN: [&str; 24] = [
+    "aber", "auf", "das", "dass", "den", "der", "die", "ein", "eine", "einen", "im", "ist", "mit",
+    "nicht", "oder", "sich", "sie", "und", "von", "wie", "was", "wer", "zu", "zum",
+];
+const FRENCH: [&str; 24] = [
+    "au", "aux", "avec", "ce", "comment", "dans", "de", "des", "du", "elle", "en", "est", "et",
+    "il", "la", "le", "les", "ou", "par", "pas", "pour", "que", "qui", "un",
+];
+const SPANISH: [&str; 24] = [
+    "a", "al", "como", "con", "de", "del", "el", "en", "es", "la", "las", "lo", "los", "o", "para",
+    "por", "que", "se", "su", "un", "una", "unos", "y", "qué",
+];
+
+// The stopwords of a language by its ISO 639-1 code, or none if the language
+// isn't known, which disables stopwords
+pub fn stopwords(language: &str) -> &'static [&'static str] {
+    match language {
+        "en" => &ENGLISH,
+        "de" => &GERMAN,
+        "fr" => &FRENCH,
+        "es" => &SPANISH,
+        _ => &[],
+    }
+}
+
+// The query terms that are stopwords, or none if every term is one, since
+// then the stopwords are all there is to match
+pub fn query_stopwords(terms: &[String], language: &str) -> HashSet<String> {
+    let stopwords = stopwords(language);
+    let is_stopword = |term: &&String| stopwords.contains(&term.as_str());
+    if terms.iter().all(|term| is_stopword(&term)) {
+        return HashSet::new();
+    }
+    terms.iter().filter(is_stopword).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_stopwords() {
+        let terms = |query: &str| query.split(' ').map(str::to_string).collect::<Vec<_>>();
+        let stopwords = query_stopwords(&terms("how to make a website"), "en");
+        assert_eq!(stopwords.len(), 3);
+        assert!(stopwords.contains("to"));
+        assert!(query_stopwords(&terms("what is it"), "en").is_empty());
+        assert!(query_stopwords(&terms("how to"), "en").is_empty());
+        assert!(query_stopwords(&terms("how to make"), "").is_empty());
+        assert_eq!(query_stopwords(&terms("wie man kocht"), "de").len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+// Text processing shared by matching, highlighting and the features:
+// tokenization, folding of plurals, units and hyphenation, and truncation
+pub mod plurals;
+pub mod snippet;
+pub mod tokenizer;
+pub mod units;
+
+use regex::Regex;
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+// Shorten the string to at most `max_length` bytes without slicing within a
+// character
+pub fn shorten_string(s: &str, max_length: usize) -> &str {
+    if s.len() <= max_length {
+        return s;
+    }
+
+    let mut end = max_length;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+// Find the spans of text matching the query, as UTF-16 offsets
+pub fn highlight_spans(query_regex: &Regex, text: &str, fold_plurals: bool) -> Vec<(u32, u32)> {
+    let (mut text_lower, mut offsets) = tokenizer::normalize_with_offsets(text);
+    if fold_plurals {
+        let (folded, folded_offsets) = plurals::fold_plurals(&text_lower, &offsets);
+        text_lower = folded;
+        offsets = folded_offsets;
+    }
+    query_regex
+        .find_iter(&text_lower)
+        .map(|m| {
+            let offset = |index: usize| {
+                let offset = tokenizer::utf16_offset(text, offsets[index]);
+                u32::try_from(offset).unwrap_or(u32::MAX)
+            };
+            (offset(m.start()), offset(m.end()))
+        })
+        .collect()
+}
+
+// The text as the query regex is matched against it: lowercased, with
+// apostrophes, units and hyphenation normalized
+pub fn matchable_text(text: &str, fold_plurals: bool) -> String {
+    let (mut text, offsets) = tokenizer::normalize_with_offsets(text);
+    if fold_plurals {
+        text = plurals::fold_plurals(&text, &offsets).0;
+    }
+    let text = units::normalize_units(&text).into_owned();
+    tokenizer::add_joined_hyphenations(&text).into_owned()
+}
+
+pub fn word_regex() -> &'static Regex {
+    static WORD_REGEX: OnceLock<Regex> = OnceLock::new();
+    WORD_REGEX.get_or_init(|| Regex::new(r"\w+").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten_string() {
+        assert_eq!(shorten_string("rust", 10), "rust");
+        assert_eq!(shorten_string("rustacean", 4), "rust");
+        assert_eq!(shorten_string("Über", 1), "");
+        assert_eq!(shorten_string("Über", 2), "Ü");
+    }
+
+    #[test]
+    fn test_highlight_spans() {
+        let regex = Regex::new(r"\brust\b").unwrap();
+        let spans = highlight_spans(&regex, "Über Rust", false);
+        assert_eq!(spans, vec![(5, 9)]);
+
+        let regex = Regex::new(r"\bdont\b").unwrap();
+        let spans = highlight_spans(&regex, "Don't panic", false);
+        assert_eq!(spans, vec![(0, 5)]);
+
+        let regex = Regex::new(r"\bcity\b").unwrap();
+        let spans = highlight_spans(&regex, "Cities", true);
+        assert_eq!(spans, vec![(0, 6)]);
+    }
+
+    #[test]
+    fn test_matchable_text() {
+        assert_eq!(matchable_text("Don't PANIC", false), "dont panic");
+        assert_eq!(matchable_text("Cities", true), "city");
+        let words = word_regex()
+            .find_iter("rust, wasm!")
+            .map(|word| word.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(words, vec!["rust", "wasm"]);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use std::borrow::Cow;
+
+// Words ending in "s" that aren't plurals, or whose singular the rules below
+// would get wrong
+const EXCEPTIONS: [&str; 24] = [
+    "always", "analysis", "basis", "bias", "bonus", "canvas", "chaos", "crisis", "gas", "has",
+    "lens", "news", "perhaps", "physics", "plus", "series", "species", "status", "this", "thus",
+    "towards", "was", "whereas", "yes",
+];
+
+// The singular of an English plural, using a few suffix rules rather than a
+// full stemmer. Expects lowercase text.
+pub fn singular(word: &str) -> Cow<'_, str> {
+    if word.len() <= 3
+        || !word.ends_with('s')
+        || word.ends_with("ss")
+        || word.ends_with("us")
+        || word.ends_with("is")
+        || EXCEPTIONS.contains(&word)
+    {
+        return Cow::Borrowed(word);
+    }
+    if let Some(stem) = word.strip_suffix("ies") {
+        if stem.len() > 1 {
+            return Cow::Owned(format!("{}y", stem));
+        }
+    }
+    for suffix in ["sses", "ches", "shes", "xes"].iter() {
+        if word.ends_with(suffix) {
+            return Cow::Borrowed(&word[..word.len() - 2]);
+        }
+    }
+    Cow::Borrowed(&word[..word.len() - 1])
+}
+
+// Replace every word in the text with its singular. `offsets` gives the
+// offset in the original text of each byte of `text` plus one for the end,
+// and is mapped the same way for the result, so matches can still be
+// highlighted.
+pub fn fold_plurals(text: &str, offsets: &[usize]) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut folded_offsets = Vec::with_capacity(offsets.len());
+    let mut last_end = 0;
+    let options = TokenizeOptions {
+        lowercase: false,
+        max_tokens: None,
+    };
+    for token in tokenize(text, &options) {
+        folded.push_str(&text[last_end..token.start]);
+        folded_offsets.extend_from_slice(&offsets[last_end..token.start]);
+        let word = singular(&token.text);
+        // Bytes past the part shared with the original word come from the
+        // start of the replaced suffix
+        let shared = word
+            .bytes()
+            .zip(token.text.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        folded.push_str(&word);
+        folded_offsets.extend((0..word.len()).map(|i| offsets[token.start + i.min(shared)]));
+        last_end = token.end;
+    }
+    folded.push_str(&text[last_end..]);
+    folded_offsets.extend_from_slice(&offsets[last_end..]);
+    (folded, folded_offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_singular() {
+        assert_eq!(singular("cities"), "city");
+        assert_eq!(singular("boxes"), "box");
+        assert_eq!(singular("churches"), "church");
+        assert_eq!(singular("classes"), "class");
+        assert_eq!(singular("laptops"), "laptop");
+        assert_eq!(singular("class"), "class");
+        assert_eq!(singular("status"), "status");
+        assert_eq!(singular("analysis"), "analysis");
+        assert_eq!(singular("series"), "series");
+        assert_eq!(singular("its"), "its");
+        assert_eq!(singular("ties"), "tie");
+    }
+
+    #[test]
+    fn test_fold_plurals() {
+        let text = "big cities!";
+        let offsets = (0..=text.len()).collect::<Vec<usize>>();
+        let (folded, offsets) = fold_plurals(text, &offsets);
+        assert_eq!(folded, "big city!");
+        assert_eq!(offsets, vec![0, 1, 2, 3, 4, 5, 6, 7, 10, 11]);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use std::collections::HashSet;
+
+const ELLIPSIS: &str = "…";
+
+// Find the byte offset just after the last sentence ending in `text`, where a
+// sentence ends with '.', '!' or '?' followed by whitespace or the end of `rest`
+fn last_sentence_end(text: &str, rest: &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        let next = match chars.peek() {
+            Some((_, next)) => Some(*next),
+            None => rest.chars().next(),
+        };
+        if next.map(char::is_whitespace).unwrap_or(true) {
+            end = Some(i + c.len_utf8());
+        }
+    }
+    end
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+// Shorten text to at most `max_length` bytes for display. Prefer to cut at the
+// end of a sentence, as long as that keeps at least half the allowed length,
+// otherwise cut at the end of a word and add an ellipsis.
+pub fn trim_snippet(text: &str, max_length: usize) -> String {
+    let text = text.trim();
+    if text.len() <= max_length {
+        return text.to_string();
+    }
+
+    let end = floor_char_boundary(text, max_length);
+    if let Some(sentence_end) = last_sentence_end(&text[..end], &text[end..]) {
+        if sentence_end >= max_length / 2 {
+            return text[..sentence_end].to_string();
+        }
+    }
+
+    let end = floor_char_boundary(text, max_length.saturating_sub(ELLIPSIS.len()));
+    let prefix = &text[..end];
+    let word_end = if text[end..].starts_with(char::is_whitespace) {
+        end
+    } else {
+        prefix.rfind(char::is_whitespace).unwrap_or(end)
+    };
+    let trimmed = prefix[..word_end].trim_end_matches(|c: char| !c.is_alphanumeric());
+    format!("{}{}", trimmed, ELLIPSIS)
+}
+
+// Words shorter than this are too common to say whether a title and extract
+// are about the same thing
+const MIN_OVERLAP_WORD_LENGTH: usize = 3;
+
+// The proportion of distinct title words that also appear in the extract. A
+// title that matches well but has nothing in common with its extract is a
+// common clickbait pattern. Returns 1.0 when either is empty, as there is
+// nothing to compare.
+pub fn title_extract_overlap(title: &str, extract: &str) -> f32 {
+    let words = |text: &str| {
+        tokenize(text, &TokenizeOptions::default())
+            .into_iter()
+            .map(|token| token.text)
+            .filter(|word| word.chars().count() >= MIN_OVERLAP_WORD_LENGTH)
+            .collect::<HashSet<String>>()
+    };
+    let title_words = words(title);
+    let extract_words = words(extract);
+    if title_words.is_empty() || extract_words.is_empty() {
+        return 1.0;
+    }
+    let num_shared = title_words.intersection(&extract_words).count();
+    num_shared as f32 / title_words.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_extract_overlap() {
+        let title = "Rust programming language";
+        assert_eq!(
+            title_extract_overlap(title, "Rust is a programming language"),
+            1.0
+        );
+        assert_eq!(title_extract_overlap(title, "Win a free phone today"), 0.0);
+        assert_eq!(
+            title_extract_overlap(title, "The language of love"),
+            1.0 / 3.0
+        );
+        assert_eq!(title_extract_overlap(title, ""), 1.0);
+    }
+
+    #[test]
+    fn short_text_is_unchanged() {
+        assert_eq!(trim_snippet(" A short text ", 50), "A short text");
+    }
+
+    #[test]
+    fn trims_to_sentence_end() {
+        let text = "The first sentence is here. The second sentence is much longer.";
+        assert_eq!(trim_snippet(text, 40), "The first sentence is here.");
+    }
+
+    #[test]
+    fn trims_to_word_end_without_a_sentence() {
+        let text = "A single sentence without any break in it at all";
+        assert_eq!(trim_snippet(text, 20), "A single sentence…");
+        assert!(trim_snippet(text, 20).len() <= 20);
+    }
+}
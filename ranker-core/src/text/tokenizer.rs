@@ -0,0 +1,209 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TokenizeOptions {
+    pub lowercase: bool,
+    // Stop after this many tokens, to bound the work done on pathological text
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for TokenizeOptions {
+    fn default() -> Self {
+        TokenizeOptions {
+            lowercase: true,
+            max_tokens: None,
+        }
+    }
+}
+
+// A token with the byte offsets of its span in the original text
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Split text into runs of word characters, matching the `\b` word boundaries
+// used by the query regex
+pub fn tokenize(text: &str, options: &TokenizeOptions) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let max_tokens = options.max_tokens.unwrap_or(usize::MAX);
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        if tokens.len() >= max_tokens {
+            break;
+        }
+        match (start, is_token_char(c)) {
+            (None, true) => start = Some(i),
+            (Some(token_start), false) => {
+                let span = &text[token_start..i];
+                let text = if options.lowercase {
+                    span.to_lowercase()
+                } else {
+                    span.to_string()
+                };
+                tokens.push(Token {
+                    text,
+                    start: token_start,
+                    end: i,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+// Follow each hyphenated word with its joined form, e.g. "e-mail" becomes
+// "e-mail email", so that text tokenizes to both the split and joined
+// variants. Applied to both the query and the result text so "email" and
+// "e-mail" match each other.
+pub fn add_joined_hyphenations(text: &str) -> Cow<'_, str> {
+    static HYPHENATED_REGEX: OnceLock<Regex> = OnceLock::new();
+    let hyphenated_regex = HYPHENATED_REGEX.get_or_init(|| Regex::new(r"\w+(?:-\w+)+").unwrap());
+    hyphenated_regex.replace_all(text, |captures: &Captures| {
+        format!("{} {}", &captures[0], captures[0].replace('-', ""))
+    })
+}
+
+// Convert a byte offset into `text` to an offset in UTF-16 code units, which
+// is what JavaScript uses to index strings
+pub fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    text[..byte_offset].encode_utf16().count()
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '\u{2019}'
+}
+
+// Drop apostrophes inside or at the end of words, along with a possessive
+// "'s", so "Rust's" becomes "Rust" and "don't" becomes "dont". Also returns
+// the byte offset in the original text of each byte of the result (plus one
+// for the end of the string), since the result can be shorter, and
+// lowercasing can change the length of a character.
+fn normalize(text: &str, lowercase: bool) -> (String, Vec<usize>) {
+    let mut normalized = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    let mut chars = text.char_indices();
+    let mut previous = None;
+    while let Some((i, c)) = chars.next() {
+        if is_apostrophe(c) && previous.is_some_and(is_token_char) {
+            let mut rest = chars.clone().map(|(_, c)| c);
+            if matches!(rest.next(), Some('s') | Some('S'))
+                && !rest.next().is_some_and(is_token_char)
+            {
+                chars.next();
+            }
+            previous = Some(c);
+            continue;
+        }
+        if lowercase {
+            normalized.extend(c.to_lowercase());
+        } else {
+            normalized.push(c);
+        }
+        offsets.resize(normalized.len(), i);
+        previous = Some(c);
+    }
+    offsets.push(text.len());
+    (normalized, offsets)
+}
+
+// Lowercase text and drop apostrophes, as the matcher sees it, along with the
+// offset in the original text of each byte of the result
+pub fn normalize_with_offsets(text: &str) -> (String, Vec<usize>) {
+    normalize(text, true)
+}
+
+pub fn strip_apostrophes(text: &str) -> String {
+    normalize(text, false).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("Hello, wörld_1!", &TokenizeOptions::default());
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    text: "hello".into(),
+                    start: 0,
+                    end: 5
+                },
+                Token {
+                    text: "wörld_1".into(),
+                    start: 7,
+                    end: 15
+                },
+            ]
+        );
+        assert_eq!(utf16_offset("Hello, wörld_1!", 15), 14);
+    }
+
+    #[test]
+    fn test_tokenize_preserves_case() {
+        let options = TokenizeOptions {
+            lowercase: false,
+            ..TokenizeOptions::default()
+        };
+        let tokens = tokenize("Rust WASM", &options);
+        assert_eq!(tokens[1].text, "WASM");
+    }
+
+    #[test]
+    fn test_tokenize_max_tokens() {
+        let options = TokenizeOptions {
+            max_tokens: Some(2),
+            ..TokenizeOptions::default()
+        };
+        assert_eq!(tokenize(&"spam ".repeat(10_000), &options).len(), 2);
+    }
+
+    #[test]
+    fn test_add_joined_hyphenations() {
+        assert_eq!(
+            add_joined_hyphenations("Wi-Fi e-mail"),
+            "Wi-Fi WiFi e-mail email"
+        );
+        assert_eq!(
+            add_joined_hyphenations("state-of-the-art"),
+            "state-of-the-art stateoftheart"
+        );
+        assert_eq!(
+            add_joined_hyphenations("no hyphens - here"),
+            "no hyphens - here"
+        );
+    }
+
+    #[test]
+    fn test_normalize_with_offsets() {
+        let (lowercased, offsets) = normalize_with_offsets("İa");
+        assert_eq!(lowercased, "i\u{307}a");
+        assert_eq!(offsets, vec![0, 0, 0, 2, 3]);
+
+        let (normalized, offsets) = normalize_with_offsets("Rust's, don’t");
+        assert_eq!(normalized, "rust, dont");
+        assert_eq!(offsets, vec![0, 1, 2, 3, 6, 7, 8, 9, 10, 14, 15]);
+        assert_eq!(
+            strip_apostrophes("Users' 'quoted' it'sy"),
+            "Users 'quoted itsy"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+// Spellings of each unit, and the form they are all rewritten to
+const UNITS: [(&str, &[&str]); 12] = [
+    ("usd", &["usd", "dollar", "dollars"]),
+    ("gbp", &["gbp"]),
+    ("eur", &["eur", "euro", "euros"]),
+    ("kg", &["kg", "kgs", "kilogram", "kilograms"]),
+    ("g", &["g", "gram", "grams"]),
+    ("lb", &["lb", "lbs"]),
+    (
+        "km",
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+    ),
+    (
+        "cm",
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+    ),
+    (
+        "mm",
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+    ),
+    ("gb", &["gb", "gigabyte", "gigabytes"]),
+    ("tb", &["tb", "terabyte", "terabytes"]),
+    ("mb", &["mb", "megabyte", "megabytes"]),
+];
+
+fn currency_unit(symbol: &str) -> &'static str {
+    match symbol {
+        "£" => "gbp",
+        "€" => "eur",
+        _ => "usd",
+    }
+}
+
+fn canonical_unit(unit: &str) -> &'static str {
+    let unit = unit.to_lowercase();
+    UNITS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&unit.as_str()))
+        .map(|(canonical, _)| *canonical)
+        .unwrap_or("")
+}
+
+fn units_regex() -> &'static Regex {
+    static UNITS_REGEX: OnceLock<Regex> = OnceLock::new();
+    UNITS_REGEX.get_or_init(|| {
+        let spellings = UNITS
+            .iter()
+            .flat_map(|(_, spellings)| spellings.iter())
+            .copied()
+            .collect::<Vec<&str>>()
+            .join("|");
+        let pattern = format!(
+            r"(?i)([$£€])\s?(\d[\d,]*(?:\.\d+)?)|\b(\d[\d,]*(?:\.\d+)?)\s?({})\b",
+            spellings
+        );
+        Regex::new(&pattern).unwrap()
+    })
+}
+
+// Rewrite amounts with a currency or unit to a single form, so that "$500",
+// "500 USD" and "500usd" all become "500 usd". Applied to both the query and
+// the result text, so they match regardless of how each was written.
+pub fn normalize_units(text: &str) -> Cow<'_, str> {
+    units_regex().replace_all(text, |captures: &Captures| match captures.get(1) {
+        Some(symbol) => format!("{} {}", &captures[2], currency_unit(symbol.as_str())),
+        None => format!("{} {}", &captures[3], canonical_unit(&captures[4])),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_units() {
+        assert_eq!(normalize_units("$500"), "500 usd");
+        assert_eq!(normalize_units("500 USD"), "500 usd");
+        assert_eq!(normalize_units("500usd"), "500 usd");
+        assert_eq!(
+            normalize_units("only £1,299.99 today"),
+            "only 1,299.99 gbp today"
+        );
+        assert_eq!(normalize_units("a 2 Kilograms bag"), "a 2 kg bag");
+        assert_eq!(normalize_units("512GB SSD"), "512 gb SSD");
+        assert_eq!(normalize_units("500 dollars"), "500 usd");
+        assert_eq!(normalize_units("usd rates"), "usd rates");
+        assert_eq!(normalize_units("b2g deals"), "b2g deals");
+    }
+}
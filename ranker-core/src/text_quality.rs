@@ -0,0 +1,114 @@
+// Titles shorter or longer than this, in characters, are unlikely to be the
+// real title of a page
+const MIN_TITLE_CHARS: usize = 3;
+const MAX_TITLE_CHARS: usize = 150;
+// Text with less than this proportion of letters starts to be demoted, and
+// text with none counts as fully junky
+const MIN_NORMAL_ALPHABETIC_RATIO: f32 = 0.5;
+// Extracts with more than this proportion of punctuation start to be demoted
+const MAX_NORMAL_PUNCTUATION_RATIO: f32 = 0.15;
+// The punctuation ratio at which an extract counts as fully junky
+const JUNK_PUNCTUATION_RATIO: f32 = 0.5;
+// How much a title of an unlikely length counts towards the junk score
+const TITLE_LENGTH_JUNK: f32 = 0.5;
+
+// Signs of junk in the title and extract of a result, such as navigation
+// text, code or lists of numbers scraped in place of the page's content,
+// which don't depend on the query
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextQuality {
+    pub title_empty: bool,
+    // The title is between `MIN_TITLE_CHARS` and `MAX_TITLE_CHARS` long
+    pub title_length_in_range: bool,
+    // The number of characters in the extract, ignoring surrounding whitespace
+    pub extract_chars: f32,
+    // The proportion of non-whitespace characters in the title and extract
+    // that are letters, or 1.0 if both are empty so that a missing title
+    // isn't counted twice
+    pub alphabetic_ratio: f32,
+    // The proportion of non-whitespace extract characters that are
+    // punctuation
+    pub extract_punctuation_ratio: f32,
+}
+
+impl TextQuality {
+    // How junky the text looks, from 0.0 to 1.0. A short extract alone
+    // isn't junk, as the backend often has none.
+    pub fn junk_score(&self) -> f32 {
+        if self.title_empty {
+            return 1.0;
+        }
+        let title_length = if self.title_length_in_range {
+            0.0
+        } else {
+            TITLE_LENGTH_JUNK
+        };
+        let alphabetic = 1.0 - self.alphabetic_ratio / MIN_NORMAL_ALPHABETIC_RATIO;
+        let punctuation = (self.extract_punctuation_ratio - MAX_NORMAL_PUNCTUATION_RATIO)
+            / (JUNK_PUNCTUATION_RATIO - MAX_NORMAL_PUNCTUATION_RATIO);
+        title_length
+            .max(alphabetic)
+            .max(punctuation)
+            .clamp(0.0, 1.0)
+    }
+}
+
+// The number of non-whitespace, alphabetic and punctuation characters
+fn count_chars(text: &str) -> (usize, usize, usize) {
+    let mut counts = (0, 0, 0);
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        counts.0 += 1;
+        if c.is_alphabetic() {
+            counts.1 += 1;
+        } else if c.is_ascii_punctuation() {
+            counts.2 += 1;
+        }
+    }
+    counts
+}
+
+pub fn text_quality(title: &str, extract: &str) -> TextQuality {
+    let title_length = title.trim().chars().count();
+    let (num_title_chars, title_alphabetic, _) = count_chars(title);
+    let (num_extract_chars, extract_alphabetic, extract_punctuation) = count_chars(extract);
+    let num_chars = num_title_chars + num_extract_chars;
+    TextQuality {
+        title_empty: title_length == 0,
+        title_length_in_range: (MIN_TITLE_CHARS..=MAX_TITLE_CHARS).contains(&title_length),
+        extract_chars: extract.trim().chars().count() as f32,
+        alphabetic_ratio: if num_chars == 0 {
+            1.0
+        } else {
+            (title_alphabetic + extract_alphabetic) as f32 / num_chars as f32
+        },
+        extract_punctuation_ratio: extract_punctuation as f32 / num_extract_chars.max(1) as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junky_text_scores_higher() {
+        let normal = text_quality(
+            "Rust Programming Language",
+            "A language empowering everyone to build reliable and efficient software.",
+        );
+        assert!(normal.title_length_in_range);
+        assert_eq!(normal.junk_score(), 0.0);
+        assert_eq!(text_quality("Rust", "").junk_score(), 0.0);
+
+        assert!(text_quality("  ", "Some text").title_empty);
+        assert_eq!(text_quality("", "").junk_score(), 1.0);
+        assert_eq!(text_quality(&"Rust ".repeat(40), "").junk_score(), 0.5);
+
+        let numbers = text_quality("1234 5678", "0.12 0.34 0.56 0.78");
+        assert_eq!(numbers.alphabetic_ratio, 0.0);
+        assert_eq!(numbers.junk_score(), 1.0);
+
+        let navigation = text_quality("Home", "Home | About | Blog | Contact >> ...");
+        assert!(navigation.extract_punctuation_ratio > 0.15);
+        assert!(navigation.junk_score() > 0.0);
+    }
+}
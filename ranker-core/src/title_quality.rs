@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+
+// Titles with more than this proportion of punctuation start to be demoted
+const MAX_NORMAL_PUNCTUATION_RATIO: f32 = 0.15;
+// The punctuation ratio at which a title counts as fully spammy
+const SPAM_PUNCTUATION_RATIO: f32 = 0.5;
+// The number of emoji at which a title counts as fully spammy
+const SPAM_NUM_EMOJI: u8 = 3;
+// Keyword lists have at least this many segments sharing a word
+const MIN_KEYWORD_LIST_SEGMENTS: usize = 3;
+// Titles need at least this many letters to count as shouting in capitals,
+// so that acronyms like "NASA" don't
+const MIN_ALL_CAPS_LETTERS: usize = 8;
+// How spammy a title entirely in capitals counts as
+const ALL_CAPS_SPAM: f32 = 0.5;
+// Phrases of clickbait headline templates, in lowercase
+const CLICKBAIT_PHRASES: [&str; 12] = [
+    "you won't believe",
+    "you wont believe",
+    "what happened next",
+    "what happens next",
+    "will blow your mind",
+    "will shock you",
+    "one weird trick",
+    "doctors hate",
+    "you need to see",
+    "can't stop laughing",
+    "the reason why will",
+    "is going viral",
+];
+
+// Signs of SEO spam in how a title is formatted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TitleFormatting {
+    // The proportion of non-whitespace characters that are punctuation
+    pub punctuation_ratio: f32,
+    pub num_emoji: u8,
+    // Whether the title is a list of keyword variations separated by pipes,
+    // e.g. "cheap shoes | best shoes | buy shoes"
+    pub keyword_list: bool,
+    // Every letter is a capital, in a title too long to be an acronym
+    pub all_caps: bool,
+    // The title follows a clickbait template like "You won't believe..."
+    pub clickbait_template: bool,
+}
+
+impl TitleFormatting {
+    // How spammy the formatting looks, from 0.0 to 1.0
+    pub fn spam_score(&self) -> f32 {
+        let punctuation = (self.punctuation_ratio - MAX_NORMAL_PUNCTUATION_RATIO)
+            / (SPAM_PUNCTUATION_RATIO - MAX_NORMAL_PUNCTUATION_RATIO);
+        let emoji = self.num_emoji as f32 / SPAM_NUM_EMOJI as f32;
+        let keyword_list = if self.keyword_list { 1.0 } else { 0.0 };
+        let all_caps = if self.all_caps { ALL_CAPS_SPAM } else { 0.0 };
+        let clickbait = if self.clickbait_template { 1.0 } else { 0.0 };
+        punctuation
+            .max(emoji)
+            .max(keyword_list)
+            .max(all_caps)
+            .max(clickbait)
+            .clamp(0.0, 1.0)
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF)
+}
+
+fn is_keyword_list(title: &str) -> bool {
+    let segments = title.split(['|', '•']).collect::<Vec<&str>>();
+    if segments.len() < MIN_KEYWORD_LIST_SEGMENTS {
+        return false;
+    }
+    let mut segment_counts: HashMap<String, usize> = HashMap::new();
+    for segment in segments {
+        let words = segment
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= 3)
+            .map(str::to_lowercase)
+            .collect::<HashSet<String>>();
+        for word in words {
+            *segment_counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    segment_counts
+        .values()
+        .any(|&count| count >= MIN_KEYWORD_LIST_SEGMENTS)
+}
+
+// Only letters with case count, so titles in scripts without it never do
+fn is_all_caps(title: &str) -> bool {
+    let cased_letters = title
+        .chars()
+        .filter(|c| c.is_uppercase() || c.is_lowercase())
+        .collect::<Vec<char>>();
+    cased_letters.len() >= MIN_ALL_CAPS_LETTERS && cased_letters.iter().all(|c| c.is_uppercase())
+}
+
+fn is_clickbait_template(title: &str) -> bool {
+    let title = title.to_lowercase().replace('\u{2019}', "'");
+    CLICKBAIT_PHRASES
+        .iter()
+        .any(|phrase| title.contains(phrase))
+}
+
+pub fn title_formatting(title: &str) -> TitleFormatting {
+    let mut num_chars = 0;
+    let mut num_punctuation = 0;
+    let mut num_emoji = 0u8;
+    for c in title.chars().filter(|c| !c.is_whitespace()) {
+        num_chars += 1;
+        if c.is_ascii_punctuation() {
+            num_punctuation += 1;
+        } else if is_emoji(c) {
+            num_emoji = num_emoji.saturating_add(1);
+        }
+    }
+    TitleFormatting {
+        punctuation_ratio: num_punctuation as f32 / num_chars.max(1) as f32,
+        num_emoji,
+        keyword_list: is_keyword_list(title),
+        all_caps: is_all_caps(title),
+        clickbait_template: is_clickbait_template(title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_formatting() {
+        let normal = title_formatting("Rust (programming language) - Wikipedia");
+        assert_eq!(normal.spam_score(), 0.0);
+        assert!(!normal.keyword_list);
+        assert_eq!(title_formatting("Docs | Rust | Guide").spam_score(), 0.0);
+
+        let keywords = title_formatting("Cheap Shoes | Best Shoes | Buy Shoes Online");
+        assert!(keywords.keyword_list);
+        assert_eq!(keywords.spam_score(), 1.0);
+
+        let shouting = title_formatting("WOW!!! $$$ FREE!!!");
+        assert!(shouting.punctuation_ratio > 0.5);
+        assert_eq!(shouting.spam_score(), 1.0);
+
+        assert!(!title_formatting("NASA").all_caps);
+        assert!(!title_formatting("東京の天気予報と週間天気").all_caps);
+        let shouting = title_formatting("BREAKING NEWS FROM THE CITY");
+        assert!(shouting.all_caps);
+        assert_eq!(shouting.spam_score(), 0.5);
+
+        let clickbait = title_formatting("You Won\u{2019}t Believe What This Cat Did");
+        assert!(clickbait.clickbait_template);
+        assert_eq!(clickbait.spam_score(), 1.0);
+
+        let emoji = title_formatting("Best deals 🔥🔥");
+        assert_eq!(emoji.num_emoji, 2);
+        assert!(emoji.spam_score() > 0.6 && emoji.spam_score() < 0.7);
+    }
+}
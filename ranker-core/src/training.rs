@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+// Propensities are clipped to at least this, so that a click at a position
+// users rarely look at can't outweigh every other example
+const MIN_PROPENSITY: f32 = 0.05;
+
+// A SERP as logged by the frontend: the feature rows of `explain_all` with
+// which results were clicked
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct LoggedSerp {
+    pub query: String,
+    pub results: Vec<LoggedResult>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct LoggedResult {
+    pub position: usize,
+    pub features: Vec<f32>,
+    pub clicked: bool,
+    // The probability that a user looked at this position, from a click
+    // model or an intervention experiment. Defaults to 1 / (position + 1).
+    pub propensity: Option<f32>,
+    // Pinned by a curation rather than ranked, so its clicks say nothing
+    // about the ranking
+    #[serde(default)]
+    pub curated: bool,
+}
+
+impl LoggedResult {
+    fn propensity(&self) -> f32 {
+        self.propensity
+            .unwrap_or(1.0 / (self.position + 1) as f32)
+            .max(MIN_PROPENSITY)
+    }
+}
+
+// A clicked result preferred over one that wasn't clicked, weighted by the
+// inverse propensity of the click to correct for position bias
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TrainingPair {
+    pub query: String,
+    pub clicked: Vec<f32>,
+    pub skipped: Vec<f32>,
+    pub weight: f32,
+}
+
+// Pair every clicked result of each SERP with every result that wasn't
+// clicked. Curated results and SERPs without clicks give no pairs.
+pub fn sample_pairs(serps: &[LoggedSerp]) -> Vec<TrainingPair> {
+    let mut pairs = Vec::new();
+    for serp in serps {
+        let ranked = serp.results.iter().filter(|result| !result.curated);
+        let (clicked, skipped): (Vec<&LoggedResult>, Vec<&LoggedResult>) =
+            ranked.partition(|result| result.clicked);
+        for click in &clicked {
+            for skip in &skipped {
+                pairs.push(TrainingPair {
+                    query: serp.query.clone(),
+                    clicked: click.features.clone(),
+                    skipped: skip.features.clone(),
+                    weight: 1.0 / click.propensity(),
+                });
+            }
+        }
+    }
+    pairs
+}
+
+// Read a log of one `LoggedSerp` as JSON per line and write its training
+// pairs in the same way. Every feature row must have the same length.
+pub fn sample_training_data(log: &str) -> Result<String, String> {
+    let mut serps = Vec::new();
+    let mut num_features = None;
+    for (i, line) in log.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let serp: LoggedSerp =
+            serde_json::from_str(line).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        for result in &serp.results {
+            let expected = *num_features.get_or_insert(result.features.len());
+            if result.features.len() != expected {
+                return Err(format!(
+                    "Line {}: expected {} features, found {}",
+                    i + 1,
+                    expected,
+                    result.features.len()
+                ));
+            }
+        }
+        serps.push(serp);
+    }
+    Ok(sample_pairs(&serps)
+        .iter()
+        .map(|pair| serde_json::to_string(pair).unwrap())
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_are_paired_with_skips_by_inverse_propensity() {
+        let log = concat!(
+            r#"{"query": "rust", "results": ["#,
+            r#"{"position": 0, "features": [1, 0], "clicked": false},"#,
+            r#"{"position": 1, "features": [0, 1], "clicked": true},"#,
+            r#"{"position": 2, "features": [0, 0], "clicked": false, "curated": true}]}"#,
+            "\n\n",
+            r#"{"query": "go", "results": ["#,
+            r#"{"position": 0, "features": [1, 1], "clicked": true, "propensity": 0},"#,
+            r#"{"position": 1, "features": [0, 1], "clicked": false}]}"#,
+        );
+        let pairs = sample_training_data(log).unwrap();
+        let pairs = pairs.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            pairs,
+            vec![
+                r#"{"query":"rust","clicked":[0.0,1.0],"skipped":[1.0,0.0],"weight":2.0}"#,
+                r#"{"query":"go","clicked":[1.0,1.0],"skipped":[0.0,1.0],"weight":20.0}"#,
+            ]
+        );
+
+        let mismatched = log.replace("[0, 0]", "[0]");
+        assert_eq!(
+            sample_training_data(&mismatched).unwrap_err(),
+            "Line 1: expected 2 features, found 1"
+        );
+    }
+}
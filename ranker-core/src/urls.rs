@@ -0,0 +1,342 @@
+use crate::text::tokenizer::{tokenize, TokenizeOptions};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use url::Url;
+
+// Second level labels under which registrations happen, e.g. example.co.uk
+const SECOND_LEVEL_SUFFIXES: [&str; 8] = ["co", "com", "org", "net", "ac", "gov", "edu", "ltd"];
+
+// Query parameters that track clicks or sessions rather than pick the page,
+// besides those starting with "utm_"
+const TRACKING_PARAMS: [&str; 15] = [
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "sessionid",
+    "session_id",
+    "sid",
+    "jsessionid",
+    "phpsessid",
+];
+
+// Path segments of at least this many hex digits are taken to be IDs
+const MIN_HEX_ID_LENGTH: usize = 32;
+
+// Path segments split by hyphens into at least this many words are taken to
+// be stuffed with keywords, as are paths repeating a word this many times
+const MIN_STUFFED_WORDS: usize = 10;
+const MIN_REPEATED_WORDS: usize = 3;
+// Subdomains with at least this many digits, making up at least half of the
+// subdomain, are taken to be generated
+const MIN_SUBDOMAIN_DIGITS: usize = 3;
+
+// Top level domains cheap enough to register in bulk that most sites on them
+// are spam, for `RankerConfig::spam_tlds`
+pub const DEFAULT_SPAM_TLDS: [&str; 10] = [
+    "xyz", "top", "click", "loan", "work", "buzz", "tk", "ml", "ga", "cf",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct UrlFeatures {
+    pub scheme: String,
+    pub domain: String,
+    pub registrable_domain: String,
+    pub subdomain: String,
+    pub path_tokens: Vec<String>,
+    pub depth: usize,
+    pub filetype: Option<String>,
+    pub is_homepage: bool,
+}
+
+// Approximate the registrable domain without a full public suffix list: the
+// last two labels, or three when the second-to-last is a known second level
+// suffix under a country code (e.g. bbc.co.uk)
+pub fn registrable_domain(domain: &str) -> &str {
+    let labels = domain.split('.').collect::<Vec<&str>>();
+    let num_labels = if labels.len() >= 3
+        && labels[labels.len() - 1].len() == 2
+        && SECOND_LEVEL_SUFFIXES.contains(&labels[labels.len() - 2])
+    {
+        3
+    } else {
+        2
+    };
+    if labels.len() <= num_labels {
+        return domain;
+    }
+    let prefix_length: usize = labels[..labels.len() - num_labels]
+        .iter()
+        .map(|label| label.len() + 1)
+        .sum();
+    &domain[prefix_length..]
+}
+
+// Extract the host and path from a string that isn't a valid URL, e.g.
+// "example.com/page" or "https://exa mple.com/page", as best we can
+fn fallback_host_and_path(raw_url: &str) -> (String, String) {
+    static URL_REGEX: OnceLock<Regex> = OnceLock::new();
+    let url_regex = URL_REGEX.get_or_init(|| {
+        Regex::new(r"^\s*(?:[a-zA-Z][a-zA-Z0-9+.-]*:)?/*(?:[^/?#@]*@)?([^/?#:]*)(?::\d*)?([^?#]*)")
+            .unwrap()
+    });
+    match url_regex.captures(raw_url) {
+        Some(captures) => (
+            captures[1].trim().to_lowercase(),
+            captures[2].trim().to_string(),
+        ),
+        None => (String::new(), String::new()),
+    }
+}
+
+// The domain and path of the URL, falling back to extracting them with a
+// regex when the URL can't be parsed. Also returns whether parsing failed.
+pub fn domain_and_path(raw_url: &str) -> (String, String, bool) {
+    match Url::parse(raw_url) {
+        Ok(parsed_url) => (
+            parsed_url.domain().unwrap_or("").to_string(),
+            parsed_url.path().to_string(),
+            false,
+        ),
+        Err(_) => {
+            let (host, path) = fallback_host_and_path(raw_url);
+            (host, path, true)
+        }
+    }
+}
+
+// Whether the URL's scheme is https, without parsing the rest of it
+pub fn is_https(url: &str) -> bool {
+    url.split(':')
+        .next()
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https"))
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+// A session ID in the path, as a `;jsessionid=` style parameter, a UUID or a
+// long run of hex digits
+fn is_session_segment(segment: &str) -> bool {
+    let segment = segment.to_ascii_lowercase();
+    if let Some((_, parameter)) = segment.split_once(';') {
+        if let Some((key, _)) = parameter.split_once('=') {
+            return is_tracking_param(key);
+        }
+    }
+    let is_uuid = segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    let is_hex_id =
+        segment.len() >= MIN_HEX_ID_LENGTH && segment.chars().all(|c| c.is_ascii_hexdigit());
+    is_uuid || is_hex_id
+}
+
+// Whether the URL has tracking parameters or a session ID, which the
+// canonical URL of a page almost never does. False if it can't be parsed.
+pub fn has_tracking_noise(url: &str) -> bool {
+    let parsed_url = match Url::parse(url) {
+        Ok(parsed_url) => parsed_url,
+        Err(_) => return false,
+    };
+    parsed_url
+        .query_pairs()
+        .any(|(key, _)| is_tracking_param(&key))
+        || parsed_url
+            .path_segments()
+            .is_some_and(|mut segments| segments.any(is_session_segment))
+}
+
+fn is_keyword_stuffed(path: &str) -> bool {
+    let mut word_counts: HashMap<String, usize> = HashMap::new();
+    for segment in path.split('/') {
+        let segment = segment.rsplit_once('.').map_or(segment, |(name, _)| name);
+        let words = segment
+            .split('-')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>();
+        if words.len() >= MIN_STUFFED_WORDS {
+            return true;
+        }
+        for word in words.iter().filter(|word| word.len() >= 3) {
+            *word_counts.entry(word.to_lowercase()).or_insert(0) += 1;
+        }
+    }
+    word_counts
+        .values()
+        .any(|&count| count >= MIN_REPEATED_WORDS)
+}
+
+fn is_digit_heavy(subdomain: &str) -> bool {
+    let num_digits = subdomain.chars().filter(char::is_ascii_digit).count();
+    let num_chars = subdomain.chars().filter(|&c| c != '.').count();
+    num_digits >= MIN_SUBDOMAIN_DIGITS && num_digits * 2 >= num_chars
+}
+
+// How spammy the URL looks from 0.0 to 1.0: the proportion of signs of spam
+// it shows, out of a keyword-stuffed path, a subdomain made up largely of
+// digits, and a top level domain in `spam_tlds`. 0.0 if it can't be parsed.
+pub fn url_spam_score(url: &str, spam_tlds: &[String]) -> f32 {
+    let features = match parse_url_features(url) {
+        Some(features) => features,
+        None => return 0.0,
+    };
+    let (_, path, _) = domain_and_path(url);
+    let tld = features.domain.rsplit('.').next().unwrap_or("");
+    let signs = [
+        is_keyword_stuffed(&path),
+        is_digit_heavy(&features.subdomain),
+        spam_tlds
+            .iter()
+            .any(|spam_tld| spam_tld.eq_ignore_ascii_case(tld)),
+    ];
+    signs.iter().filter(|&&sign| sign).count() as f32 / signs.len() as f32
+}
+
+// The number of non-empty segments in a URL path, 0 for the homepage
+pub fn path_depth(path: &str) -> usize {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .count()
+}
+
+pub fn parse_url_features(url: &str) -> Option<UrlFeatures> {
+    let parsed_url = Url::parse(url).ok()?;
+    let domain = parsed_url.host_str().unwrap_or("").to_lowercase();
+    let registrable = registrable_domain(&domain).to_string();
+    let subdomain = domain
+        .strip_suffix(registrable.as_str())
+        .unwrap_or("")
+        .trim_end_matches('.')
+        .to_string();
+    let segments = parsed_url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect::<Vec<&str>>())
+        .unwrap_or_default();
+    let filetype = segments
+        .last()
+        .and_then(|segment| segment.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_lowercase())
+        .filter(|extension| !extension.is_empty());
+    let path_tokens = tokenize(parsed_url.path(), &TokenizeOptions::default())
+        .into_iter()
+        .map(|token| token.text)
+        .collect();
+
+    Some(UrlFeatures {
+        scheme: parsed_url.scheme().to_string(),
+        is_homepage: segments.is_empty() && parsed_url.query().is_none(),
+        depth: segments.len(),
+        domain,
+        registrable_domain: registrable,
+        subdomain,
+        path_tokens,
+        filetype,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain("en.wikipedia.org"), "wikipedia.org");
+        assert_eq!(registrable_domain("news.bbc.co.uk"), "bbc.co.uk");
+        assert_eq!(registrable_domain("github.com"), "github.com");
+    }
+
+    #[test]
+    fn test_domain_and_path_fallback() {
+        assert_eq!(
+            domain_and_path("https://example.com/a/b?c=d"),
+            ("example.com".to_string(), "/a/b".to_string(), false)
+        );
+        assert_eq!(
+            domain_and_path("Example.com/a/b"),
+            ("example.com".to_string(), "/a/b".to_string(), true)
+        );
+        assert_eq!(
+            domain_and_path("http://user@exa mple.com:80/page"),
+            ("exa mple.com".to_string(), "/page".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_is_https_and_path_depth() {
+        assert!(is_https("HTTPS://example.com/"));
+        assert!(!is_https("http://example.com/"));
+        assert!(!is_https("example.com/https"));
+        assert_eq!(path_depth("/"), 0);
+        assert_eq!(path_depth("/a//b/c/"), 3);
+    }
+
+    #[test]
+    fn test_has_tracking_noise() {
+        assert!(has_tracking_noise("https://example.com/?utm_source=feed"));
+        assert!(has_tracking_noise(
+            "https://example.com/page?id=3&FBCLID=abc"
+        ));
+        assert!(has_tracking_noise(
+            "https://example.com/cart;jsessionid=0A1B2C"
+        ));
+        assert!(has_tracking_noise(
+            "https://example.com/s/123e4567-e89b-12d3-a456-426614174000/page"
+        ));
+        assert!(has_tracking_noise(
+            "https://example.com/9f86d081884c7d659a2feaa0c55ad015/"
+        ));
+        assert!(!has_tracking_noise("https://example.com/page?id=3"));
+        assert!(!has_tracking_noise("https://example.com/deadbeef"));
+        assert!(!has_tracking_noise("not a url?utm_source=feed"));
+    }
+
+    #[test]
+    fn test_url_spam_score() {
+        let spam_tlds = DEFAULT_SPAM_TLDS.map(String::from);
+        let score = |url| url_spam_score(url, &spam_tlds);
+        assert_eq!(score("https://www.rust-lang.org/learn/get-started"), 0.0);
+        assert_eq!(
+            score("https://example.com/how-to-write-a-web-server-in-rust"),
+            0.0
+        );
+        assert_eq!(score("https://shop.example.xyz/"), 1.0 / 3.0);
+        assert_eq!(
+            score("https://cheap-shoes.example.com/cheap-shoes/best-shoes-shoes"),
+            1.0 / 3.0
+        );
+        assert_eq!(
+            score("https://a1b2c345.example.top/buy-cheap-shoes-boots-hats-bags-sale-now-uk-us"),
+            1.0
+        );
+        assert_eq!(score("not a url.xyz"), 0.0);
+    }
+
+    #[test]
+    fn test_parse_url_features() {
+        let features = parse_url_features("https://docs.rs/url/latest/Url.html").unwrap();
+        assert_eq!(features.scheme, "https");
+        assert_eq!(features.subdomain, "");
+        assert_eq!(features.path_tokens, vec!["url", "latest", "url", "html"]);
+        assert_eq!(features.depth, 3);
+        assert_eq!(features.filetype.as_deref(), Some("html"));
+        assert!(!features.is_homepage);
+
+        let features = parse_url_features("http://en.wikipedia.org/").unwrap();
+        assert_eq!(features.subdomain, "en");
+        assert!(features.is_homepage);
+        assert!(parse_url_features("not a url").is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::OnceLock;
+
+// Interior mutability for caches that are filled in by `&self` methods. Access
+// goes through closures so a borrow can never outlive the call that made it,
+// which means JS callers can't trigger a borrow error by interleaving calls.
+// Under wasm everything runs on one thread, so a `RefCell` is enough.
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct Cache<T>(std::cell::RefCell<T>);
+
+#[cfg(target_arch = "wasm32")]
+impl<T> Cache<T> {
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.0.borrow())
+    }
+
+    pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.0.borrow_mut())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct Cache<T>(std::sync::RwLock<T>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T> Cache<T> {
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.0.write().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+// Clocks in milliseconds, for hosts where `SystemTime` isn't available, such
+// as the browser. The wasm bindings install the JavaScript clocks at startup.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    pub now_ms: fn() -> f64,
+    // With the sub-millisecond precision benchmarks need
+    pub precise_now_ms: fn() -> f64,
+}
+
+static CLOCK: OnceLock<Clock> = OnceLock::new();
+
+// Use the clock for all later time measurements. Only the first clock set is
+// used.
+pub fn set_clock(clock: Clock) {
+    let _ = CLOCK.set(clock);
+}
+
+fn system_now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+// The current time in milliseconds, for measuring time budgets
+pub fn now_ms() -> f64 {
+    CLOCK
+        .get()
+        .map_or_else(system_now_ms, |clock| (clock.now_ms)())
+}
+
+// As `now_ms`, but with the sub-millisecond precision benchmarks need
+pub fn precise_now_ms() -> f64 {
+    CLOCK
+        .get()
+        .map_or_else(system_now_ms, |clock| (clock.precise_now_ms)())
+}
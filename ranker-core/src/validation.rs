@@ -0,0 +1,49 @@
+use serde::Serialize;
+use url::Url;
+
+// Longer extracts are assumed to be whole pages sent by mistake
+pub const MAX_VALID_EXTRACT_LENGTH: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IngestError {
+    pub url: String,
+    pub reason: String,
+}
+
+// Check a search result strictly before it is ingested, rather than silently
+// mangling it into shape
+pub fn validate_result(url: &str, title: &str, extract: &str) -> Result<(), String> {
+    let parsed_url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed_url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", parsed_url.scheme()));
+    }
+    if parsed_url.host_str().map(str::is_empty).unwrap_or(true) {
+        return Err("URL has no host".to_string());
+    }
+    if title.trim().is_empty() {
+        return Err("Empty title".to_string());
+    }
+    if extract.len() > MAX_VALID_EXTRACT_LENGTH {
+        return Err(format!(
+            "Extract is {} bytes, more than the maximum of {}",
+            extract.len(),
+            MAX_VALID_EXTRACT_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_result() {
+        assert!(validate_result("https://example.com/", "Example", "").is_ok());
+        assert!(validate_result("example.com", "Example", "").is_err());
+        assert!(validate_result("ftp://example.com/", "Example", "").is_err());
+        assert!(validate_result("https://example.com/", "  ", "").is_err());
+        let long_extract = "a".repeat(MAX_VALID_EXTRACT_LENGTH + 1);
+        assert!(validate_result("https://example.com/", "Example", &long_extract).is_err());
+    }
+}
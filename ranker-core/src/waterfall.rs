@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Add,
+    Multiply,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Contribution {
+    // What this step is, e.g. "title", "length_penalty" or "rule 2"
+    pub label: String,
+    pub operation: Operation,
+    pub value: f32,
+    // The score after this step
+    pub total: f32,
+}
+
+// The steps that built up a score, in order, starting from zero. The total
+// of the last step is the score.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Waterfall {
+    pub steps: Vec<Contribution>,
+}
+
+impl Waterfall {
+    pub fn single(label: &str, value: f32) -> Waterfall {
+        let mut waterfall = Waterfall::default();
+        waterfall.add(label, value);
+        waterfall
+    }
+
+    pub fn total(&self) -> f32 {
+        self.steps.last().map(|step| step.total).unwrap_or(0.0)
+    }
+
+    pub fn add(&mut self, label: &str, value: f32) {
+        let total = self.total() + value;
+        self.push(label, Operation::Add, value, total);
+    }
+
+    pub fn multiply(&mut self, label: &str, factor: f32) {
+        let total = self.total() * factor;
+        self.push(label, Operation::Multiply, factor, total);
+    }
+
+    fn push(&mut self, label: &str, operation: Operation, value: f32, total: f32) {
+        self.steps.push(Contribution {
+            label: label.to_string(),
+            operation,
+            value,
+            total,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waterfall_accumulates_steps() {
+        let mut waterfall = Waterfall::single("title", 2.0);
+        waterfall.add("extract", 1.0);
+        waterfall.multiply("length_penalty", 0.5);
+        assert_eq!(waterfall.total(), 1.5);
+        assert_eq!(waterfall.steps.len(), 3);
+        assert_eq!(waterfall.steps[1].total, 3.0);
+        assert_eq!(Waterfall::default().total(), 0.0);
+    }
+}
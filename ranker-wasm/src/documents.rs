@@ -0,0 +1,55 @@
+use crate::{js_error, to_js};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const DOCUMENT_RANKER_TYPES: &'static str = r#"
+export interface DocumentField {
+    name: string;
+    weight: number;
+}
+
+export interface RankedDocument {
+    // The index the document was added at
+    index: number;
+    score: number;
+}
+"#;
+
+// Ranks documents made of any fields the caller defines, see
+// `ranker_core::DocumentRanker`
+#[wasm_bindgen]
+pub struct DocumentRanker(ranker_core::DocumentRanker);
+
+#[wasm_bindgen]
+impl DocumentRanker {
+    // Construct a ranker for documents with the fields in an array of
+    // `DocumentField`s
+    #[wasm_bindgen(constructor)]
+    pub fn new(query: &str, fields: JsValue) -> Result<DocumentRanker, JsValue> {
+        let fields = serde_wasm_bindgen::from_value(fields)?;
+        let ranker = ranker_core::DocumentRanker::new(query, fields).map_err(js_error)?;
+        Ok(DocumentRanker(ranker))
+    }
+
+    // Add a document as an object from field names to their text. Fields left
+    // out are empty.
+    pub fn add_document(&mut self, document: JsValue) -> Result<(), JsValue> {
+        let document: BTreeMap<String, String> = serde_wasm_bindgen::from_value(document)?;
+        self.0.add_document(document).map_err(js_error)
+    }
+
+    // The `RankedDocument`s from best to worst. Documents matching no query
+    // term are left out.
+    pub fn rank(&self) -> JsValue {
+        to_js(&self.0.ranked_documents())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
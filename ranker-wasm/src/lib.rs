@@ -0,0 +1,596 @@
+mod documents;
+mod output;
+mod utils;
+
+use output::{JsInitInfo, JsRankOutput};
+use ranker_core::{CandidateResult, Clock, Model, RankerConfig, ResultMetadata, RewriteConfig};
+use ranker_core::{TokenizeOptions, UrlFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+pub use documents::DocumentRanker;
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    ranker_core::set_clock(Clock {
+        now_ms: js_sys::Date::now,
+        precise_now_ms: utils::performance_now,
+    });
+}
+
+fn to_js<T: Serialize + ?Sized>(value: &T) -> JsValue {
+    serde_wasm_bindgen::to_value(value).unwrap()
+}
+
+fn js_error(error: String) -> JsValue {
+    JsValue::from_str(&error)
+}
+
+// The message of an exception thrown by a JavaScript callback
+fn callback_error(error: JsValue) -> String {
+    error.as_string().unwrap_or_else(|| format!("{:?}", error))
+}
+
+// The version of the JavaScript API, following semver. It's the ranking API
+// version recorded in SERP bundles, so bundles match the build that made them.
+#[wasm_bindgen]
+pub fn api_version() -> String {
+    ranker_core::api_version()
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct InitConfig {
+    // Log panics to the console with their messages, at some cost in code
+    // size, when built with the `console_error_panic_hook` feature
+    log_panics: bool,
+}
+
+impl Default for InitConfig {
+    fn default() -> Self {
+        InitConfig { log_panics: true }
+    }
+}
+
+// The score reported for results pinned by a curation
+#[wasm_bindgen]
+pub fn curated_score() -> f32 {
+    ranker_core::CURATED_SCORE
+}
+
+#[derive(Serialize)]
+struct InitInfo {
+    version: String,
+    // The optional cargo features the package was built with
+    features: Vec<&'static str>,
+    #[serde(flatten)]
+    build: ranker_core::BuildInfo,
+}
+
+fn init_info() -> InitInfo {
+    let mut features = Vec::new();
+    if cfg!(feature = "console_error_panic_hook") {
+        features.push("console_error_panic_hook");
+    }
+    if cfg!(feature = "collation") {
+        features.push("collation");
+    }
+    InitInfo {
+        version: api_version(),
+        features,
+        build: ranker_core::build_info(),
+    }
+}
+
+// Set up the ranker with an optional `InitConfig`, returning what this build
+// supports for the frontend to log and check compatibility against, e.g. the
+// model types it can load and the versions of the formats it exports
+#[wasm_bindgen]
+pub fn init(config: JsValue) -> Result<JsInitInfo, JsValue> {
+    let config: InitConfig = if config.is_undefined() || config.is_null() {
+        InitConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config)?
+    };
+    if config.log_panics {
+        utils::set_panic_hook();
+    }
+    Ok(to_js(&init_info()).unchecked_into())
+}
+
+// The name, description and whether it can be clamped of every feature that
+// explanations, clamps and models refer to
+#[wasm_bindgen]
+pub fn feature_schema() -> JsValue {
+    to_js(ranker_core::FEATURES)
+}
+
+// Whether a result, an object of its `url`, `title`, `extract` and optional
+// `metadata`, scores at least `threshold` for the query, for alerting on new
+// results that match saved searches. Each query is compiled once and reused,
+// so checking many saved queries against a few results is cheap.
+#[wasm_bindgen]
+pub fn matches_threshold(query: &str, result: JsValue, threshold: f32) -> Result<bool, JsValue> {
+    let result: CandidateResult = serde_wasm_bindgen::from_value(result)?;
+    Ok(ranker_core::result_matches(query, &result, threshold))
+}
+
+// Time ranking `n_results` synthetic results for the query, `iterations`
+// times, to measure performance on real devices. Returns the `n_results`,
+// `iterations` and the `min`, `p50`, `p90`, `p99`, `max` and `mean` in
+// milliseconds of each of `add`, `rank` and `total`.
+#[wasm_bindgen]
+pub fn bench(query: &str, n_results: usize, iterations: usize) -> JsValue {
+    to_js(&ranker_core::run_bench(query, n_results, iterations))
+}
+
+// The query as the ranker will use it, trimmed, lowercased and with operators
+// and bangs removed, so the frontend can display and cache the same form
+#[wasm_bindgen]
+pub fn normalize_query(raw: &str) -> String {
+    ranker_core::normalize_query(raw)
+}
+
+// The names accepted by `Ranker::new_with_profile`
+#[wasm_bindgen]
+pub fn profile_names() -> JsValue {
+    to_js(&ranker_core::PROFILE_NAMES)
+}
+
+#[derive(Serialize)]
+struct JsToken {
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+// Tokenize text exactly as the ranker does. Offsets are in UTF-16 code units
+// so they can be used directly to slice JavaScript strings.
+#[wasm_bindgen]
+pub fn tokenize(text: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: TokenizeOptions = if options.is_undefined() || options.is_null() {
+        TokenizeOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+    let tokens = ranker_core::tokenize(text, &options)
+        .into_iter()
+        .map(|token| JsToken {
+            start: ranker_core::utf16_offset(text, token.start),
+            end: ranker_core::utf16_offset(text, token.end),
+            text: token.text,
+        })
+        .collect::<Vec<JsToken>>();
+    Ok(serde_wasm_bindgen::to_value(&tokens)?)
+}
+
+// A crude bag-of-words embedding of the text using the hashing trick, the
+// same as the ranker uses when no embeddings are provided
+#[wasm_bindgen]
+pub fn hashed_embedding(text: &str, dim: usize) -> Vec<f32> {
+    ranker_core::hashed_embedding(text, dim)
+}
+
+// Parse the URL into the features the ranker uses, or null if it is invalid
+#[wasm_bindgen]
+pub fn parse_url_features(url: &str) -> JsValue {
+    to_js(&ranker_core::parse_url_features(url))
+}
+
+// Flags that pin ranking behavior, see `ranker_core::BehaviorFlag`. Combine
+// them into the bitset passed to `Ranker::set_flags`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BehaviorFlag {
+    LegacyScoring = 1,
+    Bm25Scoring = 2,
+}
+
+// How to compare embeddings, see `ranker_core::SimilarityMetric`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimilarityMetric {
+    Cosine,
+    Dot,
+    Euclidean,
+}
+
+impl From<SimilarityMetric> for ranker_core::SimilarityMetric {
+    fn from(metric: SimilarityMetric) -> Self {
+        match metric {
+            SimilarityMetric::Cosine => ranker_core::SimilarityMetric::Cosine,
+            SimilarityMetric::Dot => ranker_core::SimilarityMetric::Dot,
+            SimilarityMetric::Euclidean => ranker_core::SimilarityMetric::Euclidean,
+        }
+    }
+}
+
+// The ranker for JavaScript. Each method converts its arguments from JS
+// values and its result back, and otherwise does as the method of
+// `ranker_core::Ranker` with the same name.
+#[wasm_bindgen]
+pub struct Ranker(ranker_core::Ranker);
+
+#[wasm_bindgen]
+impl Ranker {
+    pub fn new(query: &str) -> Ranker {
+        Ranker(ranker_core::Ranker::new(query))
+    }
+
+    pub fn new_with_flags(query: &str, flags: u32) -> Ranker {
+        Ranker(ranker_core::Ranker::new_with_flags(query, flags))
+    }
+
+    pub fn new_with_profile(query: &str, profile: &str) -> Result<Ranker, JsValue> {
+        let ranker = ranker_core::Ranker::new_with_profile(query, profile).map_err(js_error)?;
+        Ok(Ranker(ranker))
+    }
+
+    pub fn get_query_terms(&self) -> JsValue {
+        to_js(&self.0.get_query_terms())
+    }
+
+    pub fn get_query_rewrite(&self) -> JsValue {
+        to_js(self.0.get_query_rewrite())
+    }
+
+    // Configure the query rewrite with an object of the `stages` to run in
+    // order (from "operators", "lowercase", "stopwords", "spelling" and
+    // "synonyms"), `spelling_corrections`, `synonyms`, `fold_plurals`,
+    // `entities`, `stemming`, `stopword_language`, `segment_terms` and
+    // `max_edit_distance`, then rewrite the query again
+    pub fn set_query_rewrite(&mut self, config: JsValue) -> Result<(), JsValue> {
+        let config: RewriteConfig = serde_wasm_bindgen::from_value(config)?;
+        self.0.set_query_rewrite(config);
+        Ok(())
+    }
+
+    pub fn set_entities(&mut self, entities: Vec<String>) {
+        self.0.set_entities(entities);
+    }
+
+    // Load a synonym table, an object from each term to the terms it should
+    // also match, e.g. `{"js": ["javascript"]}`
+    pub fn load_synonyms(&mut self, synonyms: JsValue) -> Result<(), JsValue> {
+        let synonyms: BTreeMap<String, Vec<String>> = serde_wasm_bindgen::from_value(synonyms)?;
+        self.0.load_synonyms(synonyms);
+        Ok(())
+    }
+
+    // Configure which URLs are filtered out, with an object of
+    // `blocked_domains`, `safe_search` and `allowed_schemes`
+    pub fn set_url_filter(&mut self, filter: JsValue) -> Result<(), JsValue> {
+        let filter: UrlFilter = serde_wasm_bindgen::from_value(filter)?;
+        self.0.set_url_filter(filter);
+        Ok(())
+    }
+
+    // Whether each URL would get past the URL filter, as an array of booleans
+    pub fn filter_urls(&self, urls: Vec<String>) -> JsValue {
+        to_js(&self.0.filter_urls(&urls))
+    }
+
+    pub fn add_search_result(&mut self, url: &str, title: &str, extract: &str) -> bool {
+        self.0.add_search_result(url, title, extract)
+    }
+
+    pub fn add_search_result_with_type(
+        &mut self,
+        url: &str,
+        title: &str,
+        extract: &str,
+        page_type: &str,
+    ) -> bool {
+        self.0
+            .add_search_result_with_type(url, title, extract, page_type)
+    }
+
+    // As `add_search_result`, with an object of optional details about the
+    // result: a `page_type` hint, the `final_url` it redirects to along with
+    // the `redirect_depth`, the crawler's `noindex_detected` and
+    // `soft404_suspected` flags, whether the page is `paywalled`, and its
+    // `published_date` in ISO 8601 format
+    pub fn add_search_result_with_metadata(
+        &mut self,
+        url: &str,
+        title: &str,
+        extract: &str,
+        metadata: JsValue,
+    ) -> Result<bool, JsValue> {
+        let metadata: ResultMetadata = serde_wasm_bindgen::from_value(metadata)?;
+        Ok(self
+            .0
+            .add_search_result_with_metadata(url, title, extract, &metadata))
+    }
+
+    pub fn remove_search_result(&mut self, index: usize) -> bool {
+        self.0.remove_search_result(index)
+    }
+
+    // "general", "navigational", "research" or "shopping"
+    pub fn intent(&self) -> String {
+        self.0.intent().name().to_string()
+    }
+
+    pub fn results_revision(&self) -> u32 {
+        self.0.results_revision()
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.0.revision()
+    }
+
+    // The callback is called by `notify_results_changed`, so it can rank,
+    // but mustn't add or remove results
+    pub fn on_results_changed(&mut self, callback: js_sys::Function) {
+        self.0.on_results_changed(move |revision| {
+            callback
+                .call1(&JsValue::NULL, &JsValue::from(revision))
+                .map(|_| ())
+                .map_err(callback_error)
+        });
+    }
+
+    pub fn notify_results_changed(&self) -> Result<(), JsValue> {
+        self.0.notify_results_changed().map_err(js_error)
+    }
+
+    pub fn on_rank_start(&mut self, callback: js_sys::Function) {
+        self.0.on_rank_start(move || {
+            callback.call0(&JsValue::NULL).map(|_| ()).map_err(callback_error)
+        });
+    }
+
+    pub fn on_rank_complete(&mut self, callback: js_sys::Function) {
+        self.0.on_rank_complete(move |stats| {
+            callback
+                .call1(&JsValue::NULL, &to_js(stats))
+                .map(|_| ())
+                .map_err(callback_error)
+        });
+    }
+
+    pub fn set_strict_validation(&mut self, strict: bool) {
+        self.0.set_strict_validation(strict);
+    }
+
+    pub fn get_ingest_errors(&self) -> JsValue {
+        to_js(self.0.get_ingest_errors())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn set_score_urls(&mut self, score_urls: bool) {
+        self.0.set_score_urls(score_urls);
+    }
+
+    pub fn set_prefer_open_access(&mut self, prefer_open_access: bool) {
+        self.0.set_prefer_open_access(prefer_open_access);
+    }
+
+    pub fn set_locale(&mut self, locale: &str) {
+        self.0.set_locale(locale);
+    }
+
+    // Replace the whole configuration with an object of `RankerConfig` fields,
+    // e.g. `{title_weight: 5, match_exponent: 1.5, length_penalty: 0.02}`.
+    // Missing fields take their default values.
+    pub fn set_config(&mut self, config: JsValue) -> Result<(), JsValue> {
+        let config: RankerConfig = serde_wasm_bindgen::from_value(config)?;
+        self.0.set_config(config).map_err(js_error)
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        self.0.set_flags(flags);
+    }
+
+    // The rank methods throw the first exception thrown by the `on_rank_start`
+    // or `on_rank_complete` callback while ranking
+    pub fn rank(&self) -> Result<JsRankOutput, JsValue> {
+        self.rank_output(self.0.rank())
+    }
+
+    pub fn rank_available(&self) -> Result<JsRankOutput, JsValue> {
+        self.rank_output(self.0.rank_available())
+    }
+
+    pub fn export_serp(&self) -> String {
+        self.0.export_serp()
+    }
+
+    pub fn rank_with_explanations(&self) -> Result<JsRankOutput, JsValue> {
+        self.rank_output(self.0.rank_with_explanations())
+    }
+
+    pub fn rank_with_budget(&self, budget_ms: f64) -> Result<JsRankOutput, JsValue> {
+        self.rank_output(self.0.rank_with_budget(budget_ms))
+    }
+
+    pub fn set_query_embedding(&mut self, embedding: Vec<f32>) -> Result<(), JsValue> {
+        self.0.set_query_embedding(embedding).map_err(js_error)
+    }
+
+    pub fn set_hashed_embeddings(&mut self, enabled: bool) {
+        self.0.set_hashed_embeddings(enabled);
+    }
+
+    pub fn set_feature_clamp(&mut self, name: &str, min: f32, max: f32) -> Result<(), JsValue> {
+        self.0.set_feature_clamp(name, min, max).map_err(js_error)
+    }
+
+    pub fn set_similarity_metric(&mut self, metric: SimilarityMetric) {
+        self.0.set_similarity_metric(metric.into());
+    }
+
+    // Set the embeddings of all search results at once from a single buffer,
+    // e.g. a Float32Array
+    pub fn set_result_embeddings(
+        &mut self,
+        embeddings: Vec<f32>,
+        dim: usize,
+    ) -> Result<(), JsValue> {
+        self.0
+            .set_result_embeddings(embeddings, dim)
+            .map_err(js_error)
+    }
+
+    // Load a model to score with in place of the default formula. The model
+    // is an object with `type` "linear" or "trees", the `features` it uses
+    // (names such as "title_score") and optional `standardization` with the
+    // `means` and `stds` of each feature from training. Linear models have
+    // `weights` and a `bias`, tree ensembles have `trees` and a `base_score`.
+    // Models of `type` "ensemble" average the scores of their `members`,
+    // models of the other types over the same features.
+    pub fn load_model(&mut self, model: JsValue) -> Result<Option<String>, JsValue> {
+        let model: Model = serde_wasm_bindgen::from_value(model)?;
+        self.0.load_model(model).map_err(js_error)
+    }
+
+    pub fn unload_model(&mut self) {
+        self.0.unload_model();
+    }
+
+    pub fn set_score_rules(&mut self, rules: &str) -> Result<(), JsValue> {
+        self.0.set_score_rules(rules).map_err(js_error)
+    }
+
+    pub fn pin_result(&mut self, url: &str) {
+        self.0.pin_result(url);
+    }
+
+    pub fn exclude_result(&mut self, url: &str) {
+        self.0.exclude_result(url);
+    }
+
+    pub fn remove_curation(&mut self, url: &str) {
+        self.0.remove_curation(url);
+    }
+
+    pub fn export_curations(&self) -> Vec<u8> {
+        self.0.export_curations()
+    }
+
+    pub fn import_curations(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.0.import_curations(bytes).map_err(js_error)
+    }
+
+    pub fn record_skip(&mut self, url: &str) {
+        self.0.record_skip(url);
+    }
+
+    pub fn record_bounce(&mut self, url: &str) {
+        self.0.record_bounce(url);
+    }
+
+    pub fn export_host_reputation(&self) -> Vec<u8> {
+        self.0.export_host_reputation()
+    }
+
+    pub fn import_host_reputation(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.0.import_host_reputation(bytes).map_err(js_error)
+    }
+
+    pub fn load_domain_popularity(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.0.load_domain_popularity(bytes).map_err(js_error)
+    }
+
+    pub fn load_domain_authority(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.0.load_domain_authority(bytes).map_err(js_error)
+    }
+
+    pub fn more_like_this(&self, index: usize) -> JsRankOutput {
+        output::to_js(&self.0.more_like_this(index))
+    }
+
+    pub fn rank_step(&self, n: usize) -> bool {
+        self.0.rank_step(n)
+    }
+
+    pub fn rank_stepped(&self) -> Result<JsRankOutput, JsValue> {
+        self.rank_output(self.0.rank_stepped())
+    }
+
+    pub fn reset_steps(&self) {
+        self.0.reset_steps();
+    }
+
+    // The stats of the most recent ranking, or undefined if nothing has been
+    // ranked yet
+    pub fn last_rank_stats(&self) -> JsValue {
+        to_js(&self.0.last_rank_stats())
+    }
+
+    // Explain the score of the search result that was added at `index` in the
+    // most recent ranking, or return undefined if it hasn't been ranked
+    pub fn explain(&self, index: usize) -> JsValue {
+        to_js(&self.0.explain(index))
+    }
+
+    pub fn serp_quality(&self) -> JsValue {
+        to_js(&self.0.serp_quality())
+    }
+
+    pub fn explain_all(&self, top_k: Option<usize>) -> JsValue {
+        to_js(&self.0.explain_all(top_k))
+    }
+
+    pub fn render_explanation_text(&self) -> String {
+        self.0.render_explanation_text()
+    }
+
+    // The highlights of the search result at `index`, or undefined if there
+    // is no result at `index`
+    pub fn highlights(&self, index: usize) -> JsValue {
+        to_js(&self.0.highlights(index))
+    }
+}
+
+impl Ranker {
+    fn rank_output(&self, output: ranker_core::RankOutput) -> Result<JsRankOutput, JsValue> {
+        match self.0.take_rank_callback_error() {
+            Some(error) => Err(js_error(error)),
+            None => Ok(output::to_js(&output)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_info_reports_the_build() {
+        let info = init_info();
+        assert_eq!(info.version, ranker_core::api_version());
+        assert_eq!(
+            info.features.contains(&"console_error_panic_hook"),
+            cfg!(feature = "console_error_panic_hook")
+        );
+        assert!(info.build.model_types.contains(&"ensemble"));
+    }
+
+    #[test]
+    fn behavior_flags_match_the_core() {
+        let flags = [
+            (
+                BehaviorFlag::LegacyScoring,
+                ranker_core::BehaviorFlag::LegacyScoring,
+            ),
+            (
+                BehaviorFlag::Bm25Scoring,
+                ranker_core::BehaviorFlag::Bm25Scoring,
+            ),
+        ];
+        for (flag, core_flag) in flags {
+            assert_eq!(flag as u32, core_flag as u32);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use ranker_core::RankOutput;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(typescript_custom_section)]
+const RANK_OUTPUT_TYPES: &'static str = r#"
+export interface SearchResult {
+    url: string;
+    title: string;
+    extract: string;
+    snippet: string;
+    page_type?: "article" | "product" | "recipe" | "video";
+    // The published date, or a date found in the URL or title: "YYYY",
+    // "YYYY-MM" or "YYYY-MM-DD"
+    date?: string;
+    // Where the URL redirects to, when the backend passed it
+    final_url?: string;
+    // Set when the backend flagged the page as paywalled, for a badge
+    paywalled?: true;
+}
+
+// Feature values by name, as listed by `feature_schema()`
+export type FeatureValues = Record<string, number>;
+
+export interface FeatureDefinition {
+    name: string;
+    description: string;
+    clampable: boolean;
+}
+
+export interface Contribution {
+    label: string;
+    operation: "add" | "multiply";
+    value: number;
+    total: number;
+}
+
+export interface Explanation {
+    position: number;
+    score: number;
+    features: FeatureValues;
+    // How the score was built up from zero, ending at `score` unless curated
+    contributions: Contribution[];
+    // Pinned by a curation, with a fixed `score` of `curated_score()`
+    curated: boolean;
+}
+
+export interface ExplainedResult {
+    // The index the result was added at
+    index: number;
+    position: number;
+    score: number;
+    // One value per name in `ExplainAll.feature_names`
+    features: number[];
+    contributions: Contribution[];
+    curated: boolean;
+}
+
+export interface ExplainAll {
+    feature_names: string[];
+    // In ranked order
+    results: ExplainedResult[];
+}
+
+export interface Highlights {
+    title: [number, number][];
+    extract: [number, number][];
+}
+
+export interface RankStats {
+    num_results: number;
+    num_matched: number;
+    top_score: number;
+    no_matches: boolean;
+    num_saturated: number;
+    num_url_parse_failures: number;
+    num_ingest_truncations: number;
+    truncated: boolean;
+    // Kendall's tau against the previous ranking, from -1 to 1
+    stability?: number;
+}
+
+// Returned by `Ranker.serp_quality()` for the most recent ranking
+export interface SerpQuality {
+    top_score: number;
+    // The mean proportion of query terms matched by each of the top three
+    top_term_coverage: number;
+    // The proportion of the top ten results on distinct domains
+    diversity: number;
+    // From 0 to 1, the coverage discounted by up to half for low diversity
+    score: number;
+}
+
+export interface FilteredResult {
+    result: SearchResult;
+    // Why the result was left out, e.g. "Filtered by safe search"
+    reason: string;
+}
+
+export interface RankOutput {
+    intent: "general" | "navigational" | "research" | "shopping";
+    profile: string;
+    truncated: boolean;
+    partial: boolean;
+    revision: number;
+    results: SearchResult[];
+    scores: number[];
+    // How safe the URL of each result looks. "blocked" results would be
+    // dropped by the URL filter if they were added now.
+    safety: ("ok" | "suspicious-host" | "blocked")[];
+    stats: RankStats;
+    explanations?: FeatureValues[];
+    // The standard deviation of each score across the members of an
+    // ensemble model, when score_confidence is configured
+    score_stds?: number[];
+    // The results the URL filter or drop_low_coverage left out, when
+    // return_filtered is configured, so the user can choose to see them
+    filtered?: FilteredResult[];
+}
+"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const INIT_TYPES: &'static str = r#"
+export interface InitConfig {
+    // Log panics to the console, defaults to true
+    log_panics?: boolean;
+}
+
+export interface InitInfo {
+    // The version of the JavaScript API, as `api_version()`
+    version: string;
+    // The optional cargo features the package was built with
+    features: string[];
+    // Whether this is a debug build, which ranks much slower
+    debug: boolean;
+    // The model `type`s that `load_model` accepts
+    model_types: string[];
+    // Changes whenever a feature is added, removed or renamed
+    feature_schema_version: string;
+    // The version of each format that is exported and loaded
+    formats: {
+        serp_bundle: number;
+        curations: number;
+        host_reputation: number;
+        domain_popularity: number;
+        domain_authority: number;
+    };
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "RankOutput")]
+    pub type JsRankOutput;
+
+    #[wasm_bindgen(typescript_type = "InitInfo")]
+    pub type JsInitInfo;
+}
+
+pub fn to_js(output: &RankOutput) -> JsRankOutput {
+    serde_wasm_bindgen::to_value(output)
+        .unwrap()
+        .unchecked_into()
+}
@@ -8,3 +8,11 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    // The browser may coarsen `performance.now()`, but far less than
+    // `Date.now()`, so it is precise enough for benchmarks
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    pub fn performance_now() -> f64;
+}
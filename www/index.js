@@ -1,5 +1,6 @@
-import * as wasm from "ranker";
+import * as wasm from "ranker-wasm";
 
+console.log("Ranker", wasm.init());
 
 
 // Get the search input
@@ -20,22 +21,27 @@ searchButton.addEventListener("click", async (e) => {
     for (const result of data.results) {
       ranker.add_search_result(result.url, result.title, result.extract);
     }
+    // Show what we have so far while the remaining terms are fetched
+    showResults(ranker.rank_available());
   }
   let rankedData = ranker.rank();
-  console.log(rankedData);
+  console.log("Ranked with profile", rankedData.profile, "for intent", rankedData.intent);
+  showResults(rankedData);
+});
 
-  // Insert into the output div
+// Insert into the output div
+function showResults(rankedData) {
   let outputDiv = document.getElementById("output");
   outputDiv.innerHTML = "";
-  rankedData.forEach((result) => {
+  rankedData.results.forEach((result) => {
     let div = document.createElement("div");
     div.innerHTML = `
       <a href="${result.url}">${result.url}</a>
       <h3>${result.title}</h3>
-      <p>${result.extract}</p>
+      <p>${result.snippet}</p>
       <br><br>
     `;
     outputDiv.appendChild(div);
   });
-});
+}
 